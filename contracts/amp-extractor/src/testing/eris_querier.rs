@@ -1,7 +1,7 @@
 use std::vec;
 
 use cosmwasm_std::{to_binary, Decimal, QuerierResult, Uint128};
-use eris::hub::{QueryMsg, StateResponse};
+use eris::hub::{QueryMsg, StateResponse, StorageMetrics};
 
 use super::helpers::err_unsupported_query;
 
@@ -15,12 +15,18 @@ impl ErisQuerier {
         match &query {
             QueryMsg::State {} => Ok(to_binary(&StateResponse {
                 total_ustake: Uint128::zero(),
-                total_uluna: Uint128::zero(),
+                total_utoken: Uint128::zero(),
                 exchange_rate: self.exchange_rate,
                 unlocked_coins: vec![],
                 unbonding: Uint128::zero(),
                 available: Uint128::zero(),
-                tvl_uluna: Uint128::zero(),
+                tvl_utoken: Uint128::zero(),
+                liquidity_buffer: Uint128::zero(),
+                liquidity_buffer_config: None,
+                storage_metrics: StorageMetrics {
+                    previous_batches: 0,
+                    unbond_requests: 0,
+                },
             })
             .into())
             .into(),
@@ -109,10 +109,23 @@ fn _vote() -> StdResult<()> {
             router_ref,
             eris::hub::ExecuteMsg::UpdateConfig {
                 protocol_fee_contract: None,
-                protocol_reward_fee: None,
-                delegation_strategy: None,
                 allow_donations: None,
                 vote_operator: Some(helper.base.prop_gauges.get_address_string()),
+                liquidity_buffer: None,
+                remove_slashed_validators: None,
+                insert_swap_routes: None,
+                delete_swap_routes: None,
+                validator_checks: None,
+                guardian: None,
+                fee_receivers: None,
+                auto_maintenance: None,
+                keeper_reward: None,
+                prop_gauges: None,
+                dust_cleanup: None,
+                split_bond: None,
+                remove_liquidity_buffer: None,
+                protocol_reward_fee: None,
+                delegation_strategy: None,
                 epoch_period: None,
                 unbond_period: None,
             },
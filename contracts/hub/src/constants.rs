@@ -2,9 +2,45 @@ use cosmwasm_std::Decimal;
 
 pub const CONTRACT_NAME: &str = "nicoteen-staking-hub";
 pub const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Staking denom used when none is set at instantiate
 pub const CONTRACT_DENOM: &str = "uluna";
+/// Delegation shortfalls up to this amount are treated as rounding of the staking module
+pub const SLASHING_TOLERANCE: u128 = 10;
+/// The staking module allows at most this many unbonding entries per delegator/validator pair
+pub const MAX_UNBONDING_ENTRIES: u32 = 7;
+/// Max amount of users whose unbonded utoken can be withdrawn in one transaction
+pub const MAX_WITHDRAW_FOR_USERS: usize = 30;
+/// Max amount of batches that are pruned in one transaction
+pub const MAX_PRUNE_BATCHES: u32 = 30;
+/// Reply id of the stake token instantiation
+pub const REGISTER_STAKE_TOKEN_REPLY_ID: u64 = 1;
+/// Reply id of the auto-maintenance callback
+pub const AUTO_MAINTENANCE_REPLY_ID: u64 = 2;
+/// Reply id of a reward swap
+pub const SWAP_REPLY_ID: u64 = 3;
+/// Reply ids of the single auto-maintenance tasks
+pub const SUBMIT_BATCH_REPLY_ID: u64 = 4;
+pub const RECONCILE_REPLY_ID: u64 = 5;
+pub const HARVEST_REPLY_ID: u64 = 6;
+/// Reply ids from this one on identify the fee receiver whose wake-up message failed
+pub const FEE_RECEIVER_REPLY_ID_START: u64 = 100;
 
 pub fn get_reward_fee_cap() -> Decimal {
     // 10% max reward fee
     Decimal::from_ratio(10_u128, 100_u128)
 }
+
+pub fn get_max_spread_cap() -> Decimal {
+    // 20% max spread when swapping rewards
+    Decimal::from_ratio(20_u128, 100_u128)
+}
+
+pub fn get_keeper_reward_cap() -> Decimal {
+    // 10% max keeper reward per action
+    Decimal::from_ratio(10_u128, 100_u128)
+}
+
+pub fn get_instant_unbond_fee_cap() -> Decimal {
+    // 10% max instant unbond fee
+    Decimal::from_ratio(10_u128, 100_u128)
+}
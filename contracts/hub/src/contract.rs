@@ -1,6 +1,6 @@
 use cosmwasm_std::{
     entry_point, from_binary, to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response,
-    StdResult,
+    StdResult, SubMsgResult,
 };
 use cw2::set_contract_version;
 use cw20::Cw20ReceiveMsg;
@@ -8,10 +8,15 @@ use cw20::Cw20ReceiveMsg;
 use eris::helper::unwrap_reply;
 use eris::hub::{CallbackMsg, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, ReceiveMsg};
 
-use crate::constants::{CONTRACT_DENOM, CONTRACT_NAME, CONTRACT_VERSION};
+use crate::constants::{
+    AUTO_MAINTENANCE_REPLY_ID, CONTRACT_NAME, CONTRACT_VERSION, FEE_RECEIVER_REPLY_ID_START,
+    HARVEST_REPLY_ID, RECONCILE_REPLY_ID, REGISTER_STAKE_TOKEN_REPLY_ID, SUBMIT_BATCH_REPLY_ID,
+    SWAP_REPLY_ID,
+};
 use crate::error::{ContractError, ContractResult};
 use crate::helpers::parse_received_fund;
 use crate::state::State;
+use crate::types::StakeToken;
 use crate::{execute, gov, queries};
 
 #[entry_point]
@@ -25,33 +30,93 @@ pub fn instantiate(
 }
 
 #[entry_point]
-pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> ContractResult {
+pub fn execute(mut deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> ContractResult {
     let api = deps.api;
     match msg {
         ExecuteMsg::Receive(cw20_msg) => receive(deps, env, info, cw20_msg),
         ExecuteMsg::Bond {
             receiver,
-        } => execute::bond(
+        } => {
+            let denom = State::default().get_denom(deps.storage)?;
+            let response = execute::bond(
+                deps.branch(),
+                env.clone(),
+                receiver.map(|s| api.addr_validate(&s)).transpose()?.unwrap_or(info.sender.clone()),
+                parse_received_fund(&info.funds, &denom)?,
+                false,
+            )?;
+            execute::add_auto_maintenance(deps.as_ref(), &env, info.sender, response)
+        },
+        ExecuteMsg::Donate {} => {
+            let denom = State::default().get_denom(deps.storage)?;
+            execute::bond(deps, env, info.sender, parse_received_fund(&info.funds, &denom)?, true)
+        },
+        ExecuteMsg::BondLsmShares {
+            receiver,
+        } => execute::bond_lsm_shares(
             deps,
             env,
             receiver.map(|s| api.addr_validate(&s)).transpose()?.unwrap_or(info.sender),
-            parse_received_fund(&info.funds, CONTRACT_DENOM)?,
-            false,
-        ),
-        ExecuteMsg::Donate {} => execute::bond(
-            deps,
-            env,
-            info.sender,
-            parse_received_fund(&info.funds, CONTRACT_DENOM)?,
-            true,
+            info.funds,
         ),
+        ExecuteMsg::QueueUnbond {
+            receiver,
+        } => {
+            let ustake_to_burn = match State::default().get_stake_token(deps.storage)? {
+                StakeToken::TokenFactory(denom) => parse_received_fund(&info.funds, &denom)?,
+                StakeToken::Cw20(_) => return Err(ContractError::StakeTokenIsCw20 {}),
+            };
+
+            let response = execute::queue_unbond(
+                deps.branch(),
+                env.clone(),
+                receiver.map(|s| api.addr_validate(&s)).transpose()?.unwrap_or(info.sender.clone()),
+                ustake_to_burn,
+            )?;
+            execute::add_auto_maintenance(deps.as_ref(), &env, info.sender, response)
+        },
+        ExecuteMsg::InstantUnbond {
+            receiver,
+        } => {
+            let ustake_to_burn = match State::default().get_stake_token(deps.storage)? {
+                StakeToken::TokenFactory(denom) => parse_received_fund(&info.funds, &denom)?,
+                StakeToken::Cw20(_) => return Err(ContractError::StakeTokenIsCw20 {}),
+            };
+
+            execute::instant_unbond(
+                deps,
+                env,
+                receiver.map(|s| api.addr_validate(&s)).transpose()?.unwrap_or(info.sender),
+                ustake_to_burn,
+            )
+        },
         ExecuteMsg::WithdrawUnbonded {
             receiver,
-        } => execute::withdraw_unbonded(
+        } => {
+            let response = execute::withdraw_unbonded(
+                deps.branch(),
+                env.clone(),
+                info.sender.clone(),
+                receiver.map(|s| api.addr_validate(&s)).transpose()?.unwrap_or(info.sender.clone()),
+            )?;
+            execute::add_auto_maintenance(deps.as_ref(), &env, info.sender, response)
+        },
+        ExecuteMsg::WithdrawUnbondedFor {
+            users,
+        } => execute::withdraw_unbonded_for(deps, env, users),
+        ExecuteMsg::CancelUnbond {
+            batch_id,
+        } => execute::cancel_unbond(deps, env, info.sender, batch_id),
+        ExecuteMsg::TransferUnbondRequest {
+            batch_id,
+            recipient,
+            shares,
+        } => execute::transfer_unbond_request(
             deps,
-            env,
-            info.sender.clone(),
-            receiver.map(|s| api.addr_validate(&s)).transpose()?.unwrap_or(info.sender),
+            info.sender,
+            batch_id,
+            api.addr_validate(&recipient)?,
+            shares,
         ),
         ExecuteMsg::AddValidator {
             validator,
@@ -64,13 +129,17 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> C
         } => execute::transfer_ownership(deps, info.sender, new_owner),
         ExecuteMsg::DropOwnershipProposal {} => execute::drop_ownership_proposal(deps, info.sender),
         ExecuteMsg::AcceptOwnership {} => execute::accept_ownership(deps, info.sender),
-        ExecuteMsg::Harvest {} => execute::harvest(deps, env),
+        ExecuteMsg::Harvest {} => execute::harvest(deps, env, info.sender),
         ExecuteMsg::TuneDelegations {} => execute::tune_delegations(deps, env, info.sender),
         ExecuteMsg::Rebalance {
             min_redelegation,
         } => execute::rebalance(deps, env, info.sender, min_redelegation),
-        ExecuteMsg::Reconcile {} => execute::reconcile(deps, env),
-        ExecuteMsg::SubmitBatch {} => execute::submit_batch(deps, env),
+        ExecuteMsg::Reconcile {} => execute::reconcile(deps, env, info.sender),
+        ExecuteMsg::SubmitBatch {} => execute::submit_batch(deps, env, info.sender),
+        ExecuteMsg::PruneBatches {
+            start_after,
+            limit,
+        } => execute::prune_batches(deps, env, start_after, limit),
         ExecuteMsg::Vote {
             proposal_id,
             vote,
@@ -79,49 +148,121 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> C
             proposal_id,
             votes,
         } => gov::vote_weighted(deps, env, info, proposal_id, votes),
+        ExecuteMsg::VoteFromGauge {
+            proposal_id,
+        } => gov::vote_from_gauge(deps, env, proposal_id),
+        ExecuteMsg::TrackHistory {
+            enabled,
+        } => execute::track_history(deps, info.sender, enabled),
+        ExecuteMsg::Pause {
+            operations,
+        } => execute::update_pause_flags(deps, info.sender, operations, true),
+        ExecuteMsg::Unpause {
+            operations,
+        } => execute::update_pause_flags(deps, info.sender, operations, false),
         ExecuteMsg::Callback(callback_msg) => callback(deps, env, info, callback_msg),
         ExecuteMsg::UpdateConfig {
             protocol_fee_contract,
-            protocol_reward_fee,
-            delegation_strategy,
             allow_donations,
             vote_operator,
+            liquidity_buffer,
+            remove_slashed_validators,
+            insert_swap_routes,
+            delete_swap_routes,
+            validator_checks,
+            guardian,
+            fee_receivers,
+            auto_maintenance,
+            keeper_reward,
+            prop_gauges,
+            dust_cleanup,
+            split_bond,
+            remove_liquidity_buffer,
+            protocol_reward_fee,
+            delegation_strategy,
             epoch_period,
             unbond_period,
         } => execute::update_config(
             deps,
+            env,
             info.sender,
             protocol_fee_contract,
-            protocol_reward_fee,
-            delegation_strategy,
             allow_donations,
             vote_operator,
+            liquidity_buffer,
+            remove_slashed_validators,
+            insert_swap_routes,
+            delete_swap_routes,
+            validator_checks,
+            guardian,
+            fee_receivers,
+            auto_maintenance,
+            keeper_reward,
+            prop_gauges,
+            dust_cleanup,
+            split_bond,
+            remove_liquidity_buffer,
+            protocol_reward_fee,
+            delegation_strategy,
+            epoch_period,
+            unbond_period,
+        ),
+        ExecuteMsg::ProposeConfigChange {
+            protocol_reward_fee,
+            delegation_strategy,
             epoch_period,
             unbond_period,
+            config_timelock,
+        } => execute::propose_config_change(
+            deps,
+            env,
+            info.sender,
+            protocol_reward_fee,
+            delegation_strategy,
+            epoch_period,
+            unbond_period,
+            config_timelock,
         ),
+        ExecuteMsg::ApplyConfigChange {} => execute::apply_config_change(deps, env, info.sender),
+        ExecuteMsg::CancelConfigChange {} => execute::cancel_config_change(deps, info.sender),
     }
 }
 
-fn receive(deps: DepsMut, env: Env, info: MessageInfo, cw20_msg: Cw20ReceiveMsg) -> ContractResult {
+fn receive(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> ContractResult {
     let api = deps.api;
+    let state = State::default();
+
+    match state.get_stake_token(deps.storage)? {
+        StakeToken::Cw20(stake_token) if info.sender == stake_token => {},
+        _ => return Err(ContractError::ExpectingStakeToken(info.sender.into())),
+    }
+
     match from_binary(&cw20_msg.msg)? {
         ReceiveMsg::QueueUnbond {
             receiver,
         } => {
-            let state = State::default();
-
-            let stake_token = state.stake_token.load(deps.storage)?;
-            if info.sender != stake_token {
-                return Err(ContractError::ExpectingStakeToken(info.sender.into()));
-            }
-
-            execute::queue_unbond(
-                deps,
-                env,
-                api.addr_validate(&receiver.unwrap_or(cw20_msg.sender))?,
+            let response = execute::queue_unbond(
+                deps.branch(),
+                env.clone(),
+                api.addr_validate(&receiver.unwrap_or_else(|| cw20_msg.sender.clone()))?,
                 cw20_msg.amount,
-            )
+            )?;
+            let keeper = api.addr_validate(&cw20_msg.sender)?;
+            execute::add_auto_maintenance(deps.as_ref(), &env, keeper, response)
         },
+        ReceiveMsg::InstantUnbond {
+            receiver,
+        } => execute::instant_unbond(
+            deps,
+            env,
+            api.addr_validate(&receiver.unwrap_or(cw20_msg.sender))?,
+            cw20_msg.amount,
+        ),
     }
 }
 
@@ -136,22 +277,56 @@ fn callback(
     }
 
     match callback_msg {
+        CallbackMsg::Swap {} => execute::swap(deps, env),
+        CallbackMsg::SwapCoin {
+            coin,
+        } => execute::swap_coin(deps, coin),
         CallbackMsg::Reinvest {} => execute::reinvest(deps, env),
+        CallbackMsg::AutoMaintenance {
+            keeper,
+        } => execute::auto_maintenance(deps, env, keeper),
 
         CallbackMsg::CheckReceivedCoin {
             snapshot,
         } => execute::callback_received_coin(deps, env, snapshot),
+        CallbackMsg::MintLsmShares {
+            receiver,
+            validator,
+            delegation_before,
+        } => execute::mint_lsm_shares(deps, env, receiver, validator, delegation_before),
     }
 }
 
 #[entry_point]
 pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> ContractResult {
     match reply.id {
-        1 => execute::register_stake_token(deps, unwrap_reply(reply)?),
+        REGISTER_STAKE_TOKEN_REPLY_ID => execute::register_stake_token(deps, unwrap_reply(reply)?),
+        AUTO_MAINTENANCE_REPLY_ID => {
+            execute::auto_maintenance_failed(deps, "auto_maintenance", reply_error(reply)?)
+        },
+        SUBMIT_BATCH_REPLY_ID => {
+            execute::auto_maintenance_failed(deps, "submit_batch", reply_error(reply)?)
+        },
+        RECONCILE_REPLY_ID => {
+            execute::auto_maintenance_failed(deps, "reconcile", reply_error(reply)?)
+        },
+        HARVEST_REPLY_ID => execute::auto_maintenance_failed(deps, "harvest", reply_error(reply)?),
+        SWAP_REPLY_ID => execute::swap_failed(reply_error(reply)?),
+        id if id >= FEE_RECEIVER_REPLY_ID_START => {
+            execute::fee_wake_up_failed(deps, id - FEE_RECEIVER_REPLY_ID_START, reply_error(reply)?)
+        },
         id => Err(ContractError::InvalidReplyId(id)),
     }
 }
 
+/// Returns the error of a submessage that is only dispatched with `reply_on_error`
+fn reply_error(reply: Reply) -> Result<String, ContractError> {
+    match reply.result {
+        SubMsgResult::Err(error) => Ok(error),
+        SubMsgResult::Ok(_) => Err(ContractError::UnexpectedReplySuccess(reply.id)),
+    }
+}
+
 #[entry_point]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
@@ -188,6 +363,21 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::SimulateWantedDelegations {
             period,
         } => to_binary(&queries::simulate_wanted_delegations(deps, env, period)?),
+        QueryMsg::ExchangeRates {
+            start_after,
+            limit,
+        } => to_binary(&queries::exchange_rates(deps, start_after, limit)?),
+        QueryMsg::PendingRewards {} => to_binary(&queries::pending_rewards(deps)?),
+        QueryMsg::SlashingEvents {
+            start_after,
+            limit,
+        } => to_binary(&queries::slashing_events(deps, start_after, limit)?),
+        QueryMsg::UnbondingSlots {} => to_binary(&queries::unbonding_slots(deps, env)?),
+        QueryMsg::UserHistory {
+            user,
+            start_after,
+            limit,
+        } => to_binary(&queries::user_history(deps, user, start_after, limit)?),
     }
 }
 
@@ -195,6 +385,13 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
 pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> ContractResult {
     // let contract_version = get_contract_version(deps.storage)?;
 
+    // hubs deployed before the storage metrics were kept count their entries once
+    let state = State::default();
+    if state.storage_metrics.may_load(deps.storage)?.is_none() {
+        let metrics = state.count_storage_metrics(deps.storage)?;
+        state.storage_metrics.save(deps.storage, &metrics)?;
+    }
+
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::new()
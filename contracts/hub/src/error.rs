@@ -1,5 +1,6 @@
-use cosmwasm_std::{OverflowError, Response, StdError};
+use cosmwasm_std::{OverflowError, Response, StdError, Uint128};
 use cw20_base::ContractError as cw20baseError;
+use eris::hub::PausableOperation;
 use thiserror::Error;
 
 pub type ContractResult = Result<Response, ContractError>;
@@ -28,6 +29,18 @@ pub enum ContractError {
     #[error("Expecting stake token, received {0}")]
     ExpectingStakeToken(String),
 
+    #[error("Stake token is a CW20 token, it needs to be sent to the hub for unbonding")]
+    StakeTokenIsCw20 {},
+
+    #[error("Invalid staking denom: {0}")]
+    InvalidDenom(String),
+
+    #[error("Marketing info is only supported for CW20 stake tokens")]
+    MarketingRequiresCw20 {},
+
+    #[error("Invalid tokenized shares: {0}")]
+    InvalidLsmShares(String),
+
     #[error("Protocol_reward_fee greater than max")]
     ProtocolRewardFeeTooHigh {},
 
@@ -43,9 +56,15 @@ pub enum ContractError {
     #[error("Invalid reply id: {0}")]
     InvalidReplyId(u64),
 
+    #[error("Reply {0} is only expected on error")]
+    UnexpectedReplySuccess(u64),
+
     #[error("Donations are disabled")]
     DonationsDisabled {},
 
+    #[error("Dust cleanup requires a nonzero threshold and grace period")]
+    DustCleanupNotConfigured {},
+
     #[error("No {0} available to be bonded")]
     NoTokensAvailable(String),
 
@@ -55,6 +74,9 @@ pub enum ContractError {
     #[error("validator {0} is not whitelisted")]
     ValidatorNotWhitelisted(String),
 
+    #[error("validator {0} is not eligible: {1}")]
+    ValidatorNotEligible(String, String),
+
     #[error("cannot find `instantiate` event")]
     CannotFindInstantiateEvent {},
 
@@ -64,9 +86,75 @@ pub enum ContractError {
     #[error("No vote operator set")]
     NoVoteOperatorSet {},
 
+    #[error("No prop gauges set")]
+    NoPropGaugesSet {},
+
+    #[error("Voting on proposal {0} in the prop gauges ends at {1}")]
+    PropVotingNotEnded(u64, u64),
+
+    #[error("Quorum not reached for proposal {0}")]
+    PropQuorumNotReached(u64),
+
+    #[error("No guardian set")]
+    NoGuardianSet {},
+
+    #[error("Unauthorized: sender is not guardian")]
+    UnauthorizedSenderNotGuardian {},
+
+    #[error("Operation {0} is paused")]
+    OperationPaused(PausableOperation),
+
     #[error("Not all wanted undelegations calculated, missing: {0}")]
     ComputeUndelegationsWrong(u128),
 
+    #[error("Instant_unbond_fee greater than max")]
+    InstantUnbondFeeTooHigh {},
+
+    #[error("{0} can't be greater than 1")]
+    ShareTooHigh(String),
+
+    #[error("Instant unbonding is disabled")]
+    InstantUnbondDisabled {},
+
+    #[error("Liquidity buffer can't be updated and removed at the same time")]
+    LiquidityBufferUpdatedAndRemoved {},
+
+    #[error("Not enough liquidity for instant unbonding, available: {0}")]
+    NotEnoughLiquidity(Uint128),
+
+    #[error("Max_spread of swap route greater than max")]
+    MaxSpreadTooHigh {},
+
+    #[error("Batch {0} is not pending anymore")]
+    BatchNotPending(u64),
+
+    #[error("No unbonding request found in batch {0}")]
+    UnbondRequestNotFound(u64),
+
+    #[error("Not enough shares in unbonding request, available: {0}")]
+    NotEnoughShares(Uint128),
+
+    #[error("Too many users, max: {0}")]
+    TooManyUsers(usize),
+
+    #[error("Can't transfer to self")]
+    CantTransferToSelf {},
+
+    #[error("Config change does not change any parameter")]
+    EmptyConfigChange {},
+
+    #[error("No config change is pending")]
+    NoPendingConfigChange {},
+
+    #[error("Config change can only be applied after {0}")]
+    ConfigChangeTimelocked(u64),
+
+    #[error("A config change is already pending, cancel it before proposing a new one")]
+    ConfigChangePending {},
+
+    #[error("{0} can only be changed through ProposeConfigChange")]
+    TimelockedConfigParam(String),
+
     #[error("Contract can't be migrated!")]
     MigrationError {},
 }
@@ -1,31 +1,48 @@
+use std::ops::Div;
+
 use cosmwasm_std::{
-    attr, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, DepsMut, DistributionMsg, Env, Event,
-    Order, Response, StdError, StdResult, SubMsg, SubMsgResponse, Uint128, WasmMsg,
+    attr, to_binary, Addr, Attribute, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut,
+    DistributionMsg, Env, Event, Order, Response, StdError, StdResult, Storage, SubMsg,
+    SubMsgResponse, Uint128, WasmMsg,
 };
 
 use cw2::set_contract_version;
-use cw20::{Cw20ExecuteMsg, MinterResponse};
-use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
+use cw20::{Logo, MinterResponse};
+use cw20_base::msg::{InstantiateMarketingInfo, InstantiateMsg as Cw20InstantiateMsg};
+use cw_storage_plus::Bound;
 use eris::{CustomResponse, DecimalCheckedOps};
 
+use eris::constants::DAY;
 use eris::hub::{
-    Batch, CallbackMsg, DelegationStrategy, ExecuteMsg, FeeConfig, InstantiateMsg, PendingBatch,
-    UnbondRequest,
+    AutoMaintenanceConfig, Batch, CallbackMsg, DelegationStrategy, DustCleanupConfig,
+    ExchangeHistory, ExecuteMsg, FeeConfig, FeeReceiver, InstantiateMsg, KeeperReward,
+    KeeperRewardConfig, LiquidityBufferConfig, PausableOperation, PendingBatch,
+    PendingConfigChange, RewardSwapRoute, SlashingEvent, SplitBondConfig, UnbondRequest,
+    UserHistoryAction, UserHistoryItem, ValidatorChecks,
 };
 
-use crate::constants::{get_reward_fee_cap, CONTRACT_DENOM, CONTRACT_NAME, CONTRACT_VERSION};
+use crate::constants::{
+    get_instant_unbond_fee_cap, get_keeper_reward_cap, get_max_spread_cap, get_reward_fee_cap,
+    AUTO_MAINTENANCE_REPLY_ID, CONTRACT_NAME, CONTRACT_VERSION, FEE_RECEIVER_REPLY_ID_START,
+    HARVEST_REPLY_ID, MAX_PRUNE_BATCHES, MAX_WITHDRAW_FOR_USERS, RECONCILE_REPLY_ID,
+    REGISTER_STAKE_TOKEN_REPLY_ID, SLASHING_TOLERANCE, SUBMIT_BATCH_REPLY_ID, SWAP_REPLY_ID,
+};
 use crate::error::{ContractError, ContractResult};
 use crate::helpers::{
     assert_validator_exists, assert_validators_exists, dedupe, get_wanted_delegations,
-    query_all_delegations, query_cw20_total_supply, query_delegation, query_delegations,
+    query_all_delegations, query_delegation, query_delegations, query_ineligible_validators,
 };
 use crate::math::{
+    compute_buffer_refill, compute_exchange_rate, compute_fee_split, compute_keeper_rewards,
     compute_mint_amount, compute_redelegations_for_rebalancing, compute_redelegations_for_removal,
-    compute_unbond_amount, compute_undelegations, mark_reconciled_batches, reconcile_batches,
+    compute_split_delegations, compute_unbond_amount, compute_undelegations,
+    get_utoken_per_validator, mark_reconciled_batches, reconcile_batches,
 };
+use crate::protos::lsm::{parse_share_denom, redeem_tokens_for_shares_msg};
+use crate::protos::tokenfactory::create_denom_msg;
 use crate::state::State;
 use crate::types::gauges::TuneInfoGaugeLoader;
-use crate::types::{Coins, Delegation, SendFee};
+use crate::types::{Coins, Delegation, Redelegation, SendFee};
 
 //--------------------------------------------------------------------------------------------------
 // Instantiation
@@ -48,6 +65,17 @@ pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> ContractResu
         return Err(ContractError::CantBeZero("unbond_period".into()));
     }
 
+    if let Some(denom) = msg.denom {
+        if denom.is_empty() || denom.contains(char::is_whitespace) {
+            return Err(ContractError::InvalidDenom(denom));
+        }
+        state.denom.save(deps.storage, &denom)?;
+    }
+
+    if let Some(config_timelock) = msg.config_timelock {
+        state.config_timelock.save(deps.storage, &config_timelock)?;
+    }
+
     state.owner.save(deps.storage, &deps.api.addr_validate(&msg.owner)?)?;
     state.epoch_period.save(deps.storage, &msg.epoch_period)?;
     state.unbond_period.save(deps.storage, &msg.unbond_period)?;
@@ -83,7 +111,29 @@ pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> ContractResu
     )?;
 
     let delegation_strategy = msg.delegation_strategy.unwrap_or(DelegationStrategy::Uniform);
-    state.delegation_strategy.save(deps.storage, &delegation_strategy.validate(deps.api)?)?;
+    state
+        .delegation_strategy
+        .save(deps.storage, &validate_delegation_strategy(&state, &deps, delegation_strategy)?)?;
+
+    if let Some(subdenom) = msg.token_factory_subdenom {
+        if msg.stake_token_marketing.is_some() {
+            return Err(ContractError::MarketingRequiresCw20 {});
+        }
+
+        let denom = format!("factory/{0}/{1}", env.contract.address, subdenom);
+        state.stake_token_denom.save(deps.storage, &denom)?;
+
+        return Ok(Response::new()
+            .add_message(create_denom_msg(env.contract.address.as_str(), &subdenom)?)
+            .add_attribute("stake_token", denom));
+    }
+
+    let marketing = msg.stake_token_marketing.map(|marketing| InstantiateMarketingInfo {
+        project: marketing.project,
+        description: marketing.description,
+        marketing: Some(marketing.marketing.unwrap_or_else(|| msg.owner.clone())),
+        logo: marketing.logo_url.map(Logo::Url),
+    });
 
     Ok(Response::new().add_submessage(SubMsg::reply_on_success(
         CosmosMsg::Wasm(WasmMsg::Instantiate {
@@ -98,15 +148,31 @@ pub fn instantiate(deps: DepsMut, env: Env, msg: InstantiateMsg) -> ContractResu
                     minter: env.contract.address.into(),
                     cap: None,
                 }),
-                marketing: None,
+                marketing,
             })?,
             funds: vec![],
             label: "Eris Liquid Staking Token".to_string(),
         }),
-        1,
+        REGISTER_STAKE_TOKEN_REPLY_ID,
     )))
 }
 
+/// Validates the delegation strategy, explicitly defined shares can only target whitelisted validators
+fn validate_delegation_strategy(
+    state: &State,
+    deps: &DepsMut,
+    delegation_strategy: DelegationStrategy,
+) -> Result<DelegationStrategy<Addr>, ContractError> {
+    let validators = state.validators.load(deps.storage)?;
+    if let Some(validator) =
+        delegation_strategy.defined_validators().into_iter().find(|v| !validators.contains(v))
+    {
+        return Err(ContractError::ValidatorNotWhitelisted(validator.clone()));
+    }
+
+    Ok(delegation_strategy.validate(deps.api)?)
+}
+
 pub fn register_stake_token(deps: DepsMut, response: SubMsgResponse) -> ContractResult {
     let state = State::default();
 
@@ -141,20 +207,38 @@ pub fn register_stake_token(deps: DepsMut, response: SubMsgResponse) -> Contract
 /// smallest amount of delegation. If delegations become severely unbalance as a result of this
 /// (e.g. when a single user makes a very big deposit), anyone can invoke `ExecuteMsg::Rebalance`
 /// to balance the delegations.
+///
+/// If a liquidity buffer is configured and not filled up, a part of the deposit is kept liquid
+/// instead of being delegated.
 pub fn bond(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     receiver: Addr,
     token_to_bond: Uint128,
     donate: bool,
 ) -> ContractResult {
     let state = State::default();
-    let stake_token = state.stake_token.load(deps.storage)?;
+    // donations are bonded as well, so they are paused together with bonding
+    state.assert_not_paused(deps.storage, PausableOperation::Bond)?;
+
+    let response = check_slashing(&state, &mut deps, &env, false)?;
+    let stake_token = state.get_stake_token(deps.storage)?;
+    let denom = state.get_denom(deps.storage)?;
 
-    let (new_delegation, delegations) = find_new_delegation(&state, &deps, &env, token_to_bond)?;
+    let utoken_buffer = state.liquidity_buffer.may_load(deps.storage)?.unwrap_or_default();
+    let utoken_to_buffer = match state.liquidity_buffer_config.may_load(deps.storage)? {
+        Some(config) => {
+            compute_buffer_refill(token_to_bond, utoken_buffer, &config, config.refill_bond_share)?
+        },
+        None => Uint128::zero(),
+    };
+    let utoken_to_delegate = token_to_bond.checked_sub(utoken_to_buffer)?;
+
+    let (new_delegations, delegations) =
+        find_new_delegations(&state, &deps, &env, utoken_to_delegate)?;
 
     // Query the current supply of Staking Token and compute the amount to mint
-    let ustake_supply = query_cw20_total_supply(&deps.querier, &stake_token)?;
+    let ustake_supply = stake_token.query_supply(&deps.querier)?;
     let ustake_to_mint = if donate {
         match state.allow_donations.may_load(deps.storage)? {
             Some(false) => Err(ContractError::DonationsDisabled {})?,
@@ -165,37 +249,189 @@ pub fn bond(
 
         Uint128::zero()
     } else {
-        compute_mint_amount(ustake_supply, token_to_bond, &delegations)
+        compute_mint_amount(ustake_supply, token_to_bond, &delegations, utoken_buffer)
     };
 
+    if !utoken_to_buffer.is_zero() {
+        state.liquidity_buffer.save(deps.storage, &(utoken_buffer + utoken_to_buffer))?;
+    }
+
+    for new_delegation in &new_delegations {
+        state.add_expected_delegation(
+            deps.storage,
+            &new_delegation.validator,
+            new_delegation.amount,
+        )?;
+    }
+
+    if !donate && state.tracks_history(deps.storage, &receiver)? {
+        state.add_user_history(
+            deps.storage,
+            &receiver,
+            UserHistoryItem {
+                id: 0,
+                action: UserHistoryAction::Bond,
+                utoken: token_to_bond,
+                ustake: ustake_to_mint,
+                exchange_rate: compute_exchange_rate(ustake_supply, &delegations, utoken_buffer),
+                time: env.block.time.seconds(),
+            },
+        )?;
+    }
+
     let event = Event::new("erishub/bonded")
         .add_attribute("receiver", receiver.clone())
-        .add_attribute("uluna_bonded", token_to_bond)
-        .add_attribute("ustake_minted", ustake_to_mint);
+        .add_attribute("utoken_bonded", token_to_bond)
+        .add_attribute("ustake_minted", ustake_to_mint)
+        .add_attribute("utoken_to_buffer", utoken_to_buffer);
 
-    let mint_msg = if donate {
-        None
+    let delegate_msgs = new_delegations
+        .iter()
+        .filter(|d| d.amount > 0)
+        .map(|d| d.to_cosmos_msg(&denom))
+        .collect::<Vec<_>>();
+
+    let mint_msgs = if donate {
+        vec![]
     } else {
-        Some(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: stake_token.into(),
-            msg: to_binary(&Cw20ExecuteMsg::Mint {
-                recipient: receiver.to_string(),
-                amount: ustake_to_mint,
-            })?,
-            funds: vec![],
-        }))
+        stake_token.mint_msgs(&env.contract.address, &receiver, ustake_to_mint)?
     };
 
-    Ok(Response::new()
-        .add_message(new_delegation.to_cosmos_msg())
-        .add_optional_message(mint_msg)
-        .add_message(check_received_coin_msg(&deps, &env, Some(token_to_bond))?)
+    Ok(response
+        .add_messages(delegate_msgs)
+        .add_messages(mint_msgs)
+        .add_message(check_received_coin_msg(&deps, &env, Some(utoken_to_delegate))?)
         .add_event(event)
         .add_attribute("action", "erishub/bond"))
 }
 
-pub fn harvest(deps: DepsMut, env: Env) -> ContractResult {
-    let withdraw_msgs = query_all_delegations(&deps.querier, &env.contract.address)?
+/// NOTE: The amount of tokens backing the shares is only known after the redemption, so the stake
+/// tokens are minted in a callback based on the increase of the delegation to the validator.
+pub fn bond_lsm_shares(
+    mut deps: DepsMut,
+    env: Env,
+    receiver: Addr,
+    funds: Vec<Coin>,
+) -> ContractResult {
+    let state = State::default();
+    state.assert_not_paused(deps.storage, PausableOperation::Bond)?;
+
+    if funds.len() != 1 {
+        return Err(ContractError::InvalidLsmShares(format!(
+            "must deposit exactly one coin; received {0}",
+            funds.len()
+        )));
+    }
+
+    let shares = &funds[0];
+    let validator = parse_share_denom(&shares.denom)
+        .ok_or_else(|| ContractError::InvalidLsmShares(shares.denom.clone()))?
+        .to_string();
+
+    if shares.amount.is_zero() {
+        return Err(ContractError::InvalidLsmShares("deposit amount must be non-zero".into()));
+    }
+
+    let validators = state.validators.load(deps.storage)?;
+    if !validators.contains(&validator) {
+        return Err(ContractError::ValidatorNotWhitelisted(validator));
+    }
+
+    let response = check_slashing(&state, &mut deps, &env, false)?;
+
+    let delegation_before =
+        query_delegation(&deps.querier, &validator, &env.contract.address)?.amount;
+
+    let event = Event::new("erishub/lsm_shares_received")
+        .add_attribute("receiver", receiver.clone())
+        .add_attribute("validator", validator.clone())
+        .add_attribute("shares", shares.to_string());
+
+    let mint_msg = CallbackMsg::MintLsmShares {
+        receiver,
+        validator,
+        delegation_before: Uint128::new(delegation_before),
+    }
+    .into_cosmos_msg(&env.contract.address)?;
+
+    Ok(response
+        .add_message(redeem_tokens_for_shares_msg(env.contract.address.as_str(), shares)?)
+        // redeeming into an existing delegation withdraws its rewards
+        .add_message(check_received_coin_msg(&deps, &env, None)?)
+        .add_message(mint_msg)
+        .add_event(event)
+        .add_attribute("action", "erishub/bond_lsm_shares"))
+}
+
+pub fn mint_lsm_shares(
+    deps: DepsMut,
+    env: Env,
+    receiver: Addr,
+    validator: String,
+    delegation_before: Uint128,
+) -> ContractResult {
+    let state = State::default();
+    let stake_token = state.get_stake_token(deps.storage)?;
+    let denom = state.get_denom(deps.storage)?;
+
+    let mut delegations = query_all_delegations(&deps.querier, &env.contract.address, &denom)?;
+    let delegation_after =
+        delegations.iter().find(|d| d.validator == validator).map(|d| d.amount).unwrap_or_default();
+
+    let token_bonded = Uint128::new(delegation_after).checked_sub(delegation_before)?;
+    if token_bonded.is_zero() {
+        return Err(ContractError::InvalidLsmShares("no delegation was redeemed".into()));
+    }
+
+    // the exchange rate is computed based on the delegations before the redemption
+    for d in delegations.iter_mut().filter(|d| d.validator == validator) {
+        d.amount = delegation_before.u128();
+    }
+
+    let utoken_buffer = state.liquidity_buffer.may_load(deps.storage)?.unwrap_or_default();
+    let ustake_supply = stake_token.query_supply(&deps.querier)?;
+    let ustake_to_mint =
+        compute_mint_amount(ustake_supply, token_bonded, &delegations, utoken_buffer);
+
+    state.add_expected_delegation(deps.storage, &validator, token_bonded.u128())?;
+
+    if state.tracks_history(deps.storage, &receiver)? {
+        state.add_user_history(
+            deps.storage,
+            &receiver,
+            UserHistoryItem {
+                id: 0,
+                action: UserHistoryAction::Bond,
+                utoken: token_bonded,
+                ustake: ustake_to_mint,
+                exchange_rate: compute_exchange_rate(ustake_supply, &delegations, utoken_buffer),
+                time: env.block.time.seconds(),
+            },
+        )?;
+    }
+
+    let event = Event::new("erishub/lsm_shares_bonded")
+        .add_attribute("receiver", receiver.clone())
+        .add_attribute("validator", validator)
+        .add_attribute("utoken_bonded", token_bonded)
+        .add_attribute("ustake_minted", ustake_to_mint);
+
+    Ok(Response::new()
+        .add_messages(stake_token.mint_msgs(&env.contract.address, &receiver, ustake_to_mint)?)
+        .add_event(event)
+        .add_attribute("action", "erishub/mint_lsm_shares"))
+}
+
+pub fn harvest(mut deps: DepsMut, env: Env, sender: Addr) -> ContractResult {
+    let state = State::default();
+    state.assert_not_paused(deps.storage, PausableOperation::Harvest)?;
+
+    let response = check_slashing(&state, &mut deps, &env, false)?;
+    let keeper_attrs = claim_keeper_reward(&state, &mut deps, &env, "harvest", &sender)?;
+    state.last_harvest.save(deps.storage, &env.block.time.seconds())?;
+    let denom = state.get_denom(deps.storage)?;
+
+    let withdraw_msgs = query_all_delegations(&deps.querier, &env.contract.address, &denom)?
         .into_iter()
         .map(|d| {
             CosmosMsg::Distribution(DistributionMsg::WithdrawDelegatorReward {
@@ -204,11 +440,110 @@ pub fn harvest(deps: DepsMut, env: Env) -> ContractResult {
         })
         .collect::<Vec<_>>();
 
-    Ok(Response::new()
+    // rewards in other denoms are only tracked, if they can be swapped to the staking denom
+    let swap_denoms = state
+        .reward_swap_routes
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let check_reward_msgs = swap_denoms
+        .iter()
+        .map(|denom| {
+            let snapshot = deps.querier.query_balance(&env.contract.address, denom)?;
+            CallbackMsg::CheckReceivedCoin {
+                snapshot,
+            }
+            .into_cosmos_msg(&env.contract.address)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let swap_msg = if swap_denoms.is_empty() {
+        None
+    } else {
+        Some(CallbackMsg::Swap {}.into_cosmos_msg(&env.contract.address)?)
+    };
+
+    Ok(response
         .add_messages(withdraw_msgs)
         .add_message(check_received_coin_msg(&deps, &env, None)?)
+        .add_messages(check_reward_msgs)
+        .add_optional_message(swap_msg)
         .add_message(CallbackMsg::Reinvest {}.into_cosmos_msg(&env.contract.address)?)
-        .add_attribute("action", "erishub/harvest"))
+        .add_attribute("action", "erishub/harvest")
+        .add_attributes(keeper_attrs))
+}
+
+/// Swaps all unlocked reward coins that have a swap route to utoken. The received utoken is added
+/// to the unlocked coins through the balance check after the swaps, so that it is reinvested.
+/// Every route is swapped in its own callback, so that a failing route only keeps its coin unlocked
+/// instead of reverting the harvest.
+pub fn swap(deps: DepsMut, env: Env) -> ContractResult {
+    let state = State::default();
+    let unlocked_coins = state.unlocked_coins.load(deps.storage)?;
+
+    let mut swap_msgs = vec![];
+    let mut swapped = vec![];
+    for coin in unlocked_coins {
+        if coin.amount.is_zero() || !state.reward_swap_routes.has(deps.storage, &coin.denom) {
+            continue;
+        }
+        swapped.push(coin.to_string());
+        swap_msgs.push(SubMsg::reply_on_error(
+            CallbackMsg::SwapCoin {
+                coin,
+            }
+            .into_cosmos_msg(&env.contract.address)?,
+            SWAP_REPLY_ID,
+        ));
+    }
+
+    if swap_msgs.is_empty() {
+        return Ok(Response::new().add_attribute("action", "erishub/swap"));
+    }
+
+    let event = Event::new("erishub/swapped").add_attribute("coins", swapped.join(","));
+
+    Ok(Response::new()
+        .add_submessages(swap_msgs)
+        .add_message(check_received_coin_msg(&deps, &env, None)?)
+        .add_event(event)
+        .add_attribute("action", "erishub/swap"))
+}
+
+pub fn swap_coin(deps: DepsMut, coin: Coin) -> ContractResult {
+    let state = State::default();
+    let route = state.reward_swap_routes.load(deps.storage, &coin.denom)?;
+
+    let mut unlocked_coins = Coins(state.unlocked_coins.load(deps.storage)?);
+    unlocked_coins.deduct(&coin)?;
+    state.unlocked_coins.save(deps.storage, &unlocked_coins.0)?;
+
+    Ok(Response::new()
+        .add_message(route.swap_msg(coin.amount)?)
+        .add_attribute("action", "erishub/swap_coin"))
+}
+
+/// If the wake-up message of a fee receiver fails, the fee is sent without the message
+pub fn fee_wake_up_failed(deps: DepsMut, index: u64, error: String) -> ContractResult {
+    let state = State::default();
+    let denom = state.get_denom(deps.storage)?;
+    let (receiver, amount) = state.fee_wake_ups.load(deps.storage, index)?;
+
+    let event = Event::new("erishub/fee_wake_up_failed")
+        .add_attribute("receiver", receiver.to_string())
+        .add_attribute("error", error);
+
+    Ok(Response::new()
+        .add_message(SendFee::new(receiver, amount.u128()).to_cosmos_msg(&denom))
+        .add_event(event)
+        .add_attribute("action", "erishub/fee_wake_up_failed"))
+}
+
+/// A failing swap is only reported, the coin stays unlocked and is swapped with the next harvest
+pub fn swap_failed(error: String) -> ContractResult {
+    let event = Event::new("erishub/swap_failed").add_attribute("error", error);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/swap_failed"))
 }
 
 /// This callback is used to take a current snapshot of the balance and add the received balance to the unlocked_coins state after the execution
@@ -218,8 +553,8 @@ fn check_received_coin_msg(
     // offset to account for funds being sent that should be ignored
     negative_offset: Option<Uint128>,
 ) -> StdResult<CosmosMsg> {
-    let mut amount =
-        deps.querier.query_balance(env.contract.address.to_string(), CONTRACT_DENOM)?.amount;
+    let denom = State::default().get_denom(deps.storage)?;
+    let mut amount = deps.querier.query_balance(env.contract.address.to_string(), &denom)?.amount;
 
     if let Some(negative_offset) = negative_offset {
         amount = amount.checked_sub(negative_offset)?;
@@ -228,7 +563,7 @@ fn check_received_coin_msg(
     CallbackMsg::CheckReceivedCoin {
         // 0. take current balance - offset
         snapshot: Coin {
-            denom: CONTRACT_DENOM.to_string(),
+            denom,
             amount,
         },
     }
@@ -245,34 +580,146 @@ pub fn reinvest(deps: DepsMut, env: Env) -> ContractResult {
     let state = State::default();
     let mut unlocked_coins = state.unlocked_coins.load(deps.storage)?;
     let fee_config = state.fee_config.load(deps.storage)?;
+    let denom = state.get_denom(deps.storage)?;
 
-    let uluna_available = unlocked_coins
+    let utoken_available = unlocked_coins
         .iter()
-        .find(|coin| coin.denom == CONTRACT_DENOM)
-        .ok_or_else(|| ContractError::NoTokensAvailable(CONTRACT_DENOM.into()))?
+        .find(|coin| coin.denom == denom)
+        .ok_or_else(|| ContractError::NoTokensAvailable(denom.clone()))?
         .amount;
 
-    let protocol_fee_amount = fee_config.protocol_reward_fee.checked_mul_uint(uluna_available)?;
-    let uluna_to_bond = uluna_available.saturating_sub(protocol_fee_amount);
+    let protocol_fee_amount = fee_config.protocol_reward_fee.checked_mul_uint(utoken_available)?;
+    let utoken_after_fee = utoken_available.saturating_sub(protocol_fee_amount);
+
+    // the keeper of an automatic harvest is tipped from the rewards
+    let keeper_tip = match state.keeper.may_load(deps.storage)? {
+        Some(keeper) => {
+            state.keeper.remove(deps.storage);
+            let tip = state.auto_maintenance.load(deps.storage)?.keeper_tip.min(utoken_after_fee);
+            Some(SendFee::new(keeper, tip.u128()))
+        },
+        None => None,
+    }
+    .filter(|tip| tip.amount > 0);
+    let utoken_after_tip = utoken_after_fee
+        .checked_sub(Uint128::new(keeper_tip.as_ref().map_or(0, |tip| tip.amount)))?;
+
+    // keepers of maintenance actions since the last harvest are rewarded as well
+    let keeper_claims = state
+        .keeper_claims
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (action, _) in &keeper_claims {
+        state.keeper_claims.remove(deps.storage, action);
+    }
+    let keeper_rewards = match state.keeper_reward.may_load(deps.storage)? {
+        Some(config) => {
+            let keepers = keeper_claims.into_iter().map(|(_, keeper)| keeper).collect::<Vec<_>>();
+            compute_keeper_rewards(&config.reward, &keepers, utoken_after_tip)?
+        },
+        None => vec![],
+    };
+    let utoken_keeper_rewards: u128 = keeper_rewards.iter().map(|reward| reward.amount).sum();
+    let utoken_to_bond = utoken_after_tip.checked_sub(Uint128::new(utoken_keeper_rewards))?;
+
+    let utoken_buffer = state.liquidity_buffer.may_load(deps.storage)?.unwrap_or_default();
+    let utoken_to_buffer = match state.liquidity_buffer_config.may_load(deps.storage)? {
+        Some(config) => compute_buffer_refill(
+            utoken_to_bond,
+            utoken_buffer,
+            &config,
+            config.refill_reinvest_share,
+        )?,
+        None => Uint128::zero(),
+    };
+    let utoken_to_delegate = utoken_to_bond.checked_sub(utoken_to_buffer)?;
 
-    let (new_delegation, _) = find_new_delegation(&state, &deps, &env, uluna_to_bond)?;
+    let (new_delegation, _) = find_new_delegation(&state, &deps, &env, utoken_to_delegate)?;
 
-    unlocked_coins.retain(|coin| coin.denom != CONTRACT_DENOM);
+    unlocked_coins.retain(|coin| coin.denom != denom);
     state.unlocked_coins.save(deps.storage, &unlocked_coins)?;
 
-    let event = Event::new("erishub/harvested")
-        .add_attribute("uluna_bonded", uluna_to_bond)
-        .add_attribute("uluna_protocol_fee", protocol_fee_amount);
+    if !utoken_to_buffer.is_zero() {
+        state.liquidity_buffer.save(deps.storage, &(utoken_buffer + utoken_to_buffer))?;
+    }
+
+    state.add_expected_delegation(
+        deps.storage,
+        &new_delegation.validator,
+        utoken_to_delegate.u128(),
+    )?;
+
+    // we store the exchange rate daily to not create too much data.
+    let ustake_supply = state.get_stake_token(deps.storage)?.query_supply(&deps.querier)?;
+    if !ustake_supply.is_zero() {
+        let utoken_bonded: u128 =
+            query_all_delegations(&deps.querier, &env.contract.address, &denom)?
+                .iter()
+                .map(|d| d.amount)
+                .sum();
+        let utoken_total = Uint128::new(utoken_bonded) + utoken_buffer + utoken_to_bond;
+
+        state.exchange_history.save(
+            deps.storage,
+            env.block.time.seconds().div(DAY),
+            &ExchangeHistory {
+                exchange_rate: Decimal::from_ratio(utoken_total, ustake_supply),
+                time_s: env.block.time.seconds(),
+            },
+        )?;
+    }
+
+    let mut event = Event::new("erishub/harvested")
+        .add_attribute("utoken_bonded", utoken_to_bond)
+        .add_attribute("utoken_protocol_fee", protocol_fee_amount)
+        .add_attribute("utoken_to_buffer", utoken_to_buffer);
+    if let Some(keeper_tip) = &keeper_tip {
+        event = event.add_attribute("utoken_keeper_tip", keeper_tip.amount.to_string());
+    }
+    if !keeper_rewards.is_empty() {
+        event = event.add_attribute("utoken_keeper_rewards", utoken_keeper_rewards.to_string());
+    }
+
+    let mut msgs = vec![];
 
-    let mut msgs = vec![new_delegation.to_cosmos_msg()];
+    if !utoken_to_delegate.is_zero() {
+        msgs.push(SubMsg::new(new_delegation.to_cosmos_msg(&denom)));
+    }
 
     if !protocol_fee_amount.is_zero() {
-        let send_fee = SendFee::new(fee_config.protocol_fee_contract, protocol_fee_amount.u128());
-        msgs.push(send_fee.to_cosmos_msg());
+        let send_fees = match state.fee_receivers.may_load(deps.storage)? {
+            Some(fee_receivers) => compute_fee_split(&fee_receivers, protocol_fee_amount),
+            None => {
+                vec![SendFee::new(fee_config.protocol_fee_contract, protocol_fee_amount.u128())]
+            },
+        };
+        // a failing wake-up message must not revert the harvest
+        for (index, send_fee) in send_fees.iter().enumerate() {
+            if send_fee.msg.is_none() {
+                msgs.push(SubMsg::new(send_fee.to_cosmos_msg(&denom)));
+                continue;
+            }
+            let index = index as u64;
+            state.fee_wake_ups.save(
+                deps.storage,
+                index,
+                &(Addr::unchecked(&send_fee.to_address), Uint128::new(send_fee.amount)),
+            )?;
+            msgs.push(SubMsg::reply_on_error(
+                send_fee.to_cosmos_msg(&denom),
+                FEE_RECEIVER_REPLY_ID_START + index,
+            ));
+        }
+    }
+
+    if let Some(keeper_tip) = keeper_tip {
+        msgs.push(SubMsg::new(keeper_tip.to_cosmos_msg(&denom)));
     }
 
+    msgs.extend(keeper_rewards.iter().map(|reward| SubMsg::new(reward.to_cosmos_msg(&denom))));
+
     Ok(Response::new()
-        .add_messages(msgs)
+        .add_submessages(msgs)
         .add_event(event)
         .add_attribute("action", "erishub/reinvest"))
 }
@@ -301,6 +748,45 @@ pub fn callback_received_coin(deps: DepsMut, env: Env, snapshot: Coin) -> Contra
     Ok(Response::new().add_attribute("action", "erishub/received"))
 }
 
+/// splits the amount across the validators furthest below their delegation goal if split bonding is
+/// enabled, otherwise the whole amount goes to the validator found by `find_new_delegation`
+fn find_new_delegations(
+    state: &State,
+    deps: &DepsMut,
+    env: &Env,
+    utoken_to_bond: Uint128,
+) -> Result<(Vec<Delegation>, Vec<Delegation>), StdError> {
+    let (new_delegation, delegations) = find_new_delegation(state, deps, env, utoken_to_bond)?;
+
+    if let Some(split_bond) = state.split_bond.may_load(deps.storage)?.filter(|c| c.enabled) {
+        let denom = state.get_denom(deps.storage)?;
+        let current_delegations =
+            query_all_delegations(&deps.querier, &env.contract.address, &denom)?;
+        let utoken_staked =
+            current_delegations.iter().map(|d| d.amount).sum::<u128>() + utoken_to_bond.u128();
+
+        let validators = state.validators.load(deps.storage)?;
+        let (mut utoken_per_validator, _, _, _) =
+            get_utoken_per_validator(state, deps.storage, utoken_staked, &validators, None)?;
+
+        // only whitelisted validators passing the validator checks receive new delegations
+        let eligible = state.filter_eligible_validators(deps.storage, validators)?;
+        utoken_per_validator.retain(|validator, _| eligible.contains(validator));
+
+        let new_delegations = compute_split_delegations(
+            utoken_to_bond,
+            &current_delegations,
+            &utoken_per_validator,
+            split_bond.max_delegations as usize,
+        );
+        if !new_delegations.is_empty() {
+            return Ok((new_delegations, delegations));
+        }
+    }
+
+    Ok((vec![new_delegation], delegations))
+}
+
 /// searches for the validator with the least amount of delegations
 /// For Uniform mode, searches through the validators list
 /// For Gauge mode, searches for all delegations, and if nothing found, use the first validator from the list.
@@ -308,8 +794,9 @@ fn find_new_delegation(
     state: &State,
     deps: &DepsMut,
     env: &Env,
-    uluna_to_bond: Uint128,
+    utoken_to_bond: Uint128,
 ) -> Result<(Delegation, Vec<Delegation>), StdError> {
+    let denom = state.get_denom(deps.storage)?;
     let delegation_strategy =
         state.delegation_strategy.may_load(deps.storage)?.unwrap_or(DelegationStrategy::Uniform {});
 
@@ -320,9 +807,16 @@ fn find_new_delegation(
         },
         DelegationStrategy::Gauges {
             ..
+        }
+        | DelegationStrategy::Defined {
+            ..
+        }
+        | DelegationStrategy::Hybrid {
+            ..
         } => {
             // if we have gauges, only delegate to validators that have delegations, all others are "inactive"
-            let mut delegations = query_all_delegations(&deps.querier, &env.contract.address)?;
+            let mut delegations =
+                query_all_delegations(&deps.querier, &env.contract.address, &denom)?;
             if delegations.is_empty() {
                 let validators = state.validators.load(deps.storage)?;
 
@@ -335,13 +829,32 @@ fn find_new_delegation(
         },
     };
 
+    // validators failing the validator checks or inactive do not receive new delegations
+    let delegated_validators = delegations.iter().map(|d| d.validator.clone()).collect::<Vec<_>>();
+    let eligible = state.filter_eligible_validators(deps.storage, delegated_validators)?;
+    let mut candidates = delegations.iter().filter(|d| eligible.contains(&d.validator));
+
+    let first = match candidates.next() {
+        Some(first) => first.clone(),
+        None => {
+            // all delegated validators are ineligible, fall back to any eligible validator
+            let validators = state.validators.load(deps.storage)?;
+            let validator = state
+                .filter_eligible_validators(deps.storage, validators)?
+                .into_iter()
+                .next()
+                .ok_or_else(|| StdError::generic_err("No eligible validators"))?;
+            Delegation::new(&validator, 0)
+        },
+    };
+
     // Query the current delegations made to validators, and find the validator with the smallest
     // delegated amount through a linear search
     // The code for linear search is a bit uglier than using `sort_by` but cheaper: O(n) vs O(n * log(n))
-    let mut validator = &delegations[0].validator;
-    let mut amount = delegations[0].amount;
+    let mut validator = &first.validator;
+    let mut amount = first.amount;
 
-    for d in &delegations[1..] {
+    for d in candidates {
         // when using uniform distribution, it is allowed to bond anywhere
         // otherwise bond only in one of the
         if d.amount < amount {
@@ -349,7 +862,7 @@ fn find_new_delegation(
             amount = d.amount;
         }
     }
-    let new_delegation = Delegation::new(validator, uluna_to_bond.u128());
+    let new_delegation = Delegation::new(validator, utoken_to_bond.u128());
 
     Ok((new_delegation, delegations))
 }
@@ -365,24 +878,53 @@ pub fn queue_unbond(
     ustake_to_burn: Uint128,
 ) -> ContractResult {
     let state = State::default();
+    state.assert_not_paused(deps.storage, PausableOperation::QueueUnbond)?;
 
     let mut pending_batch = state.pending_batch.load(deps.storage)?;
     pending_batch.ustake_to_burn += ustake_to_burn;
     state.pending_batch.save(deps.storage, &pending_batch)?;
 
-    state.unbond_requests.update(
-        deps.storage,
-        (pending_batch.id, &receiver),
-        |x| -> StdResult<_> {
-            let mut request = x.unwrap_or_else(|| UnbondRequest {
-                id: pending_batch.id,
-                user: receiver.clone(),
-                shares: Uint128::zero(),
-            });
-            request.shares += ustake_to_burn;
-            Ok(request)
-        },
-    )?;
+    let mut request =
+        match state.unbond_requests.may_load(deps.storage, (pending_batch.id, &receiver))? {
+            Some(request) => request,
+            None => {
+                state.update_storage_metrics(deps.storage, |m| m.unbond_requests += 1)?;
+                UnbondRequest {
+                    id: pending_batch.id,
+                    user: receiver.clone(),
+                    shares: Uint128::zero(),
+                }
+            },
+        };
+    request.shares += ustake_to_burn;
+    state.unbond_requests.save(deps.storage, (pending_batch.id, &receiver), &request)?;
+
+    if state.tracks_history(deps.storage, &receiver)? {
+        // the stake tokens are only burned when the batch is submitted, so the supply still
+        // includes them
+        let ustake_supply = state.get_stake_token(deps.storage)?.query_supply(&deps.querier)?;
+        let denom = state.get_denom(deps.storage)?;
+        let delegations = query_all_delegations(&deps.querier, &env.contract.address, &denom)?;
+        let utoken_buffer = state.liquidity_buffer.may_load(deps.storage)?.unwrap_or_default();
+
+        state.add_user_history(
+            deps.storage,
+            &receiver,
+            UserHistoryItem {
+                id: 0,
+                action: UserHistoryAction::Unbond,
+                utoken: compute_unbond_amount(
+                    ustake_supply,
+                    ustake_to_burn,
+                    &delegations,
+                    utoken_buffer,
+                ),
+                ustake: ustake_to_burn,
+                exchange_rate: compute_exchange_rate(ustake_supply, &delegations, utoken_buffer),
+                time: env.block.time.seconds(),
+            },
+        )?;
+    }
 
     let mut msgs: Vec<CosmosMsg> = vec![];
     let mut start_time = pending_batch.est_unbond_start_time.to_string();
@@ -407,106 +949,332 @@ pub fn queue_unbond(
         .add_attribute("action", "erishub/queue_unbond"))
 }
 
-pub fn submit_batch(deps: DepsMut, env: Env) -> ContractResult {
+/// Removes the user's unbonding request from the pending batch and sends the stake tokens back.
+pub fn cancel_unbond(deps: DepsMut, env: Env, user: Addr, batch_id: u64) -> ContractResult {
     let state = State::default();
-    let stake_token = state.stake_token.load(deps.storage)?;
-    let validators = state.validators.load(deps.storage)?;
-    let unbond_period = state.unbond_period.load(deps.storage)?;
-    let pending_batch = state.pending_batch.load(deps.storage)?;
+    state.assert_not_paused(deps.storage, PausableOperation::QueueUnbond)?;
 
-    let current_time = env.block.time.seconds();
-    if current_time < pending_batch.est_unbond_start_time {
-        return Err(ContractError::SubmitBatchAfter(pending_batch.est_unbond_start_time));
+    let mut pending_batch = state.pending_batch.load(deps.storage)?;
+    if pending_batch.id != batch_id {
+        return Err(ContractError::BatchNotPending(batch_id));
     }
 
-    let delegations = query_all_delegations(&deps.querier, &env.contract.address)?;
-    let ustake_supply = query_cw20_total_supply(&deps.querier, &stake_token)?;
-
-    let uluna_to_unbond =
-        compute_unbond_amount(ustake_supply, pending_batch.ustake_to_burn, &delegations);
-    let new_undelegations =
-        compute_undelegations(&state, deps.storage, uluna_to_unbond, &delegations, validators)?;
+    let request = state
+        .unbond_requests
+        .may_load(deps.storage, (batch_id, &user))?
+        .ok_or_else(|| ContractError::UnbondRequestNotFound(batch_id))?;
 
-    state.previous_batches.save(
-        deps.storage,
-        pending_batch.id,
-        &Batch {
-            id: pending_batch.id,
-            reconciled: false,
-            total_shares: pending_batch.ustake_to_burn,
-            uluna_unclaimed: uluna_to_unbond,
-            est_unbond_end_time: current_time + unbond_period,
-        },
-    )?;
+    state.unbond_requests.remove(deps.storage, (batch_id, &user))?;
+    state.update_storage_metrics(deps.storage, |m| {
+        m.unbond_requests = m.unbond_requests.saturating_sub(1)
+    })?;
 
-    let epoch_period = state.epoch_period.load(deps.storage)?;
-    state.pending_batch.save(
-        deps.storage,
-        &PendingBatch {
-            id: pending_batch.id + 1,
-            ustake_to_burn: Uint128::zero(),
-            est_unbond_start_time: current_time + epoch_period,
-        },
-    )?;
+    pending_batch.ustake_to_burn = pending_batch.ustake_to_burn.checked_sub(request.shares)?;
+    state.pending_batch.save(deps.storage, &pending_batch)?;
 
-    let undelegate_msgs = new_undelegations.iter().map(|d| d.to_cosmos_msg()).collect::<Vec<_>>();
+    let stake_token = state.get_stake_token(deps.storage)?;
+
+    if state.tracks_history(deps.storage, &user)? {
+        // same as for the unbonding, the queued stake tokens are still part of the supply
+        let ustake_supply = stake_token.query_supply(&deps.querier)?;
+        let denom = state.get_denom(deps.storage)?;
+        let delegations = query_all_delegations(&deps.querier, &env.contract.address, &denom)?;
+        let utoken_buffer = state.liquidity_buffer.may_load(deps.storage)?.unwrap_or_default();
+
+        state.add_user_history(
+            deps.storage,
+            &user,
+            UserHistoryItem {
+                id: 0,
+                action: UserHistoryAction::CancelUnbond,
+                utoken: compute_unbond_amount(
+                    ustake_supply,
+                    request.shares,
+                    &delegations,
+                    utoken_buffer,
+                ),
+                ustake: request.shares,
+                exchange_rate: compute_exchange_rate(ustake_supply, &delegations, utoken_buffer),
+                time: env.block.time.seconds(),
+            },
+        )?;
+    }
 
-    let burn_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: stake_token.into(),
-        msg: to_binary(&Cw20ExecuteMsg::Burn {
-            amount: pending_batch.ustake_to_burn,
-        })?,
-        funds: vec![],
-    });
+    let transfer_msg = stake_token.transfer_msg(&user, request.shares)?;
 
-    let event = Event::new("erishub/unbond_submitted")
-        .add_attribute("id", pending_batch.id.to_string())
-        .add_attribute("uluna_unbonded", uluna_to_unbond)
-        .add_attribute("ustake_burned", pending_batch.ustake_to_burn);
+    let event = Event::new("erishub/unbond_cancelled")
+        .add_attribute("id", batch_id.to_string())
+        .add_attribute("user", user)
+        .add_attribute("ustake_returned", request.shares);
 
     Ok(Response::new()
-        .add_messages(undelegate_msgs)
-        .add_message(burn_msg)
-        .add_message(check_received_coin_msg(&deps, &env, None)?)
+        .add_message(transfer_msg)
         .add_event(event)
-        .add_attribute("action", "erishub/unbond"))
+        .add_attribute("action", "erishub/cancel_unbond"))
 }
 
-pub fn reconcile(deps: DepsMut, env: Env) -> ContractResult {
+/// Moves shares of the sender's unbonding request in a batch to the recipient's request in the same
+/// batch. The batch itself is unaffected, as the total amount of shares does not change.
+pub fn transfer_unbond_request(
+    deps: DepsMut,
+    sender: Addr,
+    batch_id: u64,
+    recipient: Addr,
+    shares: Uint128,
+) -> ContractResult {
     let state = State::default();
-    let current_time = env.block.time.seconds();
-
-    // Load batches that have not been reconciled
-    let all_batches = state
-        .previous_batches
-        .idx
-        .reconciled
-        .prefix(false.into())
-        .range(deps.storage, None, None, Order::Ascending)
-        .map(|item| {
-            let (_, v) = item?;
-            Ok(v)
-        })
-        .collect::<StdResult<Vec<_>>>()?;
-
-    let mut batches = all_batches
-        .into_iter()
-        .filter(|b| current_time > b.est_unbond_end_time)
-        .collect::<Vec<_>>();
+    state.assert_not_paused(deps.storage, PausableOperation::QueueUnbond)?;
 
-    let uluna_expected_received: Uint128 = batches.iter().map(|b| b.uluna_unclaimed).sum();
+    if shares.is_zero() {
+        return Err(ContractError::CantBeZero("shares".into()));
+    }
 
-    if uluna_expected_received.is_zero() {
-        return Ok(Response::new());
+    if sender == recipient {
+        return Err(ContractError::CantTransferToSelf {});
     }
 
-    let unlocked_coins = state.unlocked_coins.load(deps.storage)?;
-    let uluna_expected_unlocked = Coins(unlocked_coins).find(CONTRACT_DENOM).amount;
+    let mut request = state
+        .unbond_requests
+        .may_load(deps.storage, (batch_id, &sender))?
+        .ok_or_else(|| ContractError::UnbondRequestNotFound(batch_id))?;
 
-    let uluna_expected = uluna_expected_received + uluna_expected_unlocked;
-    let uluna_actual = deps.querier.query_balance(&env.contract.address, CONTRACT_DENOM)?.amount;
+    if shares > request.shares {
+        return Err(ContractError::NotEnoughShares(request.shares));
+    }
 
-    if uluna_actual >= uluna_expected {
+    // saving and removing through the IndexedMap keeps the `user` index in sync
+    request.shares -= shares;
+    if request.shares.is_zero() {
+        state.unbond_requests.remove(deps.storage, (batch_id, &sender))?;
+        state.update_storage_metrics(deps.storage, |m| {
+            m.unbond_requests = m.unbond_requests.saturating_sub(1)
+        })?;
+    } else {
+        state.unbond_requests.save(deps.storage, (batch_id, &sender), &request)?;
+    }
+
+    let mut recipient_request =
+        match state.unbond_requests.may_load(deps.storage, (batch_id, &recipient))? {
+            Some(request) => request,
+            None => {
+                state.update_storage_metrics(deps.storage, |m| m.unbond_requests += 1)?;
+                UnbondRequest {
+                    id: batch_id,
+                    user: recipient.clone(),
+                    shares: Uint128::zero(),
+                }
+            },
+        };
+    recipient_request.shares += shares;
+    state.unbond_requests.save(deps.storage, (batch_id, &recipient), &recipient_request)?;
+
+    let event = Event::new("erishub/unbond_request_transferred")
+        .add_attribute("id", batch_id.to_string())
+        .add_attribute("sender", sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("shares", shares);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/transfer_unbond_request"))
+}
+
+/// Burns the received Stake token and pays out the utoken value minus the instant unbond fee from
+/// the liquidity buffer. The fee stays in the buffer and therefore benefits all Stake token holders.
+pub fn instant_unbond(
+    deps: DepsMut,
+    env: Env,
+    receiver: Addr,
+    ustake_to_burn: Uint128,
+) -> ContractResult {
+    let state = State::default();
+    // instant unbonding is an exit as well, so it is paused together with unbonding
+    state.assert_not_paused(deps.storage, PausableOperation::QueueUnbond)?;
+    let stake_token = state.get_stake_token(deps.storage)?;
+
+    let config = state
+        .liquidity_buffer_config
+        .may_load(deps.storage)?
+        .ok_or(ContractError::InstantUnbondDisabled {})?;
+    let utoken_buffer = state.liquidity_buffer.may_load(deps.storage)?.unwrap_or_default();
+    let denom = state.get_denom(deps.storage)?;
+
+    let delegations = query_all_delegations(&deps.querier, &env.contract.address, &denom)?;
+    let ustake_supply = stake_token.query_supply(&deps.querier)?;
+
+    let utoken_to_unbond =
+        compute_unbond_amount(ustake_supply, ustake_to_burn, &delegations, utoken_buffer);
+    let utoken_fee = config.instant_unbond_fee.checked_mul_uint(utoken_to_unbond)?;
+    let utoken_to_send = utoken_to_unbond.checked_sub(utoken_fee)?;
+
+    if utoken_to_send.is_zero() {
+        return Err(ContractError::CantBeZero("instant unbond amount".into()));
+    }
+
+    if utoken_to_send > utoken_buffer {
+        return Err(ContractError::NotEnoughLiquidity(utoken_buffer));
+    }
+
+    state.liquidity_buffer.save(deps.storage, &(utoken_buffer - utoken_to_send))?;
+
+    let burn_msg = stake_token.burn_msg(&env.contract.address, ustake_to_burn)?;
+
+    let send_msg = CosmosMsg::Bank(BankMsg::Send {
+        to_address: receiver.to_string(),
+        amount: vec![Coin::new(utoken_to_send.u128(), denom)],
+    });
+
+    let event = Event::new("erishub/instant_unbonded")
+        .add_attribute("receiver", receiver)
+        .add_attribute("ustake_burned", ustake_to_burn)
+        .add_attribute("utoken_unbonded", utoken_to_send)
+        .add_attribute("utoken_fee", utoken_fee);
+
+    Ok(Response::new()
+        .add_message(burn_msg)
+        .add_message(send_msg)
+        .add_event(event)
+        .add_attribute("action", "erishub/instant_unbond"))
+}
+
+pub fn submit_batch(mut deps: DepsMut, env: Env, sender: Addr) -> ContractResult {
+    let state = State::default();
+    state.assert_not_paused(deps.storage, PausableOperation::SubmitBatch)?;
+    let stake_token = state.get_stake_token(deps.storage)?;
+    let validators = state.validators.load(deps.storage)?;
+    let unbond_period = state.unbond_period.load(deps.storage)?;
+    let pending_batch = state.pending_batch.load(deps.storage)?;
+    let denom = state.get_denom(deps.storage)?;
+
+    let current_time = env.block.time.seconds();
+    if current_time < pending_batch.est_unbond_start_time {
+        return Err(ContractError::SubmitBatchAfter(pending_batch.est_unbond_start_time));
+    }
+
+    let response = check_slashing(&state, &mut deps, &env, false)?;
+    let keeper_attrs = claim_keeper_reward(&state, &mut deps, &env, "submit_batch", &sender)?;
+
+    let delegations = query_all_delegations(&deps.querier, &env.contract.address, &denom)?;
+    let ustake_supply = stake_token.query_supply(&deps.querier)?;
+    let utoken_buffer = state.liquidity_buffer.may_load(deps.storage)?.unwrap_or_default();
+
+    // the share of the liquidity buffer is unbonded from the delegations, the buffer stays liquid
+    let utoken_to_unbond = compute_unbond_amount(
+        ustake_supply,
+        pending_batch.ustake_to_burn,
+        &delegations,
+        utoken_buffer,
+    );
+    let full_validators = state.full_validators(deps.storage, current_time)?;
+    let new_undelegations = compute_undelegations(
+        &state,
+        deps.storage,
+        utoken_to_unbond,
+        &delegations,
+        validators,
+        &full_validators,
+    )?;
+
+    state.previous_batches.save(
+        deps.storage,
+        pending_batch.id,
+        &Batch {
+            id: pending_batch.id,
+            reconciled: false,
+            total_shares: pending_batch.ustake_to_burn,
+            utoken_unclaimed: utoken_to_unbond,
+            est_unbond_end_time: current_time + unbond_period,
+        },
+    )?;
+    state.update_storage_metrics(deps.storage, |m| m.previous_batches += 1)?;
+
+    let epoch_period = state.epoch_period.load(deps.storage)?;
+    state.pending_batch.save(
+        deps.storage,
+        &PendingBatch {
+            id: pending_batch.id + 1,
+            ustake_to_burn: Uint128::zero(),
+            est_unbond_start_time: current_time + epoch_period,
+        },
+    )?;
+
+    for undelegation in &new_undelegations {
+        state.sub_expected_delegation(
+            deps.storage,
+            &undelegation.validator,
+            undelegation.amount,
+        )?;
+        state.add_unbonding_entry(
+            deps.storage,
+            &undelegation.validator,
+            current_time,
+            current_time + unbond_period,
+        )?;
+    }
+
+    let undelegate_msgs =
+        new_undelegations.iter().map(|d| d.to_cosmos_msg(&denom)).collect::<Vec<_>>();
+
+    let burn_msg = stake_token.burn_msg(&env.contract.address, pending_batch.ustake_to_burn)?;
+
+    let event = Event::new("erishub/unbond_submitted")
+        .add_attribute("id", pending_batch.id.to_string())
+        .add_attribute("utoken_unbonded", utoken_to_unbond)
+        .add_attribute("ustake_burned", pending_batch.ustake_to_burn);
+
+    Ok(response
+        .add_messages(undelegate_msgs)
+        .add_message(burn_msg)
+        .add_message(check_received_coin_msg(&deps, &env, None)?)
+        .add_event(event)
+        .add_attribute("action", "erishub/unbond")
+        .add_attributes(keeper_attrs))
+}
+
+pub fn reconcile(mut deps: DepsMut, env: Env, sender: Addr) -> ContractResult {
+    let state = State::default();
+    let current_time = env.block.time.seconds();
+
+    let response = check_slashing(&state, &mut deps, &env, true)?;
+    let response = response.add_attributes(claim_keeper_reward(
+        &state,
+        &mut deps,
+        &env,
+        "reconcile",
+        &sender,
+    )?);
+
+    // Load batches that have not been reconciled
+    let all_batches = state
+        .previous_batches
+        .idx
+        .reconciled
+        .prefix(false.into())
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let mut batches = all_batches
+        .into_iter()
+        .filter(|b| current_time > b.est_unbond_end_time)
+        .collect::<Vec<_>>();
+
+    let utoken_expected_received: Uint128 = batches.iter().map(|b| b.utoken_unclaimed).sum();
+
+    if utoken_expected_received.is_zero() {
+        return Ok(response);
+    }
+
+    let denom = state.get_denom(deps.storage)?;
+    let unlocked_coins = state.unlocked_coins.load(deps.storage)?;
+    let utoken_expected_unlocked = Coins(unlocked_coins).find(&denom).amount;
+    let utoken_buffer = state.liquidity_buffer.may_load(deps.storage)?.unwrap_or_default();
+
+    let utoken_expected = utoken_expected_received + utoken_expected_unlocked + utoken_buffer;
+    let utoken_actual = deps.querier.query_balance(&env.contract.address, denom)?.amount;
+
+    if utoken_actual >= utoken_expected {
         mark_reconciled_batches(&mut batches);
         for batch in &batches {
             state.previous_batches.save(deps.storage, batch.id, batch)?;
@@ -514,13 +1282,13 @@ pub fn reconcile(deps: DepsMut, env: Env) -> ContractResult {
         let ids = batches.iter().map(|b| b.id.to_string()).collect::<Vec<_>>().join(",");
         let event = Event::new("erishub/reconciled")
             .add_attribute("ids", ids)
-            .add_attribute("uluna_deducted", "0");
-        return Ok(Response::new().add_event(event).add_attribute("action", "erishub/reconcile"));
+            .add_attribute("utoken_deducted", "0");
+        return Ok(response.add_event(event).add_attribute("action", "erishub/reconcile"));
     }
 
-    let uluna_to_deduct = uluna_expected - uluna_actual;
+    let utoken_to_deduct = utoken_expected - utoken_actual;
 
-    reconcile_batches(&mut batches, uluna_to_deduct);
+    reconcile_batches(&mut batches, utoken_to_deduct);
 
     for batch in &batches {
         state.previous_batches.save(deps.storage, batch.id, batch)?;
@@ -530,13 +1298,318 @@ pub fn reconcile(deps: DepsMut, env: Env) -> ContractResult {
 
     let event = Event::new("erishub/reconciled")
         .add_attribute("ids", ids)
-        .add_attribute("uluna_deducted", uluna_to_deduct.to_string());
+        .add_attribute("utoken_deducted", utoken_to_deduct.to_string());
 
-    Ok(Response::new().add_event(event).add_attribute("action", "erishub/reconcile"))
+    Ok(response.add_event(event).add_attribute("action", "erishub/reconcile"))
+}
+
+/// Compares the current delegations with the expected delegations. Every validator holding less
+/// than expected is recorded as slashed and, if configured, removed from the validator set.
+/// Afterwards the expected delegations are synced with the current delegations.
+///
+/// Removing a validator redelegates its funds, which would conflict with the messages of the other
+/// operations. Unless `allow_removal` is set, slashed validators that are going to be removed are
+/// skipped and left for the next reconcile.
+fn check_slashing(
+    state: &State,
+    deps: &mut DepsMut,
+    env: &Env,
+    allow_removal: bool,
+) -> ContractResult {
+    let current_time = env.block.time.seconds();
+    let denom = state.get_denom(deps.storage)?;
+    let delegations = query_all_delegations(&deps.querier, &env.contract.address, &denom)?;
+    let expected_delegations = state
+        .delegations_snapshot
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let remove_slashed =
+        state.remove_slashed_validators.may_load(deps.storage)?.unwrap_or_default();
+    let whitelisted = state.validators.load(deps.storage)?;
+
+    let mut slashed = vec![];
+    let mut skipped = vec![];
+    for (validator, utoken_expected) in expected_delegations {
+        let utoken_actual = delegations
+            .iter()
+            .find(|d| d.validator == validator)
+            .map(|d| d.amount)
+            .unwrap_or_default();
+
+        let utoken_slashed = utoken_expected.u128().saturating_sub(utoken_actual);
+        if utoken_slashed > SLASHING_TOLERANCE {
+            if !allow_removal && remove_slashed && whitelisted.contains(&validator) {
+                skipped.push(validator);
+                continue;
+            }
+            slashed.push((validator.clone(), utoken_expected, Uint128::new(utoken_slashed)));
+        }
+
+        if utoken_actual == 0 {
+            state.delegations_snapshot.remove(deps.storage, &validator);
+        }
+    }
+
+    for delegation in delegations.iter().filter(|d| d.amount > 0) {
+        if skipped.contains(&delegation.validator) {
+            continue;
+        }
+        state.delegations_snapshot.save(
+            deps.storage,
+            &delegation.validator,
+            &Uint128::new(delegation.amount),
+        )?;
+    }
+
+    let mut next_id = state
+        .slashing_events
+        .keys(deps.storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map_or(1, |id| id + 1);
+
+    let mut events = vec![];
+    let mut new_redelegations = vec![];
+    for (validator, utoken_expected, utoken_slashed) in slashed {
+        let validators = state.validators.load(deps.storage)?;
+        // the last validator can't be removed, as the delegations need to go somewhere
+        let removed = remove_slashed && validators.len() > 1 && validators.contains(&validator);
+        let mut deferred = vec![];
+        if removed {
+            let (redelegations, deferred_redelegations) =
+                remove_from_validator_set(state, deps, env, &validator)?;
+            new_redelegations.extend(redelegations);
+            deferred = deferred_redelegations;
+        }
+
+        state.slashing_events.save(
+            deps.storage,
+            next_id,
+            &SlashingEvent {
+                id: next_id,
+                validator: validator.clone(),
+                time: current_time,
+                utoken_expected,
+                utoken_slashed,
+                removed,
+            },
+        )?;
+        next_id += 1;
+
+        events.push(
+            Event::new("erishub/slashed")
+                .add_attribute("validator", validator)
+                .add_attribute("utoken_expected", utoken_expected)
+                .add_attribute("utoken_slashed", utoken_slashed)
+                .add_attribute("removed", removed.to_string())
+                .add_attributes(deferred_attributes(&deferred)),
+        );
+    }
+
+    let redelegate_msgs =
+        new_redelegations.iter().map(|rd| rd.to_cosmos_msg(&denom)).collect::<Vec<_>>();
+    let check_msg = if !redelegate_msgs.is_empty() {
+        // only check coins if a redelegation is happening
+        Some(check_received_coin_msg(deps, env, None)?)
+    } else {
+        None
+    };
+
+    Ok(Response::new()
+        .add_messages(redelegate_msgs)
+        .add_optional_message(check_msg)
+        .add_events(events))
 }
 
 pub fn withdraw_unbonded(deps: DepsMut, env: Env, user: Addr, receiver: Addr) -> ContractResult {
     let state = State::default();
+    let denom = state.get_denom(deps.storage)?;
+
+    let (total_utoken_to_refund, ids) =
+        withdraw_finished_requests(&state, deps.storage, &env, &user)?;
+
+    if total_utoken_to_refund.is_zero() {
+        return Err(ContractError::CantBeZero("withdrawable amount".into()));
+    }
+
+    let refund_msg = CosmosMsg::Bank(BankMsg::Send {
+        to_address: receiver.clone().into(),
+        amount: vec![Coin::new(total_utoken_to_refund.u128(), &denom)],
+    });
+
+    let event = Event::new("erishub/unbonded_withdrawn")
+        .add_attribute("ids", ids.join(","))
+        .add_attribute("user", user)
+        .add_attribute("receiver", receiver)
+        .add_attribute("utoken_refunded", total_utoken_to_refund);
+
+    Ok(Response::new()
+        .add_message(refund_msg)
+        .add_event(event)
+        .add_attribute("action", "erishub/withdraw_unbonded"))
+}
+
+/// Pays out the finished unbonding requests of the users to themselves. Users without anything
+/// to withdraw are skipped, so that they don't block the others.
+pub fn withdraw_unbonded_for(deps: DepsMut, env: Env, mut users: Vec<String>) -> ContractResult {
+    let state = State::default();
+    let denom = state.get_denom(deps.storage)?;
+
+    dedupe(&mut users);
+    if users.len() > MAX_WITHDRAW_FOR_USERS {
+        return Err(ContractError::TooManyUsers(MAX_WITHDRAW_FOR_USERS));
+    }
+
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    let mut events: Vec<Event> = vec![];
+    for user in users {
+        let user = deps.api.addr_validate(&user)?;
+        let (total_utoken_to_refund, ids) =
+            withdraw_finished_requests(&state, deps.storage, &env, &user)?;
+
+        if total_utoken_to_refund.is_zero() {
+            continue;
+        }
+
+        msgs.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: user.to_string(),
+            amount: vec![Coin::new(total_utoken_to_refund.u128(), &denom)],
+        }));
+
+        events.push(
+            Event::new("erishub/unbonded_withdrawn")
+                .add_attribute("ids", ids.join(","))
+                .add_attribute("user", user.clone())
+                .add_attribute("receiver", user)
+                .add_attribute("utoken_refunded", total_utoken_to_refund),
+        );
+    }
+
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_events(events)
+        .add_attribute("action", "erishub/withdraw_unbonded_for"))
+}
+
+/// Goes through the reconciled batches that finished unbonding more than the grace period ago.
+/// Requests worth at most the dust threshold are removed and their utoken is sent to the protocol
+/// fee contract. Batches without any shares left are removed.
+pub fn prune_batches(
+    deps: DepsMut,
+    env: Env,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> ContractResult {
+    let state = State::default();
+    state.assert_not_paused(deps.storage, PausableOperation::PruneBatches)?;
+    let current_time = env.block.time.seconds();
+
+    let (threshold, grace_period) = match state.dust_cleanup.may_load(deps.storage)? {
+        Some(config) if !config.threshold.is_zero() && config.grace_period > 0 => {
+            (config.threshold, config.grace_period)
+        },
+        _ => return Err(ContractError::DustCleanupNotConfigured {}),
+    };
+
+    let limit = limit.unwrap_or(MAX_PRUNE_BATCHES).min(MAX_PRUNE_BATCHES) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let batches = state
+        .previous_batches
+        .idx
+        .reconciled
+        .prefix(true.into())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect::<StdResult<Vec<Batch>>>()?;
+
+    let mut utoken_swept = Uint128::zero();
+    let mut requests_removed: u64 = 0;
+    let mut batches_removed: Vec<String> = vec![];
+    for mut batch in batches {
+        if batch.est_unbond_end_time.saturating_add(grace_period) >= current_time {
+            continue;
+        }
+
+        let requests = state
+            .unbond_requests
+            .prefix(batch.id)
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| {
+                let (_, v) = item?;
+                Ok(v)
+            })
+            .collect::<StdResult<Vec<UnbondRequest>>>()?;
+
+        let mut changed = false;
+        for request in requests {
+            let utoken = if batch.total_shares.is_zero() {
+                Uint128::zero()
+            } else {
+                batch.utoken_unclaimed.multiply_ratio(request.shares, batch.total_shares)
+            };
+            if utoken > threshold {
+                continue;
+            }
+
+            batch.total_shares = batch.total_shares.checked_sub(request.shares)?;
+            batch.utoken_unclaimed = batch.utoken_unclaimed.checked_sub(utoken)?;
+            utoken_swept += utoken;
+            requests_removed += 1;
+            changed = true;
+
+            state.unbond_requests.remove(deps.storage, (batch.id, &request.user))?;
+        }
+
+        if batch.total_shares.is_zero() {
+            // rounding leftovers of the batch are swept as well
+            utoken_swept += batch.utoken_unclaimed;
+            batches_removed.push(batch.id.to_string());
+            state.previous_batches.remove(deps.storage, batch.id)?;
+        } else if changed {
+            state.previous_batches.save(deps.storage, batch.id, &batch)?;
+        }
+    }
+
+    state.update_storage_metrics(deps.storage, |m| {
+        m.previous_batches = m.previous_batches.saturating_sub(batches_removed.len() as u64);
+        m.unbond_requests = m.unbond_requests.saturating_sub(requests_removed);
+    })?;
+
+    let sweep_msg = if utoken_swept.is_zero() {
+        None
+    } else {
+        let fee_config = state.fee_config.load(deps.storage)?;
+        let denom = state.get_denom(deps.storage)?;
+        Some(CosmosMsg::Bank(BankMsg::Send {
+            to_address: fee_config.protocol_fee_contract.into(),
+            amount: vec![Coin::new(utoken_swept.u128(), denom)],
+        }))
+    };
+
+    let event = Event::new("erishub/batches_pruned")
+        .add_attribute("batches_removed", batches_removed.join(","))
+        .add_attribute("requests_removed", requests_removed.to_string())
+        .add_attribute("utoken_swept", utoken_swept);
+
+    Ok(Response::new()
+        .add_optional_message(sweep_msg)
+        .add_event(event)
+        .add_attribute("action", "erishub/prune_batches"))
+}
+
+/// Removes the user's requests in batches that are reconciled and have finished unbonding,
+/// returning the amount of utoken to refund and the ids of the batches
+fn withdraw_finished_requests(
+    state: &State,
+    storage: &mut dyn Storage,
+    env: &Env,
+    user: &Addr,
+) -> StdResult<(Uint128, Vec<String>)> {
     let current_time = env.block.time.seconds();
 
     // NOTE: If the user has too many unclaimed requests, this may not fit in the WASM memory...
@@ -547,7 +1620,7 @@ pub fn withdraw_unbonded(deps: DepsMut, env: Env, user: Addr, receiver: Addr) ->
         .idx
         .user
         .prefix(user.to_string())
-        .range(deps.storage, None, None, Order::Ascending)
+        .range(storage, None, None, Order::Ascending)
         .map(|item| {
             let (_, v) = item?;
             Ok(v)
@@ -560,61 +1633,209 @@ pub fn withdraw_unbonded(deps: DepsMut, env: Env, user: Addr, receiver: Addr) ->
     // - has finished unbonding
     // If not sure whether the batches have been reconciled, the user should first invoke `ExecuteMsg::Reconcile`
     // before withdrawing.
-    let mut total_uluna_to_refund = Uint128::zero();
+    let mut total_utoken_to_refund = Uint128::zero();
+    let mut total_ustake_redeemed = Uint128::zero();
     let mut ids: Vec<String> = vec![];
+    let mut batches_removed: u64 = 0;
     for request in &requests {
-        if let Ok(mut batch) = state.previous_batches.load(deps.storage, request.id) {
+        if let Ok(mut batch) = state.previous_batches.load(storage, request.id) {
             if batch.reconciled && batch.est_unbond_end_time < current_time {
-                let uluna_to_refund =
-                    batch.uluna_unclaimed.multiply_ratio(request.shares, batch.total_shares);
+                let utoken_to_refund =
+                    batch.utoken_unclaimed.multiply_ratio(request.shares, batch.total_shares);
 
                 ids.push(request.id.to_string());
 
-                total_uluna_to_refund += uluna_to_refund;
+                total_utoken_to_refund += utoken_to_refund;
+                total_ustake_redeemed += request.shares;
                 batch.total_shares -= request.shares;
-                batch.uluna_unclaimed -= uluna_to_refund;
+                batch.utoken_unclaimed -= utoken_to_refund;
 
                 if batch.total_shares.is_zero() {
-                    state.previous_batches.remove(deps.storage, request.id)?;
+                    state.previous_batches.remove(storage, request.id)?;
+                    batches_removed += 1;
                 } else {
-                    state.previous_batches.save(deps.storage, batch.id, &batch)?;
+                    state.previous_batches.save(storage, batch.id, &batch)?;
                 }
 
-                state.unbond_requests.remove(deps.storage, (request.id, &user))?;
+                state.unbond_requests.remove(storage, (request.id, user))?;
             }
         }
     }
 
-    if total_uluna_to_refund.is_zero() {
-        return Err(ContractError::CantBeZero("withdrawable amount".into()));
+    if !ids.is_empty() {
+        state.update_storage_metrics(storage, |m| {
+            m.previous_batches = m.previous_batches.saturating_sub(batches_removed);
+            m.unbond_requests = m.unbond_requests.saturating_sub(ids.len() as u64);
+        })?;
+    }
+
+    if !total_utoken_to_refund.is_zero() && state.tracks_history(storage, user)? {
+        state.add_user_history(
+            storage,
+            user,
+            UserHistoryItem {
+                id: 0,
+                action: UserHistoryAction::Withdraw,
+                utoken: total_utoken_to_refund,
+                ustake: total_ustake_redeemed,
+                exchange_rate: Decimal::from_ratio(total_utoken_to_refund, total_ustake_redeemed),
+                time: current_time,
+            },
+        )?;
+    }
+
+    Ok((total_utoken_to_refund, ids))
+}
+
+/// Appends the overdue maintenance to the response of a user interaction, if enabled. It is run
+/// within the configured gas limit and failing does not revert the user interaction.
+pub fn add_auto_maintenance(
+    deps: Deps,
+    env: &Env,
+    keeper: Addr,
+    response: Response,
+) -> ContractResult {
+    let state = State::default();
+    let config = match state.auto_maintenance.may_load(deps.storage)? {
+        Some(config) if config.enabled => config,
+        _ => return Ok(response),
+    };
+
+    if overdue_maintenance(&state, deps, env, config.harvest_interval)?.is_empty() {
+        return Ok(response);
+    }
+
+    let maintenance_msg = SubMsg::reply_on_error(
+        CallbackMsg::AutoMaintenance {
+            keeper,
+        }
+        .into_cosmos_msg(&env.contract.address)?,
+        AUTO_MAINTENANCE_REPLY_ID,
+    )
+    .with_gas_limit(config.gas_limit);
+
+    Ok(response.add_submessage(maintenance_msg))
+}
+
+/// Runs each overdue maintenance task on its own, so that a failing task does not prevent the
+/// others. The keeper receives the keeper tip if rewards are harvested.
+pub fn auto_maintenance(deps: DepsMut, env: Env, keeper: Addr) -> ContractResult {
+    let state = State::default();
+    let config = state.auto_maintenance.load(deps.storage)?;
+
+    let tasks = overdue_maintenance(&state, deps.as_ref(), &env, config.harvest_interval)?;
+
+    if !config.keeper_tip.is_zero()
+        && tasks.iter().any(|(_, _, msg)| *msg == ExecuteMsg::Harvest {})
+    {
+        state.keeper.save(deps.storage, &keeper)?;
+    }
+
+    let task_names = tasks.iter().map(|(name, _, _)| *name).collect::<Vec<_>>();
+    let event = Event::new("erishub/auto_maintenance")
+        .add_attribute("keeper", keeper)
+        .add_attribute("tasks", task_names.join(","));
+
+    let task_msgs = tasks
+        .into_iter()
+        .map(|(_, reply_id, msg)| {
+            Ok(SubMsg::reply_on_error(
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: env.contract.address.to_string(),
+                    msg: to_binary(&msg)?,
+                    funds: vec![],
+                }),
+                reply_id,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(Response::new()
+        .add_submessages(task_msgs)
+        .add_event(event)
+        .add_attribute("action", "erishub/auto_maintenance"))
+}
+
+/// Failing maintenance is only reported, the keeper of a failed harvest is not tipped
+pub fn auto_maintenance_failed(deps: DepsMut, task: &str, error: String) -> ContractResult {
+    if task == "harvest" {
+        State::default().keeper.remove(deps.storage);
+    }
+
+    let event = Event::new("erishub/auto_maintenance_failed")
+        .add_attribute("task", task)
+        .add_attribute("error", error);
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/auto_maintenance_failed"))
+}
+
+/// Returns the maintenance tasks that are overdue and not paused
+fn overdue_maintenance(
+    state: &State,
+    deps: Deps,
+    env: &Env,
+    harvest_interval: u64,
+) -> StdResult<Vec<(&'static str, u64, ExecuteMsg)>> {
+    let current_time = env.block.time.seconds();
+    let mut tasks = vec![];
+
+    let pause_flags = state.pause_flags.may_load(deps.storage)?.unwrap_or_default();
+
+    let pending_batch = state.pending_batch.load(deps.storage)?;
+    if current_time >= pending_batch.est_unbond_start_time
+        && !pending_batch.ustake_to_burn.is_zero()
+        && !pause_flags.is_paused(&PausableOperation::SubmitBatch)
+    {
+        tasks.push(("submit_batch", SUBMIT_BATCH_REPLY_ID, ExecuteMsg::SubmitBatch {}));
     }
 
-    let refund_msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: receiver.clone().into(),
-        amount: vec![Coin::new(total_uluna_to_refund.u128(), CONTRACT_DENOM)],
-    });
+    let unbonded_batches = state
+        .previous_batches
+        .idx
+        .reconciled
+        .prefix(false.into())
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, batch)| batch))
+        .filter(|batch| batch.as_ref().map_or(true, |b| current_time > b.est_unbond_end_time))
+        .collect::<StdResult<Vec<_>>>()?;
+    if !unbonded_batches.is_empty() {
+        tasks.push(("reconcile", RECONCILE_REPLY_ID, ExecuteMsg::Reconcile {}));
+    }
 
-    let event = Event::new("erishub/unbonded_withdrawn")
-        .add_attribute("ids", ids.join(","))
-        .add_attribute("user", user)
-        .add_attribute("receiver", receiver)
-        .add_attribute("uluna_refunded", total_uluna_to_refund);
+    let last_harvest = state.last_harvest.may_load(deps.storage)?.unwrap_or_default();
+    if current_time >= last_harvest + harvest_interval
+        && !pause_flags.is_paused(&PausableOperation::Harvest)
+    {
+        tasks.push(("harvest", HARVEST_REPLY_ID, ExecuteMsg::Harvest {}));
+    }
 
-    Ok(Response::new()
-        .add_message(refund_msg)
-        .add_event(event)
-        .add_attribute("action", "erishub/withdraw_unbonded"))
+    Ok(tasks)
 }
 
-pub fn tune_delegations(deps: DepsMut, env: Env, sender: Addr) -> ContractResult {
+pub fn tune_delegations(mut deps: DepsMut, env: Env, sender: Addr) -> ContractResult {
     let state = State::default();
+    let denom = state.get_denom(deps.storage)?;
 
     state.assert_owner(deps.storage, &sender)?;
 
+    let response = check_slashing(&state, &mut deps, &env, false)?;
+
+    // validators failing the validator checks are not part of the goal anymore, so their
+    // delegations are moved to the eligible validators. Inactive validators are left out of the
+    // goal as well, but keep their delegations.
+    let validators = state.validators.load(deps.storage)?;
+    let (ineligible, inactive) =
+        query_ineligible_validators(&state, deps.storage, &deps.querier, &validators)?;
+    state
+        .ineligible_validators
+        .save(deps.storage, &ineligible.iter().map(|(v, _)| v.clone()).collect())?;
+    state.inactive_validators.save(deps.storage, &inactive)?;
+    let eligible_validators = state.filter_eligible_validators(deps.storage, validators)?;
+
     let (wanted_delegations, save) =
         get_wanted_delegations(&state, &env, deps.storage, &deps.querier, TuneInfoGaugeLoader {})?;
 
-    let attributes = if save {
+    let mut attributes = if save {
         state.delegation_goal.save(deps.storage, &wanted_delegations)?;
 
         wanted_delegations
@@ -628,7 +1849,58 @@ pub fn tune_delegations(deps: DepsMut, env: Env, sender: Addr) -> ContractResult
         vec![]
     };
 
-    Ok(Response::new()
+    let (delegations_to_remove, delegations): (Vec<_>, Vec<_>) =
+        query_all_delegations(&deps.querier, &env.contract.address, &denom)?
+            .into_iter()
+            .partition(|d| ineligible.iter().any(|(v, _)| *v == d.validator));
+    let mut delegations =
+        delegations.into_iter().filter(|d| !inactive.contains(&d.validator)).collect::<Vec<_>>();
+
+    let locked_validators = state.locked_validators(deps.storage, env.block.time.seconds())?;
+    let mut new_redelegations: Vec<Redelegation> = vec![];
+    let mut deferred: Vec<Delegation> = vec![];
+    for delegation_to_remove in delegations_to_remove {
+        let (redelegations, deferred_redelegations) = compute_redelegations_for_removal(
+            &state,
+            deps.storage,
+            &delegation_to_remove,
+            &delegations,
+            eligible_validators.clone(),
+            &locked_validators,
+        )?;
+        deferred.extend(deferred_redelegations);
+
+        for rd in &redelegations {
+            match delegations.iter_mut().find(|d| d.validator == rd.dst) {
+                Some(d) => d.amount += rd.amount,
+                None => delegations.push(Delegation::new(&rd.dst, rd.amount)),
+            }
+        }
+        new_redelegations.extend(redelegations);
+    }
+
+    save_expected_redelegations(&state, deps.storage, &env, &new_redelegations)?;
+
+    attributes.extend(
+        ineligible
+            .into_iter()
+            .map(|(v, reason)| attr("ineligible_validator", format!("{0}={1}", v, reason))),
+    );
+    attributes.extend(inactive.into_iter().map(|v| attr("inactive_validator", v)));
+    attributes.extend(deferred_attributes(&deferred));
+
+    let redelegate_msgs =
+        new_redelegations.iter().map(|rd| rd.to_cosmos_msg(&denom)).collect::<Vec<_>>();
+    let check_msg = if !redelegate_msgs.is_empty() {
+        // only check coins if a redelegation is happening
+        Some(check_received_coin_msg(&deps, &env, None)?)
+    } else {
+        None
+    };
+
+    Ok(response
+        .add_messages(redelegate_msgs)
+        .add_optional_message(check_msg)
         .add_attribute("action", "erishub/tune_delegations")
         .add_attributes(attributes))
 }
@@ -638,30 +1910,46 @@ pub fn tune_delegations(deps: DepsMut, env: Env, sender: Addr) -> ContractResult
 //--------------------------------------------------------------------------------------------------
 
 pub fn rebalance(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     sender: Addr,
     min_redelegation: Option<Uint128>,
 ) -> ContractResult {
     let state = State::default();
     state.assert_owner(deps.storage, &sender)?;
+    state.assert_not_paused(deps.storage, PausableOperation::Rebalance)?;
+
+    let response = check_slashing(&state, &mut deps, &env, false)?;
 
-    let delegations = query_all_delegations(&deps.querier, &env.contract.address)?;
+    let denom = state.get_denom(deps.storage)?;
+    let delegations = query_all_delegations(&deps.querier, &env.contract.address, &denom)?;
     let validators = state.validators.load(deps.storage)?;
 
     let min_redelegation = min_redelegation.unwrap_or_default();
+    let locked_validators = state.locked_validators(deps.storage, env.block.time.seconds())?;
 
-    let new_redelegations =
-        compute_redelegations_for_rebalancing(&state, deps.storage, &delegations, validators)?
-            .into_iter()
-            .filter(|redelegation| redelegation.amount >= min_redelegation.u128())
-            .collect::<Vec<_>>();
+    let (new_redelegations, deferred) = compute_redelegations_for_rebalancing(
+        &state,
+        deps.storage,
+        &delegations,
+        validators,
+        &locked_validators,
+    )?;
+    let new_redelegations = new_redelegations
+        .into_iter()
+        .filter(|redelegation| redelegation.amount >= min_redelegation.u128())
+        .collect::<Vec<_>>();
+
+    save_expected_redelegations(&state, deps.storage, &env, &new_redelegations)?;
 
-    let redelegate_msgs = new_redelegations.iter().map(|rd| rd.to_cosmos_msg()).collect::<Vec<_>>();
+    let redelegate_msgs =
+        new_redelegations.iter().map(|rd| rd.to_cosmos_msg(&denom)).collect::<Vec<_>>();
 
     let amount: u128 = new_redelegations.iter().map(|rd| rd.amount).sum();
 
-    let event = Event::new("erishub/rebalanced").add_attribute("uluna_moved", amount.to_string());
+    let event = Event::new("erishub/rebalanced")
+        .add_attribute("utoken_moved", amount.to_string())
+        .add_attributes(deferred_attributes(&deferred));
 
     let check_msg = if !redelegate_msgs.is_empty() {
         // only check coins if a redelegation is happening
@@ -670,7 +1958,7 @@ pub fn rebalance(
         None
     };
 
-    Ok(Response::new()
+    Ok(response
         .add_messages(redelegate_msgs)
         .add_optional_message(check_msg)
         .add_event(event)
@@ -683,6 +1971,27 @@ pub fn add_validator(deps: DepsMut, sender: Addr, validator: String) -> Contract
     state.assert_owner(deps.storage, &sender)?;
     assert_validator_exists(&deps.querier, &validator)?;
 
+    let (ineligible, inactive) = query_ineligible_validators(
+        &state,
+        deps.storage,
+        &deps.querier,
+        std::slice::from_ref(&validator),
+    )?;
+    if let Some((_, reason)) = ineligible.into_iter().next() {
+        return Err(ContractError::ValidatorNotEligible(validator, reason));
+    }
+    if !inactive.is_empty() {
+        return Err(ContractError::ValidatorNotEligible(validator, "not active".into()));
+    }
+
+    // the validator passed the checks, results of an earlier check are outdated
+    for list in [&state.ineligible_validators, &state.inactive_validators] {
+        if let Some(mut validators) = list.may_load(deps.storage)? {
+            validators.retain(|v| *v != validator);
+            list.save(deps.storage, &validators)?;
+        }
+    }
+
     state.validators.update(deps.storage, |mut validators| {
         if validators.contains(&validator) {
             return Err(ContractError::ValidatorAlreadyWhitelisted(validator.clone()));
@@ -697,7 +2006,7 @@ pub fn add_validator(deps: DepsMut, sender: Addr, validator: String) -> Contract
 }
 
 pub fn remove_validator(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     sender: Addr,
     validator: String,
@@ -705,10 +2014,42 @@ pub fn remove_validator(
     let state = State::default();
 
     state.assert_owner(deps.storage, &sender)?;
+    let denom = state.get_denom(deps.storage)?;
+
+    let (new_redelegations, deferred) =
+        remove_from_validator_set(&state, &mut deps, &env, &validator)?;
+    let redelegate_msgs =
+        new_redelegations.iter().map(|d| d.to_cosmos_msg(&denom)).collect::<Vec<_>>();
+
+    let event = Event::new("erishub/validator_removed")
+        .add_attribute("validator", validator)
+        .add_attributes(deferred_attributes(&deferred));
+
+    let check_msg = if !redelegate_msgs.is_empty() {
+        // only check coins if a redelegation is happening
+        Some(check_received_coin_msg(&deps, &env, None)?)
+    } else {
+        None
+    };
+
+    Ok(Response::new()
+        .add_messages(redelegate_msgs)
+        .add_optional_message(check_msg)
+        .add_event(event)
+        .add_attribute("action", "erishub/remove_validator"))
+}
 
+/// Removes the validator from the whitelist and returns the redelegations moving its delegation to
+/// the remaining validators, together with the delegations deferred due to a redelegation lock.
+fn remove_from_validator_set(
+    state: &State,
+    deps: &mut DepsMut,
+    env: &Env,
+    validator: &str,
+) -> Result<(Vec<Redelegation>, Vec<Delegation>), ContractError> {
     let validators = state.validators.update(deps.storage, |mut validators| {
-        if !validators.contains(&validator) {
-            return Err(ContractError::ValidatorNotWhitelisted(validator.clone()));
+        if !validators.iter().any(|v| v == validator) {
+            return Err(ContractError::ValidatorNotWhitelisted(validator.to_string()));
         }
         validators.retain(|v| *v != validator);
         Ok(validators)
@@ -717,44 +2058,147 @@ pub fn remove_validator(
     let delegation_strategy =
         state.delegation_strategy.may_load(deps.storage)?.unwrap_or(DelegationStrategy::Uniform);
 
-    let redelegate_msgs = match delegation_strategy {
+    let (new_redelegations, deferred) = match delegation_strategy {
         DelegationStrategy::Uniform => {
             // only redelegate when old strategy
             let delegations = query_delegations(&deps.querier, &validators, &env.contract.address)?;
             let delegation_to_remove =
-                query_delegation(&deps.querier, &validator, &env.contract.address)?;
-            let new_redelegations = compute_redelegations_for_removal(
-                &state,
+                query_delegation(&deps.querier, validator, &env.contract.address)?;
+            let locked_validators =
+                state.locked_validators(deps.storage, env.block.time.seconds())?;
+            compute_redelegations_for_removal(
+                state,
                 deps.storage,
                 &delegation_to_remove,
                 &delegations,
                 validators,
-            )?;
-
-            new_redelegations.iter().map(|d| d.to_cosmos_msg()).collect::<Vec<_>>()
+                &locked_validators,
+            )?
         },
         DelegationStrategy::Gauges {
             ..
+        }
+        | DelegationStrategy::Defined {
+            ..
+        }
+        | DelegationStrategy::Hybrid {
+            ..
         } => {
             // removed validators can have a delegation until the next tune, to keep undelegations in sync.
-            vec![]
+            (vec![], vec![])
         },
     };
 
-    let event = Event::new("erishub/validator_removed").add_attribute("validator", validator);
+    save_expected_redelegations(state, deps.storage, env, &new_redelegations)?;
 
-    let check_msg = if !redelegate_msgs.is_empty() {
-        // only check coins if a redelegation is happening
-        Some(check_received_coin_msg(&deps, &env, None)?)
+    Ok((new_redelegations, deferred))
+}
+
+/// Updates the expected delegations and locks the destination validators as redelegation source
+/// until the redelegations complete.
+fn save_expected_redelegations(
+    state: &State,
+    storage: &mut dyn Storage,
+    env: &Env,
+    redelegations: &[Redelegation],
+) -> StdResult<()> {
+    let now = env.block.time.seconds();
+    let completion_time = now + state.unbond_period.load(storage)?;
+
+    // locks of completed redelegations are not needed anymore
+    let expired = state
+        .redelegation_locks
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| item.as_ref().map_or(false, |(_, time)| *time <= now))
+        .map(|item| item.map(|(validator, _)| validator))
+        .collect::<StdResult<Vec<_>>>()?;
+    for validator in expired {
+        state.redelegation_locks.remove(storage, &validator);
+    }
+
+    for redelegation in redelegations {
+        state.sub_expected_delegation(storage, &redelegation.src, redelegation.amount)?;
+        state.add_expected_delegation(storage, &redelegation.dst, redelegation.amount)?;
+        state.redelegation_locks.save(storage, &redelegation.dst, &completion_time)?;
+    }
+    Ok(())
+}
+
+/// Attributes reporting the amounts that could not be redelegated due to redelegation locks
+fn deferred_attributes(deferred: &[Delegation]) -> Vec<Attribute> {
+    deferred.iter().map(|d| attr("deferred", format!("{0}={1}", d.validator, d.amount))).collect()
+}
+
+/// Records the keeper of an overdue maintenance action, who is rewarded from the next harvested
+/// rewards. It has to be called before the action updates the state. Each action is rewarded at
+/// most once per cooldown, actions triggered by the contract itself are not rewarded.
+fn claim_keeper_reward(
+    state: &State,
+    deps: &mut DepsMut,
+    env: &Env,
+    action: &str,
+    keeper: &Addr,
+) -> StdResult<Vec<Attribute>> {
+    let config = match state.keeper_reward.may_load(deps.storage)? {
+        Some(config) => config,
+        None => return Ok(vec![]),
+    };
+
+    if *keeper == env.contract.address
+        || state.keeper_claims.may_load(deps.storage, action)?.is_some()
+    {
+        return Ok(vec![]);
+    }
+
+    // without auto maintenance the harvest is overdue once the cooldown has passed
+    let harvest_interval = state
+        .auto_maintenance
+        .may_load(deps.storage)?
+        .map_or(config.cooldown, |auto_maintenance| auto_maintenance.harvest_interval);
+    let overdue = overdue_maintenance(state, deps.as_ref(), env, harvest_interval)?;
+    if !overdue.iter().any(|(name, _, _)| *name == action) {
+        return Ok(vec![]);
+    }
+
+    let current_time = env.block.time.seconds();
+    if let Some(last_rewarded) = state.keeper_rewarded.may_load(deps.storage, action)? {
+        if current_time < last_rewarded + config.cooldown {
+            return Ok(vec![]);
+        }
+    }
+
+    state.keeper_claims.save(deps.storage, action, keeper)?;
+    state.keeper_rewarded.save(deps.storage, action, &current_time)?;
+
+    Ok(vec![attr("keeper_reward_claimed", format!("{0}={1}", action, keeper))])
+}
+
+pub fn update_pause_flags(
+    deps: DepsMut,
+    sender: Addr,
+    operations: Vec<PausableOperation>,
+    paused: bool,
+) -> ContractResult {
+    let state = State::default();
+
+    state.assert_owner_or_guardian(deps.storage, &sender)?;
+
+    let mut pause_flags = state.pause_flags.may_load(deps.storage)?.unwrap_or_default();
+    for operation in &operations {
+        pause_flags.set(operation, paused);
+    }
+    state.pause_flags.save(deps.storage, &pause_flags)?;
+
+    let (event, action) = if paused {
+        ("erishub/paused", "erishub/pause")
     } else {
-        None
+        ("erishub/unpaused", "erishub/unpause")
     };
+    let event = Event::new(event).add_attributes(
+        operations.iter().map(|operation| attr("operation", operation.to_string())),
+    );
 
-    Ok(Response::new()
-        .add_messages(redelegate_msgs)
-        .add_optional_message(check_msg)
-        .add_event(event)
-        .add_attribute("action", "erishub/remove_validator"))
+    Ok(Response::new().add_event(event).add_attribute("action", action))
 }
 
 pub fn transfer_ownership(deps: DepsMut, sender: Addr, new_owner: String) -> ContractResult {
@@ -795,15 +2239,46 @@ pub fn accept_ownership(deps: DepsMut, sender: Addr) -> ContractResult {
     Ok(Response::new().add_event(event).add_attribute("action", "erishub/transfer_ownership"))
 }
 
+pub fn track_history(deps: DepsMut, sender: Addr, enabled: bool) -> ContractResult {
+    let state = State::default();
+
+    if enabled {
+        state.history_users.save(deps.storage, &sender, &true)?;
+    } else {
+        // the existing history is kept, so it is still available when opting in again
+        state.history_users.remove(deps.storage, &sender);
+    }
+
+    let event = Event::new("erishub/history_tracking_updated")
+        .add_attribute("user", sender)
+        .add_attribute("enabled", enabled.to_string());
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/track_history"))
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn update_config(
     deps: DepsMut,
+    env: Env,
     sender: Addr,
     protocol_fee_contract: Option<String>,
-    protocol_reward_fee: Option<Decimal>,
-    delegation_strategy: Option<DelegationStrategy>,
     allow_donations: Option<bool>,
     vote_operator: Option<String>,
+    liquidity_buffer: Option<LiquidityBufferConfig>,
+    remove_slashed_validators: Option<bool>,
+    insert_swap_routes: Option<Vec<RewardSwapRoute>>,
+    delete_swap_routes: Option<Vec<String>>,
+    validator_checks: Option<ValidatorChecks>,
+    guardian: Option<String>,
+    fee_receivers: Option<Vec<FeeReceiver>>,
+    auto_maintenance: Option<AutoMaintenanceConfig>,
+    keeper_reward: Option<KeeperRewardConfig>,
+    prop_gauges: Option<String>,
+    dust_cleanup: Option<DustCleanupConfig>,
+    split_bond: Option<SplitBondConfig>,
+    remove_liquidity_buffer: Option<bool>,
+    protocol_reward_fee: Option<Decimal>,
+    delegation_strategy: Option<DelegationStrategy>,
     epoch_period: Option<u64>,
     unbond_period: Option<u64>,
 ) -> ContractResult {
@@ -811,48 +2286,298 @@ pub fn update_config(
 
     state.assert_owner(deps.storage, &sender)?;
 
-    if protocol_fee_contract.is_some() || protocol_reward_fee.is_some() {
+    // parameters that affect stakers are only changed after the config timelock
+    let timelocked = [
+        ("protocol_reward_fee", protocol_reward_fee.is_some()),
+        ("delegation_strategy", delegation_strategy.is_some()),
+        ("epoch_period", epoch_period.is_some()),
+        ("unbond_period", unbond_period.is_some()),
+    ];
+    if let Some((param, _)) = timelocked.iter().find(|(_, set)| *set) {
+        return Err(ContractError::TimelockedConfigParam(param.to_string()));
+    }
+
+    let remove_liquidity_buffer = remove_liquidity_buffer.unwrap_or(false);
+    if remove_liquidity_buffer && liquidity_buffer.is_some() {
+        return Err(ContractError::LiquidityBufferUpdatedAndRemoved {});
+    }
+
+    if let Some(protocol_fee_contract) = protocol_fee_contract {
         let mut fee_config = state.fee_config.load(deps.storage)?;
+        fee_config.protocol_fee_contract = deps.api.addr_validate(&protocol_fee_contract)?;
+        state.fee_config.save(deps.storage, &fee_config)?;
+    }
+
+    if let Some(allow_donations) = allow_donations {
+        state.allow_donations.save(deps.storage, &allow_donations)?;
+    }
+
+    if let Some(vote_operator) = vote_operator {
+        state.vote_operator.save(deps.storage, &deps.api.addr_validate(&vote_operator)?)?;
+    }
 
-        if let Some(protocol_fee_contract) = protocol_fee_contract {
-            fee_config.protocol_fee_contract = deps.api.addr_validate(&protocol_fee_contract)?;
+    if let Some(liquidity_buffer) = liquidity_buffer {
+        if liquidity_buffer.instant_unbond_fee.gt(&get_instant_unbond_fee_cap()) {
+            return Err(ContractError::InstantUnbondFeeTooHigh {});
+        }
+        if liquidity_buffer.refill_bond_share > Decimal::one() {
+            return Err(ContractError::ShareTooHigh("refill_bond_share".into()));
         }
+        if liquidity_buffer.refill_reinvest_share > Decimal::one() {
+            return Err(ContractError::ShareTooHigh("refill_reinvest_share".into()));
+        }
+        state.liquidity_buffer_config.save(deps.storage, &liquidity_buffer)?;
+    }
 
-        if let Some(protocol_reward_fee) = protocol_reward_fee {
-            if protocol_reward_fee.gt(&get_reward_fee_cap()) {
-                return Err(ContractError::ProtocolRewardFeeTooHigh {});
+    let mut delegate_msgs: Vec<CosmosMsg> = vec![];
+    let mut events: Vec<Event> = vec![];
+    if remove_liquidity_buffer {
+        state.liquidity_buffer_config.remove(deps.storage);
+
+        // the buffer is part of the exchange rate already, delegating it does not change the rate
+        let utoken_buffer = state.liquidity_buffer.may_load(deps.storage)?.unwrap_or_default();
+        state.liquidity_buffer.remove(deps.storage);
+
+        if !utoken_buffer.is_zero() {
+            let denom = state.get_denom(deps.storage)?;
+            let (new_delegations, _) = find_new_delegations(&state, &deps, &env, utoken_buffer)?;
+            for new_delegation in new_delegations.iter().filter(|d| d.amount > 0) {
+                state.add_expected_delegation(
+                    deps.storage,
+                    &new_delegation.validator,
+                    new_delegation.amount,
+                )?;
+                delegate_msgs.push(new_delegation.to_cosmos_msg(&denom));
             }
-            fee_config.protocol_reward_fee = protocol_reward_fee;
         }
 
-        state.fee_config.save(deps.storage, &fee_config)?;
+        events.push(
+            Event::new("erishub/liquidity_buffer_removed")
+                .add_attribute("utoken_delegated", utoken_buffer),
+        );
+    }
+
+    if let Some(remove_slashed_validators) = remove_slashed_validators {
+        state.remove_slashed_validators.save(deps.storage, &remove_slashed_validators)?;
     }
 
-    if let Some(epoch_period) = epoch_period {
-        if epoch_period == 0 {
-            return Err(ContractError::CantBeZero("epoch_period".into()));
+    if let Some(validator_checks) = validator_checks {
+        if validator_checks.max_commission > Decimal::one() {
+            return Err(ContractError::ShareTooHigh("max_commission".into()));
         }
-        state.epoch_period.save(deps.storage, &epoch_period)?;
+        state.validator_checks.save(deps.storage, &validator_checks)?;
     }
 
-    if let Some(unbond_period) = unbond_period {
-        if unbond_period == 0 {
-            return Err(ContractError::CantBeZero("unbond_period".into()));
+    if let Some(guardian) = guardian {
+        if guardian.is_empty() {
+            state.guardian.remove(deps.storage);
+        } else {
+            state.guardian.save(deps.storage, &deps.api.addr_validate(&guardian)?)?;
         }
-        state.unbond_period.save(deps.storage, &unbond_period)?;
+    }
+
+    if let Some(fee_receivers) = fee_receivers {
+        if fee_receivers.is_empty() {
+            state.fee_receivers.remove(deps.storage);
+        } else {
+            let fee_receivers = fee_receivers
+                .into_iter()
+                .map(|receiver| receiver.validate(deps.api))
+                .collect::<StdResult<Vec<_>>>()?;
+            state.fee_receivers.save(deps.storage, &fee_receivers)?;
+        }
+    }
+
+    if let Some(auto_maintenance) = auto_maintenance {
+        if auto_maintenance.enabled && auto_maintenance.gas_limit == 0 {
+            return Err(ContractError::CantBeZero("gas_limit".into()));
+        }
+        state.auto_maintenance.save(deps.storage, &auto_maintenance)?;
+    }
+
+    if let Some(keeper_reward) = keeper_reward {
+        if let KeeperReward::Share(share) = keeper_reward.reward {
+            if share > get_keeper_reward_cap() {
+                return Err(ContractError::ShareTooHigh("keeper_reward".into()));
+            }
+        }
+        state.keeper_reward.save(deps.storage, &keeper_reward)?;
+    }
+
+    if let Some(prop_gauges) = prop_gauges {
+        if prop_gauges.is_empty() {
+            state.prop_gauges.remove(deps.storage);
+        } else {
+            state.prop_gauges.save(deps.storage, &deps.api.addr_validate(&prop_gauges)?)?;
+        }
+    }
+
+    if let Some(dust_cleanup) = dust_cleanup {
+        state.dust_cleanup.save(deps.storage, &dust_cleanup)?;
+    }
+
+    if let Some(split_bond) = split_bond {
+        if split_bond.enabled && split_bond.max_delegations == 0 {
+            return Err(ContractError::CantBeZero("max_delegations".into()));
+        }
+        state.split_bond.save(deps.storage, &split_bond)?;
+    }
+
+    if let Some(delete_swap_routes) = delete_swap_routes {
+        for denom in delete_swap_routes {
+            state.reward_swap_routes.remove(deps.storage, &denom);
+        }
+    }
+
+    if let Some(insert_swap_routes) = insert_swap_routes {
+        let denom = state.get_denom(deps.storage)?;
+        for route in insert_swap_routes {
+            if route.max_spread.gt(&get_max_spread_cap()) {
+                return Err(ContractError::MaxSpreadTooHigh {});
+            }
+            let route = route.validate(deps.api, &denom)?;
+            state.reward_swap_routes.save(deps.storage, &route.denom, &route)?;
+        }
+    }
+
+    Ok(Response::new()
+        .add_messages(delegate_msgs)
+        .add_events(events)
+        .add_attribute("action", "erishub/update_config"))
+}
+
+/// Stores a change of the parameters that affect stakers. It is validated right away, so that
+/// stakers only have to react to changes that can be applied.
+#[allow(clippy::too_many_arguments)]
+pub fn propose_config_change(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    protocol_reward_fee: Option<Decimal>,
+    delegation_strategy: Option<DelegationStrategy>,
+    epoch_period: Option<u64>,
+    unbond_period: Option<u64>,
+    config_timelock: Option<u64>,
+) -> ContractResult {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    if state.pending_config_change.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::ConfigChangePending {});
+    }
+
+    let change = PendingConfigChange {
+        protocol_reward_fee,
+        delegation_strategy,
+        epoch_period,
+        unbond_period,
+        config_timelock,
+        apply_after: env.block.time.seconds() + state.get_config_timelock(deps.storage)?,
+    };
+
+    if change.protocol_reward_fee.is_none()
+        && change.delegation_strategy.is_none()
+        && change.epoch_period.is_none()
+        && change.unbond_period.is_none()
+        && change.config_timelock.is_none()
+    {
+        return Err(ContractError::EmptyConfigChange {});
+    }
+
+    validate_config_change(&state, &deps, &change)?;
+    state.pending_config_change.save(deps.storage, &change)?;
+
+    let event = Event::new("erishub/config_change_proposed")
+        .add_attribute("apply_after", change.apply_after.to_string());
+
+    Ok(Response::new().add_event(event).add_attribute("action", "erishub/propose_config_change"))
+}
+
+pub fn apply_config_change(deps: DepsMut, env: Env, sender: Addr) -> ContractResult {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    let change = state
+        .pending_config_change
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoPendingConfigChange {})?;
+
+    if env.block.time.seconds() < change.apply_after {
+        return Err(ContractError::ConfigChangeTimelocked(change.apply_after));
+    }
+
+    // validators can have been removed during the timelock
+    let delegation_strategy = validate_config_change(&state, &deps, &change)?;
+
+    if let Some(protocol_reward_fee) = change.protocol_reward_fee {
+        let mut fee_config = state.fee_config.load(deps.storage)?;
+        fee_config.protocol_reward_fee = protocol_reward_fee;
+        state.fee_config.save(deps.storage, &fee_config)?;
     }
 
     if let Some(delegation_strategy) = delegation_strategy {
-        state.delegation_strategy.save(deps.storage, &delegation_strategy.validate(deps.api)?)?;
+        state.delegation_strategy.save(deps.storage, &delegation_strategy)?;
     }
 
-    if let Some(allow_donations) = allow_donations {
-        state.allow_donations.save(deps.storage, &allow_donations)?;
+    if let Some(epoch_period) = change.epoch_period {
+        state.epoch_period.save(deps.storage, &epoch_period)?;
     }
 
-    if let Some(vote_operator) = vote_operator {
-        state.vote_operator.save(deps.storage, &deps.api.addr_validate(&vote_operator)?)?;
+    if let Some(unbond_period) = change.unbond_period {
+        state.unbond_period.save(deps.storage, &unbond_period)?;
+    }
+
+    if let Some(config_timelock) = change.config_timelock {
+        state.config_timelock.save(deps.storage, &config_timelock)?;
+    }
+
+    state.pending_config_change.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_event(Event::new("erishub/config_change_applied"))
+        .add_attribute("action", "erishub/apply_config_change"))
+}
+
+pub fn cancel_config_change(deps: DepsMut, sender: Addr) -> ContractResult {
+    let state = State::default();
+
+    state.assert_owner(deps.storage, &sender)?;
+
+    if state.pending_config_change.may_load(deps.storage)?.is_none() {
+        return Err(ContractError::NoPendingConfigChange {});
+    }
+    state.pending_config_change.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_event(Event::new("erishub/config_change_cancelled"))
+        .add_attribute("action", "erishub/cancel_config_change"))
+}
+
+/// Checks the parameters of a config change and returns the validated delegation strategy
+fn validate_config_change(
+    state: &State,
+    deps: &DepsMut,
+    change: &PendingConfigChange,
+) -> Result<Option<DelegationStrategy<Addr>>, ContractError> {
+    if let Some(protocol_reward_fee) = change.protocol_reward_fee {
+        if protocol_reward_fee.gt(&get_reward_fee_cap()) {
+            return Err(ContractError::ProtocolRewardFeeTooHigh {});
+        }
+    }
+
+    if change.epoch_period == Some(0) {
+        return Err(ContractError::CantBeZero("epoch_period".into()));
+    }
+
+    if change.unbond_period == Some(0) {
+        return Err(ContractError::CantBeZero("unbond_period".into()));
     }
 
-    Ok(Response::new().add_attribute("action", "erishub/update_config"))
+    change
+        .delegation_strategy
+        .clone()
+        .map(|delegation_strategy| validate_delegation_strategy(state, deps, delegation_strategy))
+        .transpose()
 }
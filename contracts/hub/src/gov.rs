@@ -1,11 +1,15 @@
 use cosmwasm_std::{
     CosmosMsg, Decimal, DepsMut, Env, Event, Fraction, GovMsg, MessageInfo, Response,
 };
+use eris::prop_gauges::{
+    ConfigResponse as PropGaugesConfigResponse, PropDetailResponse, QueryMsg as PropGaugesQueryMsg,
+};
+use eris::voting_escrow::get_total_voting_power_at_by_period;
 use itertools::Itertools;
 use protobuf::SpecialFields;
 
 use crate::{
-    error::ContractResult,
+    error::{ContractError, ContractResult},
     protos::proto::{MsgVoteWeighted, VoteOption, WeightedVoteOption},
     state::State,
 };
@@ -42,9 +46,82 @@ pub fn vote_weighted(
 
     let event = Event::new("erishub/voted_weighted").add_attribute("prop", proposal_id.to_string());
 
-    let vote = MsgVoteWeighted {
+    let vote = vote_weighted_msg(&_env, proposal_id, votes);
+
+    Ok(Response::new()
+        .add_message(vote)
+        .add_event(event)
+        .add_attribute("action", "erishub/vote_weighted"))
+}
+
+pub fn vote_from_gauge(deps: DepsMut, env: Env, proposal_id: u64) -> ContractResult {
+    let state = State::default();
+    let prop_gauges =
+        state.prop_gauges.may_load(deps.storage)?.ok_or(ContractError::NoPropGaugesSet {})?;
+
+    let config: PropGaugesConfigResponse =
+        deps.querier.query_wasm_smart(&prop_gauges, &PropGaugesQueryMsg::Config {})?;
+    let prop = deps
+        .querier
+        .query_wasm_smart::<PropDetailResponse>(
+            &prop_gauges,
+            &PropGaugesQueryMsg::PropDetail {
+                user: None,
+                proposal_id,
+            },
+        )?
+        .prop;
+
+    if env.block.time.seconds() <= prop.end_time_s {
+        return Err(ContractError::PropVotingNotEnded(proposal_id, prop.end_time_s));
+    }
+
+    let total_vp =
+        get_total_voting_power_at_by_period(&deps.querier, config.escrow_addr, prop.period)?;
+    if total_vp.is_zero() || !prop.reached_quorum(total_vp, config.quorum_bps)? {
+        return Err(ContractError::PropQuorumNotReached(proposal_id));
+    }
+
+    let event = Event::new("erishub/voted_from_gauge")
+        .add_attribute("prop", proposal_id.to_string())
+        .add_attribute("voted_vp", prop.voted_vp())
+        .add_attribute("total_vp", total_vp);
+
+    let (vote, event) = if config.use_weighted_vote {
+        let votes = prop.get_weighted_votes();
+        let event = votes.iter().fold(event, |event, (weight, option)| {
+            event.add_attribute("vote", format!("{0:?}={1}", option, weight))
+        });
+        (vote_weighted_msg(&env, proposal_id, votes), event)
+    } else {
+        // the quorum is reached, so there is always a wanted vote
+        let vote = prop
+            .get_wanted_vote(total_vp, config.quorum_bps)?
+            .ok_or(ContractError::PropQuorumNotReached(proposal_id))?;
+        let event = event.add_attribute("vote", format!("{0:?}", vote));
+        (
+            CosmosMsg::Gov(GovMsg::Vote {
+                proposal_id,
+                vote,
+            }),
+            event,
+        )
+    };
+
+    Ok(Response::new()
+        .add_message(vote)
+        .add_event(event)
+        .add_attribute("action", "erishub/vote_from_gauge"))
+}
+
+fn vote_weighted_msg(
+    env: &Env,
+    proposal_id: u64,
+    votes: Vec<(Decimal, cosmwasm_std::VoteOption)>,
+) -> CosmosMsg {
+    MsgVoteWeighted {
         proposal_id,
-        voter: _env.contract.address.to_string(),
+        voter: env.contract.address.to_string(),
         options: votes
             .into_iter()
             .map(|vote| WeightedVoteOption {
@@ -61,12 +138,6 @@ pub fn vote_weighted(
             })
             .collect_vec(),
         special_fields: SpecialFields::default(),
-    };
-
-    let vote = vote.to_cosmos_msg();
-
-    Ok(Response::new()
-        .add_message(vote)
-        .add_event(event)
-        .add_attribute("action", "erishub/vote_weighted"))
+    }
+    .to_cosmos_msg()
 }
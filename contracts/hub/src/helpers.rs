@@ -5,7 +5,7 @@ use std::{
 
 use cosmwasm_std::{
     Addr, Coin, Decimal, Env, QuerierWrapper, QueryRequest, StakingQuery, StdError, StdResult,
-    Storage, Uint128, ValidatorResponse,
+    Storage, Uint128, Validator, ValidatorResponse,
 };
 use cw20::{Cw20QueryMsg, TokenInfoResponse};
 use eris::{
@@ -16,7 +16,6 @@ use eris::{
 use itertools::Itertools;
 
 use crate::{
-    constants::CONTRACT_DENOM,
     state::State,
     types::{gauges::GaugeLoader, Delegation},
 };
@@ -61,11 +60,12 @@ pub(crate) fn query_delegations(
 pub(crate) fn query_all_delegations(
     querier: &QuerierWrapper,
     delegator_addr: &Addr,
+    denom: &str,
 ) -> StdResult<Vec<Delegation>> {
     let result: Vec<_> = querier
         .query_all_delegations(delegator_addr)?
         .into_iter()
-        .filter(|d| d.amount.denom == CONTRACT_DENOM && !d.amount.amount.is_zero())
+        .filter(|d| d.amount.denom == denom && !d.amount.amount.is_zero())
         .map(|d| Delegation {
             validator: d.validator,
             amount: d.amount.amount.u128(),
@@ -118,6 +118,79 @@ pub fn assert_validators_exists(
     Ok(())
 }
 
+/// Checks the validators against the configured `ValidatorChecks`. Returns the validators failing
+/// the checks together with the reason, and separately the validators outside of the active set.
+/// The staking module does not tell jailed validators apart from validators that are not bonded
+/// enough to be active, so inactive validators only stop receiving delegations.
+///
+/// This queries all validators, so it only runs in `AddValidator` and `TuneDelegations`. The result
+/// is stored and used by the other operations.
+pub(crate) fn query_ineligible_validators(
+    state: &State,
+    storage: &dyn Storage,
+    querier: &QuerierWrapper,
+    validators: &[String],
+) -> StdResult<(Vec<(String, String)>, Vec<String>)> {
+    let checks = match state.validator_checks.may_load(storage)? {
+        Some(checks) => checks,
+        None => return Ok((vec![], vec![])),
+    };
+
+    let active_validators: HashMap<String, Validator> =
+        querier.query_all_validators()?.into_iter().map(|v| (v.address.clone(), v)).collect();
+
+    let mut ineligible = vec![];
+    let mut inactive = vec![];
+    for validator in validators {
+        let reason = match active_validators.get(validator) {
+            None => {
+                inactive.push(validator.clone());
+                None
+            },
+            Some(info) if info.commission > checks.max_commission => {
+                Some(format!("commission {} above {}", info.commission, checks.max_commission))
+            },
+            Some(_) if !checks.min_self_delegation.is_zero() => {
+                let self_delegation = query_self_delegation(querier, validator)?;
+                if self_delegation < checks.min_self_delegation {
+                    Some(format!(
+                        "self-delegation {} below {}",
+                        self_delegation, checks.min_self_delegation
+                    ))
+                } else {
+                    None
+                }
+            },
+            Some(_) => None,
+        };
+
+        if let Some(reason) = reason {
+            ineligible.push((validator.clone(), reason));
+        }
+    }
+
+    Ok((ineligible, inactive))
+}
+
+/// Query the amount of Luna the operator of a validator is delegating to its own validator
+fn query_self_delegation(querier: &QuerierWrapper, validator: &str) -> StdResult<Uint128> {
+    let operator = to_operator_account(validator)?;
+    Ok(querier
+        .query_delegation(operator, validator)?
+        .map(|fd| fd.amount.amount)
+        .unwrap_or_default())
+}
+
+/// Converts a validator address (e.g. terravaloper1...) to the account address of its operator
+pub(crate) fn to_operator_account(validator: &str) -> StdResult<String> {
+    let invalid = || StdError::generic_err(format!("invalid validator address {}", validator));
+
+    let (hrp, data, variant) = bech32::decode(validator).map_err(|_| invalid())?;
+    let prefix = hrp.strip_suffix("valoper").ok_or_else(invalid)?;
+
+    bech32::encode(prefix, data, variant).map_err(|_| invalid())
+}
+
 /// Dedupes a Vector of strings using a hashset.
 pub fn dedupe(validators: &mut Vec<String>) {
     let mut set = HashSet::new();
@@ -140,7 +213,11 @@ pub(crate) fn get_wanted_delegations(
 
     match delegation_strategy {
         DelegationStrategy::Uniform {} => {
-            let validators = state.validators.load(storage)?;
+            let (validators, dropped) = load_eligible_validators(state, storage)?;
+            if validators.is_empty() {
+                return Err(StdError::generic_err("No eligible validators"));
+            }
+
             let validator_count = Uint128::new(validators.len() as u128);
             let share_per_validator = Decimal::from_ratio(Uint128::one(), validator_count);
 
@@ -153,8 +230,8 @@ pub(crate) fn get_wanted_delegations(
                         .map(|val| (val, share_per_validator))
                         .collect_vec(),
                 },
-                // no need to store it
-                false,
+                // no need to store it, unless validators have been dropped
+                dropped,
             ))
         },
         DelegationStrategy::Gauges {
@@ -165,62 +242,104 @@ pub(crate) fn get_wanted_delegations(
             max_delegation_bps,
             validator_count,
         } => {
-            let min_delegation = BasicPoints::try_from(min_delegation_bps)?.decimal();
-            let max_delegation = BasicPoints::try_from(max_delegation_bps)?.decimal();
-
-            let vamp_factor = BasicPoints::try_from(amp_factor_bps)?.decimal();
-            let emp_factor = Decimal::one().checked_sub(vamp_factor)?;
+            let (validators, _) = load_eligible_validators(state, storage)?;
+            let shares = compute_gauge_shares(
+                &loader,
+                querier,
+                validators,
+                amp_gauges,
+                emp_gauges,
+                amp_factor_bps,
+                min_delegation_bps,
+                max_delegation_bps,
+                validator_count,
+            )?;
 
-            let vamp_context = Context::from_amps(&loader, querier, amp_gauges)?;
-            let emp_context = Context::from_emps(&loader, querier, emp_gauges)?;
-
-            let validators: Vec<_> = state
-                .validators
-                .load(storage)?
-                .into_iter()
-                .map(|val| -> StdResult<(String, Decimal, Decimal)> {
-                    let vamp = vamp_context.points.get(&val).copied().unwrap_or_default();
-
-                    let total_share = if let Some(emp_context) = &emp_context {
-                        let vamp_share =
-                            vamp_factor.checked_mul(Decimal::from_ratio(vamp, vamp_context.sum))?;
-
-                        let emp = emp_context.points.get(&val).copied().unwrap_or(Uint128::zero());
-                        let emp_share =
-                            emp_factor.checked_mul(Decimal::from_ratio(emp, emp_context.sum))?;
-
-                        vamp_share.checked_add(emp_share)?
-                    } else {
-                        Decimal::from_ratio(vamp, vamp_context.sum)
-                    };
-
-                    let score = Decimal::min(total_share, max_delegation);
-
-                    Ok((val, score, total_share))
-                })
-                .collect::<StdResult<Vec<_>>>()?
-                .into_iter()
-                .filter(|(_, amount, _)| *amount > min_delegation)
-                .sorted_by(|(_, _, a), (_, _, b)| b.cmp(a)) // Sort in descending order
-                .take(validator_count.into())
-                .collect();
-
-            // normalize missing percentage over all validators
-            let total: Decimal = validators.iter().map(|a| a.1).sum();
-            let validators: Vec<_> = validators
+            Ok((
+                WantedDelegationsShare {
+                    shares,
+                    tune_time: env.block.time.seconds(),
+                    tune_period: get_period(env.block.time.seconds())?,
+                },
+                true,
+            ))
+        },
+        DelegationStrategy::Defined {
+            shares_bps,
+        } => {
+            let (validators, _) = load_eligible_validators(state, storage)?;
+            let shares = shares_bps
                 .into_iter()
-                .map(|v| -> StdResult<(String, Decimal)> {
-                    let normalized =
-                        v.1.checked_div(total)
-                            .map_err(|_| StdError::generic_err("Could not divide by total"))?;
-
-                    Ok((v.0, normalized))
+                .filter(|(val, _)| validators.contains(val))
+                .map(|(val, bps)| -> StdResult<(String, Decimal)> {
+                    Ok((val, BasicPoints::try_from(bps)?.decimal()))
                 })
                 .collect::<StdResult<Vec<_>>>()?;
+            if shares.is_empty() {
+                return Err(StdError::generic_err("No eligible validators"));
+            }
+
+            Ok((
+                WantedDelegationsShare {
+                    shares: normalize_shares(shares)?,
+                    tune_time: env.block.time.seconds(),
+                    tune_period: get_period(env.block.time.seconds())?,
+                },
+                true,
+            ))
+        },
+        DelegationStrategy::Hybrid {
+            core_shares_bps,
+            amp_gauges,
+            emp_gauges,
+            amp_factor_bps,
+            min_delegation_bps,
+            max_delegation_bps,
+            validator_count,
+        } => {
+            let (validators, _) = load_eligible_validators(state, storage)?;
+
+            let core_total: u16 = core_shares_bps.iter().map(|(_, bps)| *bps).sum();
+            let remainder = BasicPoints::try_from(10000 - core_total)?.decimal();
+
+            // the remainder is distributed by the gauges, core validators can receive a share of
+            // it on top of their fixed share
+            let gauge_shares = if remainder.is_zero() {
+                vec![]
+            } else {
+                compute_gauge_shares(
+                    &loader,
+                    querier,
+                    validators.clone(),
+                    amp_gauges,
+                    emp_gauges,
+                    amp_factor_bps,
+                    min_delegation_bps,
+                    max_delegation_bps,
+                    validator_count,
+                )?
+            };
+
+            let mut shares: Vec<(String, Decimal)> = vec![];
+            for (val, bps) in core_shares_bps {
+                if validators.contains(&val) {
+                    shares.push((val, BasicPoints::try_from(bps)?.decimal()));
+                }
+            }
+            for (val, share) in gauge_shares {
+                let share = remainder.checked_mul(share)?;
+                match shares.iter_mut().find(|(v, _)| *v == val) {
+                    Some((_, core_share)) => *core_share = core_share.checked_add(share)?,
+                    None => shares.push((val, share)),
+                }
+            }
+            if shares.is_empty() {
+                return Err(StdError::generic_err("No eligible validators"));
+            }
 
             Ok((
                 WantedDelegationsShare {
-                    shares: validators,
+                    shares: normalize_shares(shares)?,
                     tune_time: env.block.time.seconds(),
                     tune_period: get_period(env.block.time.seconds())?,
                 },
@@ -230,6 +349,90 @@ pub(crate) fn get_wanted_delegations(
     }
 }
 
+/// Computes the shares of the validators based on the gauges, normalized to sum up to 1
+#[allow(clippy::too_many_arguments)]
+fn compute_gauge_shares(
+    loader: &impl GaugeLoader,
+    querier: &QuerierWrapper,
+    validators: Vec<String>,
+    amp_gauges: Addr,
+    emp_gauges: Option<Addr>,
+    amp_factor_bps: u16,
+    min_delegation_bps: u16,
+    max_delegation_bps: u16,
+    validator_count: u8,
+) -> StdResult<Vec<(String, Decimal)>> {
+    let min_delegation = BasicPoints::try_from(min_delegation_bps)?.decimal();
+    let max_delegation = BasicPoints::try_from(max_delegation_bps)?.decimal();
+
+    let vamp_factor = BasicPoints::try_from(amp_factor_bps)?.decimal();
+    let emp_factor = Decimal::one().checked_sub(vamp_factor)?;
+
+    let vamp_context = Context::from_amps(loader, querier, amp_gauges)?;
+    let emp_context = Context::from_emps(loader, querier, emp_gauges)?;
+
+    let validators: Vec<_> = validators
+        .into_iter()
+        .map(|val| -> StdResult<(String, Decimal, Decimal)> {
+            let vamp = vamp_context.points.get(&val).copied().unwrap_or_default();
+
+            let total_share = if let Some(emp_context) = &emp_context {
+                let vamp_share =
+                    vamp_factor.checked_mul(Decimal::from_ratio(vamp, vamp_context.sum))?;
+
+                let emp = emp_context.points.get(&val).copied().unwrap_or(Uint128::zero());
+                let emp_share =
+                    emp_factor.checked_mul(Decimal::from_ratio(emp, emp_context.sum))?;
+
+                vamp_share.checked_add(emp_share)?
+            } else {
+                Decimal::from_ratio(vamp, vamp_context.sum)
+            };
+
+            let score = Decimal::min(total_share, max_delegation);
+
+            Ok((val, score, total_share))
+        })
+        .collect::<StdResult<Vec<_>>>()?
+        .into_iter()
+        .filter(|(_, amount, _)| *amount > min_delegation)
+        .sorted_by(|(_, _, a), (_, _, b)| b.cmp(a)) // Sort in descending order
+        .take(validator_count.into())
+        .collect();
+
+    // normalize missing percentage over all validators
+    normalize_shares(validators)
+}
+
+/// Scales the shares so that they sum up to 1
+fn normalize_shares(shares: Vec<(String, Decimal)>) -> StdResult<Vec<(String, Decimal)>> {
+    let total: Decimal = shares.iter().map(|a| a.1).sum();
+    shares
+        .into_iter()
+        .map(|v| -> StdResult<(String, Decimal)> {
+            let normalized =
+                v.1.checked_div(total)
+                    .map_err(|_| StdError::generic_err("Could not divide by total"))?;
+
+            Ok((v.0, normalized))
+        })
+        .collect()
+}
+
+/// Loads the whitelisted validators that passed the last validator checks, and whether any were
+/// dropped
+fn load_eligible_validators(
+    state: &State,
+    storage: &dyn Storage,
+) -> StdResult<(Vec<String>, bool)> {
+    let validators = state.validators.load(storage)?;
+    let count = validators.len();
+    let validators = state.filter_eligible_validators(storage, validators)?;
+    let dropped = validators.len() < count;
+
+    Ok((validators, dropped))
+}
+
 struct Context {
     pub sum: Uint128,
     pub points: HashMap<String, Uint128>,
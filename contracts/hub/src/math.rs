@@ -4,18 +4,19 @@ use std::{
     collections::{HashMap, HashSet},
 };
 
-use cosmwasm_std::{Addr, QuerierWrapper, StdResult, Storage, Uint128};
+use cosmwasm_std::{Addr, Decimal, QuerierWrapper, StdResult, Storage, Uint128};
 
 use eris::{
-    hub::{Batch, WantedDelegationsShare},
+    hub::{Batch, FeeReceiver, KeeperReward, LiquidityBufferConfig, WantedDelegationsShare},
     DecimalCheckedOps,
 };
 
 use crate::{
+    constants::get_keeper_reward_cap,
     error::ContractError,
     helpers::query_all_delegations,
     state::State,
-    types::{Delegation, Redelegation, Undelegation},
+    types::{Delegation, Redelegation, SendFee, Undelegation},
 };
 
 type UtokenPerValidator =
@@ -25,23 +26,27 @@ type UtokenPerValidator =
 // Minting/burning logics
 //--------------------------------------------------------------------------------------------------
 
-/// Compute the amount of Stake token to mint for a specific Luna stake amount. If current total
-/// staked amount is zero, we use 1 ustake = 1 uluna; otherwise, we calculate base on the current
-/// uluna per ustake ratio.
+/// Compute the amount of Stake token to mint for a specific utoken stake amount. If current total
+/// staked amount is zero, we use 1 ustake = 1 utoken; otherwise, we calculate base on the current
+/// utoken per ustake ratio.
+///
+/// The liquidity buffer is backing the Stake token as well, so it is added to the bonded amount.
 pub(crate) fn compute_mint_amount(
     ustake_supply: Uint128,
-    uluna_to_bond: Uint128,
+    utoken_to_bond: Uint128,
     current_delegations: &[Delegation],
+    utoken_buffer: Uint128,
 ) -> Uint128 {
-    let uluna_bonded: u128 = current_delegations.iter().map(|d| d.amount).sum();
-    if uluna_bonded == 0 {
-        uluna_to_bond
+    let utoken_bonded: u128 =
+        current_delegations.iter().map(|d| d.amount).sum::<u128>() + utoken_buffer.u128();
+    if utoken_bonded == 0 {
+        utoken_to_bond
     } else {
-        ustake_supply.multiply_ratio(uluna_to_bond, uluna_bonded)
+        ustake_supply.multiply_ratio(utoken_to_bond, utoken_bonded)
     }
 }
 
-/// Compute the amount of `uluna` to unbond for a specific `ustake` burn amount
+/// Compute the amount of `utoken` to unbond for a specific `ustake` burn amount
 ///
 /// There is no way `ustake` total supply is zero when the user is senting a non-zero amount of `ustake`
 /// to burn, so we don't need to handle division-by-zero here
@@ -49,62 +54,176 @@ pub(crate) fn compute_unbond_amount(
     ustake_supply: Uint128,
     ustake_to_burn: Uint128,
     current_delegations: &[Delegation],
+    utoken_buffer: Uint128,
 ) -> Uint128 {
-    let uluna_bonded: u128 = current_delegations.iter().map(|d| d.amount).sum();
-    Uint128::new(uluna_bonded).multiply_ratio(ustake_to_burn, ustake_supply)
+    let utoken_bonded: u128 =
+        current_delegations.iter().map(|d| d.amount).sum::<u128>() + utoken_buffer.u128();
+    Uint128::new(utoken_bonded).multiply_ratio(ustake_to_burn, ustake_supply)
+}
+
+/// Compute the utoken per ustake ratio. If nothing is staked yet, 1 ustake = 1 utoken.
+pub(crate) fn compute_exchange_rate(
+    ustake_supply: Uint128,
+    current_delegations: &[Delegation],
+    utoken_buffer: Uint128,
+) -> Decimal {
+    let utoken_bonded: u128 =
+        current_delegations.iter().map(|d| d.amount).sum::<u128>() + utoken_buffer.u128();
+    if utoken_bonded == 0 || ustake_supply.is_zero() {
+        Decimal::one()
+    } else {
+        Decimal::from_ratio(utoken_bonded, ustake_supply)
+    }
+}
+
+/// Compute the amount of `utoken` that is kept liquid to refill the liquidity buffer. At most
+/// `refill_share` of the available amount is used and the buffer is never filled above its target.
+pub(crate) fn compute_buffer_refill(
+    utoken_available: Uint128,
+    utoken_buffer: Uint128,
+    config: &LiquidityBufferConfig,
+    refill_share: Decimal,
+) -> StdResult<Uint128> {
+    let max_refill = refill_share.checked_mul_uint(utoken_available)?;
+    Ok(max_refill.min(config.target.saturating_sub(utoken_buffer)))
+}
+
+/// Compute the rewards of the keepers waiting for them. The rewards are paid from the harvested
+/// rewards, together they receive at most the keeper reward cap of them. If the cap is exhausted
+/// the later keepers receive less.
+pub(crate) fn compute_keeper_rewards(
+    reward: &KeeperReward,
+    keepers: &[Addr],
+    utoken_available: Uint128,
+) -> StdResult<Vec<SendFee>> {
+    let mut utoken_remaining = get_keeper_reward_cap().checked_mul_uint(utoken_available)?;
+    let mut keeper_rewards = vec![];
+    for keeper in keepers {
+        let amount = match reward {
+            KeeperReward::Share(share) => share.checked_mul_uint(utoken_available)?,
+            KeeperReward::Fixed(amount) => *amount,
+        }
+        .min(utoken_remaining);
+
+        if !amount.is_zero() {
+            utoken_remaining -= amount;
+            keeper_rewards.push(SendFee::new(keeper.clone(), amount.u128()));
+        }
+    }
+    Ok(keeper_rewards)
+}
+
+/// Split the protocol fee across the fee receivers according to their weights. The rounding
+/// remainder goes to the first receiver.
+pub(crate) fn compute_fee_split(
+    fee_receivers: &[FeeReceiver<Addr>],
+    protocol_fee_amount: Uint128,
+) -> Vec<SendFee> {
+    let total_weight: u64 = fee_receivers.iter().map(|receiver| receiver.weight).sum();
+
+    let mut amounts = fee_receivers
+        .iter()
+        .map(|receiver| protocol_fee_amount.multiply_ratio(receiver.weight, total_weight))
+        .collect::<Vec<_>>();
+
+    let distributed: Uint128 = amounts.iter().sum();
+    if let Some(first) = amounts.first_mut() {
+        *first += protocol_fee_amount - distributed;
+    }
+
+    fee_receivers
+        .iter()
+        .zip(amounts)
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|(receiver, amount)| {
+            SendFee::with_msg(receiver.addr.clone(), amount.u128(), receiver.msg.clone())
+        })
+        .collect()
 }
 
 //--------------------------------------------------------------------------------------------------
 // Delegation logics
 //--------------------------------------------------------------------------------------------------
 
-/// Given the current delegations made to validators, and a specific amount of `uluna` to unstake,
+/// Given the current delegations made to validators, and a specific amount of `utoken` to unstake,
 /// compute the undelegations to make such that the delegated amount to each validator is as even
 /// as possible.
 ///
 /// This function is based on Lido's implementation:
 /// https://github.com/lidofinance/lido-terra-contracts/blob/v1.0.2/contracts/lido_terra_validators_registry/src/common.rs#L55-102
+///
+/// Validators without a free unbonding slot are skipped, their part is undelegated from the other
+/// validators in the order of the current delegations.
 pub(crate) fn compute_undelegations(
     state: &State,
     storage: &dyn Storage,
-    uluna_to_unbond: Uint128,
+    utoken_to_unbond: Uint128,
     current_delegations: &[Delegation],
     validators: Vec<String>,
+    full_validators: &HashSet<String>,
 ) -> Result<Vec<Undelegation>, ContractError> {
-    let uluna_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
-    let uluna_to_distribute = uluna_staked - uluna_to_unbond.u128();
+    let utoken_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    let utoken_to_distribute = utoken_staked - utoken_to_unbond.u128();
 
-    let (uluna_per_validator, mut add, mut remove, _) =
-        get_uluna_per_validator(state, storage, uluna_to_distribute, &validators, None)?;
+    let (utoken_per_validator, mut add, mut remove, _) =
+        get_utoken_per_validator(state, storage, utoken_to_distribute, &validators, None)?;
 
     let mut new_undelegations: Vec<Undelegation> = vec![];
-    let mut uluna_available = uluna_to_unbond.u128();
+    let mut utoken_available = utoken_to_unbond.u128();
     for (_, d) in merge_with_validators(current_delegations, validators).iter().enumerate() {
-        let uluna_for_validator =
-            get_uluna_for_validator(&uluna_per_validator, d, &mut add, &mut remove);
+        let utoken_for_validator =
+            get_utoken_for_validator(&utoken_per_validator, d, &mut add, &mut remove);
+
+        if full_validators.contains(&d.validator) {
+            continue;
+        }
 
-        let mut uluna_to_undelegate = if d.amount < uluna_for_validator {
+        let mut utoken_to_undelegate = if d.amount < utoken_for_validator {
             0
         } else {
-            d.amount - uluna_for_validator
+            d.amount - utoken_for_validator
         };
 
-        if uluna_to_undelegate > 0 {
-            uluna_to_undelegate = std::cmp::min(uluna_to_undelegate, uluna_available);
-            uluna_available -= uluna_to_undelegate;
+        if utoken_to_undelegate > 0 {
+            utoken_to_undelegate = std::cmp::min(utoken_to_undelegate, utoken_available);
+            utoken_available -= utoken_to_undelegate;
 
-            if uluna_to_undelegate > 0 {
-                new_undelegations.push(Undelegation::new(&d.validator, uluna_to_undelegate));
+            if utoken_to_undelegate > 0 {
+                new_undelegations.push(Undelegation::new(&d.validator, utoken_to_undelegate));
             }
 
-            if uluna_available == 0 {
+            if utoken_available == 0 {
                 break;
             }
         }
     }
 
-    if uluna_available > 0 {
-        Err(ContractError::ComputeUndelegationsWrong(uluna_available))?
+    if utoken_available > 0 {
+        for d in current_delegations.iter().filter(|d| !full_validators.contains(&d.validator)) {
+            let existing = new_undelegations.iter_mut().find(|u| u.validator == d.validator);
+            let undelegated = existing.as_ref().map_or(0, |u| u.amount);
+
+            let utoken_to_undelegate = std::cmp::min(d.amount - undelegated, utoken_available);
+            if utoken_to_undelegate == 0 {
+                continue;
+            }
+            utoken_available -= utoken_to_undelegate;
+
+            match existing {
+                Some(undelegation) => undelegation.amount += utoken_to_undelegate,
+                None => {
+                    new_undelegations.push(Undelegation::new(&d.validator, utoken_to_undelegate))
+                },
+            }
+
+            if utoken_available == 0 {
+                break;
+            }
+        }
+    }
+
+    if utoken_available > 0 {
+        Err(ContractError::ComputeUndelegationsWrong(utoken_available))?
     }
 
     Ok(new_undelegations)
@@ -116,48 +235,61 @@ pub(crate) fn compute_undelegations(
 ///
 /// This function is based on Lido's implementation:
 /// https://github.com/lidofinance/lido-terra-contracts/blob/v1.0.2/contracts/lido_terra_validators_registry/src/common.rs#L19-L53
+///
+/// If the validator to remove is still locked by an incoming redelegation, nothing can be moved and
+/// the whole delegation is returned as deferred.
 pub(crate) fn compute_redelegations_for_removal(
     state: &State,
     storage: &dyn Storage,
     delegation_to_remove: &Delegation,
     current_delegations: &[Delegation],
     validators: Vec<String>,
-) -> StdResult<Vec<Redelegation>> {
-    let uluna_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
-    let uluna_to_distribute = uluna_staked + delegation_to_remove.amount;
+    locked_validators: &HashSet<String>,
+) -> StdResult<(Vec<Redelegation>, Vec<Delegation>)> {
+    if locked_validators.contains(&delegation_to_remove.validator) {
+        let deferred = if delegation_to_remove.amount > 0 {
+            vec![delegation_to_remove.clone()]
+        } else {
+            vec![]
+        };
+        return Ok((vec![], deferred));
+    }
 
-    let (uluna_per_validator, mut add, mut remove, _) =
-        get_uluna_per_validator(state, storage, uluna_to_distribute, &validators, None)?;
+    let utoken_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    let utoken_to_distribute = utoken_staked + delegation_to_remove.amount;
+
+    let (utoken_per_validator, mut add, mut remove, _) =
+        get_utoken_per_validator(state, storage, utoken_to_distribute, &validators, None)?;
 
     let mut new_redelegations: Vec<Redelegation> = vec![];
-    let mut uluna_available = delegation_to_remove.amount;
+    let mut utoken_available = delegation_to_remove.amount;
     for (_, d) in merge_with_validators(current_delegations, validators).iter().enumerate() {
-        let uluna_for_validator =
-            get_uluna_for_validator(&uluna_per_validator, d, &mut add, &mut remove);
+        let utoken_for_validator =
+            get_utoken_for_validator(&utoken_per_validator, d, &mut add, &mut remove);
 
-        let mut uluna_to_redelegate = if d.amount > uluna_for_validator {
+        let mut utoken_to_redelegate = if d.amount > utoken_for_validator {
             0
         } else {
-            uluna_for_validator - d.amount
+            utoken_for_validator - d.amount
         };
 
-        uluna_to_redelegate = std::cmp::min(uluna_to_redelegate, uluna_available);
-        uluna_available -= uluna_to_redelegate;
+        utoken_to_redelegate = std::cmp::min(utoken_to_redelegate, utoken_available);
+        utoken_available -= utoken_to_redelegate;
 
-        if uluna_to_redelegate > 0 {
+        if utoken_to_redelegate > 0 {
             new_redelegations.push(Redelegation::new(
                 &delegation_to_remove.validator,
                 &d.validator,
-                uluna_to_redelegate,
+                utoken_to_redelegate,
             ));
         }
 
-        if uluna_available == 0 {
+        if utoken_available == 0 {
             break;
         }
     }
 
-    Ok(new_redelegations)
+    Ok((new_redelegations, vec![]))
 }
 
 // adds all validators stored in the config that do not received delegations right now
@@ -181,41 +313,45 @@ fn merge_with_validators(
     delegations
 }
 
-fn get_uluna_for_validator(
-    uluna_per_validator: &HashMap<String, Uint128>,
+fn get_utoken_for_validator(
+    utoken_per_validator: &HashMap<String, Uint128>,
     delegation: &Delegation,
     add: &mut Option<u128>,
     remove: &mut Option<u128>,
 ) -> u128 {
-    let mut uluna_for_validator =
-        uluna_per_validator.get(&delegation.validator).map(|a| a.u128()).unwrap_or_default();
+    let mut utoken_for_validator =
+        utoken_per_validator.get(&delegation.validator).map(|a| a.u128()).unwrap_or_default();
     if let Some(add_set) = *add {
-        uluna_for_validator += add_set;
+        utoken_for_validator += add_set;
         *add = None;
     }
     if let Some(remove_set) = *remove {
-        if uluna_for_validator >= remove_set {
-            uluna_for_validator -= remove_set;
+        if utoken_for_validator >= remove_set {
+            utoken_for_validator -= remove_set;
             *remove = None;
         }
     }
-    uluna_for_validator
+    utoken_for_validator
 }
 
 /// Compute redelegation moves that will make each validator's delegation the targeted amount (hopefully
 /// this sentence makes sense)
 ///
 /// This algorithm does not guarantee the minimal number of moves, but is the best I can some up with...
+///
+/// Validators that are still locked by an incoming redelegation can't be used as source, the amounts
+/// that should have been moved away from them are returned as deferred.
 pub(crate) fn compute_redelegations_for_rebalancing(
     state: &State,
     storage: &dyn Storage,
     current_delegations: &[Delegation],
     validators: Vec<String>,
-) -> StdResult<Vec<Redelegation>> {
-    let uluna_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    locked_validators: &HashSet<String>,
+) -> StdResult<(Vec<Redelegation>, Vec<Delegation>)> {
+    let utoken_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
 
-    let (uluna_per_validator, mut add, mut remove, _) =
-        get_uluna_per_validator(state, storage, uluna_staked, &validators, None)?;
+    let (utoken_per_validator, mut add, mut remove, _) =
+        get_utoken_per_validator(state, storage, utoken_staked, &validators, None)?;
 
     // If a validator's current delegated amount is greater than the target amount, Luna will be
     // redelegated _from_ them. They will be put in `src_validators` vector
@@ -223,16 +359,22 @@ pub(crate) fn compute_redelegations_for_rebalancing(
     // redelegated _to_ them. They will be put in `dst_validators` vector
     let mut src_delegations: Vec<Delegation> = vec![];
     let mut dst_delegations: Vec<Delegation> = vec![];
+    let mut deferred: Vec<Delegation> = vec![];
     for (_, d) in merge_with_validators(current_delegations, validators).iter().enumerate() {
-        let uluna_for_validator =
-            get_uluna_for_validator(&uluna_per_validator, d, &mut add, &mut remove);
+        let utoken_for_validator =
+            get_utoken_for_validator(&utoken_per_validator, d, &mut add, &mut remove);
 
-        match d.amount.cmp(&uluna_for_validator) {
+        match d.amount.cmp(&utoken_for_validator) {
+            Ordering::Greater if locked_validators.contains(&d.validator) => {
+                deferred.push(Delegation::new(&d.validator, d.amount - utoken_for_validator));
+            },
             Ordering::Greater => {
-                src_delegations.push(Delegation::new(&d.validator, d.amount - uluna_for_validator));
+                src_delegations
+                    .push(Delegation::new(&d.validator, d.amount - utoken_for_validator));
             },
             Ordering::Less => {
-                dst_delegations.push(Delegation::new(&d.validator, uluna_for_validator - d.amount));
+                dst_delegations
+                    .push(Delegation::new(&d.validator, utoken_for_validator - d.amount));
             },
             Ordering::Equal => (),
         }
@@ -242,33 +384,78 @@ pub(crate) fn compute_redelegations_for_rebalancing(
     while !src_delegations.is_empty() && !dst_delegations.is_empty() {
         let src_delegation = src_delegations[0].clone();
         let dst_delegation = dst_delegations[0].clone();
-        let uluna_to_redelegate = cmp::min(src_delegation.amount, dst_delegation.amount);
+        let utoken_to_redelegate = cmp::min(src_delegation.amount, dst_delegation.amount);
 
-        if src_delegation.amount == uluna_to_redelegate {
+        if src_delegation.amount == utoken_to_redelegate {
             src_delegations.remove(0);
         } else {
-            src_delegations[0].amount -= uluna_to_redelegate;
+            src_delegations[0].amount -= utoken_to_redelegate;
         }
 
-        if dst_delegation.amount == uluna_to_redelegate {
+        if dst_delegation.amount == utoken_to_redelegate {
             dst_delegations.remove(0);
         } else {
-            dst_delegations[0].amount -= uluna_to_redelegate;
+            dst_delegations[0].amount -= utoken_to_redelegate;
         }
 
         new_redelegations.push(Redelegation::new(
             &src_delegation.validator,
             &dst_delegation.validator,
-            uluna_to_redelegate,
+            utoken_to_redelegate,
         ));
     }
 
-    Ok(new_redelegations)
+    Ok((new_redelegations, deferred))
+}
+
+/// Split the amount to bond across the validators in proportion to how far their delegation is below
+/// their goal. Only the `max_delegations` validators with the largest deficits receive a part, no
+/// delegations are returned if every validator has reached its goal.
+pub(crate) fn compute_split_delegations(
+    utoken_to_bond: Uint128,
+    current_delegations: &[Delegation],
+    utoken_per_validator: &HashMap<String, Uint128>,
+    max_delegations: usize,
+) -> Vec<Delegation> {
+    let mut deficits = utoken_per_validator
+        .iter()
+        .filter_map(|(validator, goal)| {
+            let current = current_delegations
+                .iter()
+                .find(|d| d.validator == *validator)
+                .map(|d| d.amount)
+                .unwrap_or(0);
+            goal.u128().checked_sub(current).filter(|deficit| *deficit > 0).map(|d| (validator, d))
+        })
+        .collect::<Vec<_>>();
+
+    // largest deficits first, ties are ordered by validator to be deterministic
+    deficits.sort_by(|(val_a, a), (val_b, b)| b.cmp(a).then_with(|| val_a.cmp(val_b)));
+    deficits.truncate(max_delegations);
+
+    let total_deficit: u128 = deficits.iter().map(|(_, deficit)| deficit).sum();
+    if total_deficit == 0 || utoken_to_bond.is_zero() {
+        return vec![];
+    }
+
+    let mut new_delegations = deficits
+        .into_iter()
+        .map(|(validator, deficit)| {
+            Delegation::new(validator, utoken_to_bond.multiply_ratio(deficit, total_deficit).u128())
+        })
+        .collect::<Vec<_>>();
+
+    // the rounding remainder goes to the validator with the largest deficit
+    let split: u128 = new_delegations.iter().map(|d| d.amount).sum();
+    new_delegations[0].amount += utoken_to_bond.u128() - split;
+    new_delegations.retain(|d| d.amount > 0);
+
+    new_delegations
 }
 
-/// Load uluna per validator
+/// Load utoken per validator
 /// If no goal is provided, the stored goal or uniform distribution is used.
-pub(crate) fn get_uluna_per_validator_prepared(
+pub(crate) fn get_utoken_per_validator_prepared(
     state: &State,
     storage: &dyn Storage,
     querier: &QuerierWrapper,
@@ -276,32 +463,33 @@ pub(crate) fn get_uluna_per_validator_prepared(
     goal: Option<WantedDelegationsShare>,
     validators: Option<Vec<String>>,
 ) -> StdResult<UtokenPerValidator> {
-    let current_delegations = query_all_delegations(querier, contract)?;
-    let uluna_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
+    let denom = state.get_denom(storage)?;
+    let current_delegations = query_all_delegations(querier, contract, &denom)?;
+    let utoken_staked: u128 = current_delegations.iter().map(|d| d.amount).sum();
     let validators = if let Some(validators) = validators {
         validators
     } else {
         state.validators.load(storage)?
     };
 
-    get_uluna_per_validator(state, storage, uluna_staked, &validators, goal)
+    get_utoken_per_validator(state, storage, utoken_staked, &validators, goal)
 }
 
-pub(crate) fn get_uluna_per_validator(
+pub(crate) fn get_utoken_per_validator(
     state: &State,
     storage: &dyn Storage,
-    uluna_staked: u128,
+    utoken_staked: u128,
     validators: &[String],
     goal: Option<WantedDelegationsShare>,
 ) -> StdResult<UtokenPerValidator> {
-    let uluna_staked_uint = Uint128::new(uluna_staked);
+    let utoken_staked_uint = Uint128::new(utoken_staked);
     let delegation_goal = if goal.is_some() {
         goal
     } else {
         state.delegation_goal.may_load(storage)?
     };
 
-    let uluna_per_validator: Option<HashMap<_, _>> =
+    let utoken_per_validator: Option<HashMap<_, _>> =
         if let Some(delegation_goal) = delegation_goal.clone() {
             if !delegation_goal.shares.is_empty() {
                 // calculate via distribution
@@ -310,7 +498,7 @@ pub(crate) fn get_uluna_per_validator(
                         .shares
                         .into_iter()
                         .map(|d| -> StdResult<(String, Uint128)> {
-                            Ok((d.0, d.1.checked_mul_uint(uluna_staked_uint)?))
+                            Ok((d.0, d.1.checked_mul_uint(utoken_staked_uint)?))
                         })
                         .collect::<StdResult<HashMap<_, _>>>()?,
                 )
@@ -321,86 +509,86 @@ pub(crate) fn get_uluna_per_validator(
             None
         };
 
-    let uluna_per_validator = uluna_per_validator.unwrap_or_else(|| {
+    let utoken_per_validator = utoken_per_validator.unwrap_or_else(|| {
         let validator_count = validators.len() as u128;
-        let uluna_per_validator = uluna_staked / validator_count;
-        validators.iter().map(|d| (d.clone(), Uint128::new(uluna_per_validator))).collect()
+        let utoken_per_validator = utoken_staked / validator_count;
+        validators.iter().map(|d| (d.clone(), Uint128::new(utoken_per_validator))).collect()
     });
-    let total: u128 = uluna_per_validator.iter().map(|a| a.1.u128()).sum();
-    let add = if total < uluna_staked {
-        Some(uluna_staked - total)
+    let total: u128 = utoken_per_validator.iter().map(|a| a.1.u128()).sum();
+    let add = if total < utoken_staked {
+        Some(utoken_staked - total)
     } else {
         None
     };
-    let remove = if total > uluna_staked {
-        Some(total - uluna_staked)
+    let remove = if total > utoken_staked {
+        Some(total - utoken_staked)
     } else {
         None
     };
-    Ok((uluna_per_validator, add, remove, delegation_goal))
+    Ok((utoken_per_validator, add, remove, delegation_goal))
 }
 
 //--------------------------------------------------------------------------------------------------
 // Batch logics
 //--------------------------------------------------------------------------------------------------
 
-/// If the received uluna amount after the unbonding period is less than expected, e.g. due to rounding
+/// If the received utoken amount after the unbonding period is less than expected, e.g. due to rounding
 /// error or the validator(s) being slashed, then deduct the difference in amount evenly from each
 /// unreconciled batch.
 ///
 /// The idea of "reconciling" is based on Stader's implementation:
 /// https://github.com/stader-labs/stader-liquid-token/blob/v0.2.1/contracts/staking/src/contract.rs#L968-L1048
-pub(crate) fn reconcile_batches(batches: &mut [Batch], uluna_to_deduct: Uint128) {
+pub(crate) fn reconcile_batches(batches: &mut [Batch], utoken_to_deduct: Uint128) {
     let batch_count = batches.len() as u128;
-    let uluna_per_batch = uluna_to_deduct.u128() / batch_count;
-    let remainder = uluna_to_deduct.u128() % batch_count;
+    let utoken_per_batch = utoken_to_deduct.u128() / batch_count;
+    let remainder = utoken_to_deduct.u128() % batch_count;
     let mut underflows: HashMap<usize, Uint128> = HashMap::default();
 
     for (i, batch) in batches.iter_mut().enumerate() {
         let remainder_for_batch: u128 = u128::from((i + 1) as u128 <= remainder);
-        let uluna_for_batch = uluna_per_batch + remainder_for_batch;
-        let uluna_for_batch = Uint128::new(uluna_for_batch);
+        let utoken_for_batch = utoken_per_batch + remainder_for_batch;
+        let utoken_for_batch = Uint128::new(utoken_for_batch);
 
         // check for underflow
-        if batch.uluna_unclaimed < uluna_for_batch && batch_count > 1 {
-            underflows.insert(i, uluna_for_batch - batch.uluna_unclaimed);
+        if batch.utoken_unclaimed < utoken_for_batch && batch_count > 1 {
+            underflows.insert(i, utoken_for_batch - batch.utoken_unclaimed);
         }
 
-        batch.uluna_unclaimed = batch.uluna_unclaimed.saturating_sub(uluna_for_batch);
+        batch.utoken_unclaimed = batch.utoken_unclaimed.saturating_sub(utoken_for_batch);
         batch.reconciled = true;
     }
 
     if !underflows.is_empty() {
         let batch_count: u128 = batch_count - (underflows.len() as u128);
         let to_deduct: Uint128 = underflows.iter().map(|v| v.1).sum();
-        let uluna_per_batch = to_deduct.u128() / batch_count;
+        let utoken_per_batch = to_deduct.u128() / batch_count;
         let remainder = to_deduct.u128() % batch_count;
         let mut remaining_underflow = Uint128::zero();
         // distribute the underflows uniformly accross non-underflowing batches
         for (i, batch) in batches.iter_mut().enumerate() {
-            if !batch.uluna_unclaimed.is_zero() {
+            if !batch.utoken_unclaimed.is_zero() {
                 let remainder_for_batch: u128 = u128::from((i + 1) as u128 <= remainder);
-                let uluna_for_batch = uluna_per_batch + remainder_for_batch;
-                let uluna_for_batch = Uint128::new(uluna_for_batch);
+                let utoken_for_batch = utoken_per_batch + remainder_for_batch;
+                let utoken_for_batch = Uint128::new(utoken_for_batch);
 
-                if batch.uluna_unclaimed < uluna_for_batch && batch_count > 1 {
-                    remaining_underflow += uluna_for_batch - batch.uluna_unclaimed;
+                if batch.utoken_unclaimed < utoken_for_batch && batch_count > 1 {
+                    remaining_underflow += utoken_for_batch - batch.utoken_unclaimed;
                 }
 
-                batch.uluna_unclaimed = batch.uluna_unclaimed.saturating_sub(uluna_for_batch);
+                batch.utoken_unclaimed = batch.utoken_unclaimed.saturating_sub(utoken_for_batch);
             }
         }
 
         if !remaining_underflow.is_zero() {
             // the remaining underflow will be applied by oldest batch first.
             for (_, batch) in batches.iter_mut().enumerate() {
-                if !batch.uluna_unclaimed.is_zero() && !remaining_underflow.is_zero() {
-                    if batch.uluna_unclaimed >= remaining_underflow {
-                        batch.uluna_unclaimed -= remaining_underflow;
+                if !batch.utoken_unclaimed.is_zero() && !remaining_underflow.is_zero() {
+                    if batch.utoken_unclaimed >= remaining_underflow {
+                        batch.utoken_unclaimed -= remaining_underflow;
                         remaining_underflow = Uint128::zero()
                     } else {
-                        remaining_underflow -= batch.uluna_unclaimed;
-                        batch.uluna_unclaimed = Uint128::zero();
+                        remaining_underflow -= batch.utoken_unclaimed;
+                        batch.utoken_unclaimed = Uint128::zero();
                     }
                 }
             }
@@ -0,0 +1,31 @@
+use cosmwasm_std::{Coin, CosmosMsg, StdResult};
+
+use super::{encode, encode_coin};
+
+// Messages of the liquid staking module (LSM). Tokenized delegation shares are bank coins with the
+// denom `{validator}/{record_id}`.
+
+/// Returns the validator of a tokenized share denom, or `None` if the denom is not one
+pub fn parse_share_denom(denom: &str) -> Option<&str> {
+    let mut parts = denom.split('/');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(validator), Some(record_id), None)
+            if !validator.is_empty() && record_id.parse::<u64>().is_ok() =>
+        {
+            Some(validator)
+        },
+        _ => None,
+    }
+}
+
+/// Redeems the tokenized shares into a delegation of the sender
+pub fn redeem_tokens_for_shares_msg(sender: &str, shares: &Coin) -> StdResult<CosmosMsg> {
+    let shares = encode_coin(shares)?;
+    Ok(CosmosMsg::Stargate {
+        type_url: "/cosmos.staking.v1beta1.MsgRedeemTokensForShares".to_string(),
+        value: encode(|os| {
+            os.write_string(1, sender)?;
+            os.write_bytes(2, shares.as_slice())
+        })?,
+    })
+}
@@ -1,9 +1,11 @@
-use cosmwasm_std::{Binary, CosmosMsg};
-use protobuf::Message;
+use cosmwasm_std::{Binary, Coin, CosmosMsg, StdError, StdResult};
+use protobuf::{CodedOutputStream, Message};
 
 use self::proto::MsgVoteWeighted;
 
+pub mod lsm;
 pub mod proto;
+pub mod tokenfactory;
 
 impl MsgVoteWeighted {
     pub fn to_cosmos_msg(&self) -> CosmosMsg {
@@ -15,3 +17,26 @@ impl MsgVoteWeighted {
         }
     }
 }
+
+/// Encodes a message field by field, used for messages that are too small to warrant generated
+/// proto definitions
+pub(crate) fn encode<F>(write: F) -> StdResult<Binary>
+where
+    F: FnOnce(&mut CodedOutputStream) -> protobuf::Result<()>,
+{
+    let mut bytes: Vec<u8> = vec![];
+    {
+        let mut os = CodedOutputStream::vec(&mut bytes);
+        write(&mut os)
+            .and_then(|_| os.flush())
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+    }
+    Ok(Binary::from(bytes))
+}
+
+pub(crate) fn encode_coin(coin: &Coin) -> StdResult<Binary> {
+    encode(|os| {
+        os.write_string(1, &coin.denom)?;
+        os.write_string(2, &coin.amount.to_string())
+    })
+}
@@ -0,0 +1,40 @@
+use cosmwasm_std::{Coin, CosmosMsg, StdResult};
+
+use super::{encode, encode_coin};
+
+// The token factory messages only consist of strings and coins, so they are encoded by hand
+// instead of generating the full proto definitions.
+
+pub fn create_denom_msg(sender: &str, subdenom: &str) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Stargate {
+        type_url: "/osmosis.tokenfactory.v1beta1.MsgCreateDenom".to_string(),
+        value: encode(|os| {
+            os.write_string(1, sender)?;
+            os.write_string(2, subdenom)
+        })?,
+    })
+}
+
+/// Mints the coin to the sender
+pub fn mint_msg(sender: &str, coin: &Coin) -> StdResult<CosmosMsg> {
+    let coin = encode_coin(coin)?;
+    Ok(CosmosMsg::Stargate {
+        type_url: "/osmosis.tokenfactory.v1beta1.MsgMint".to_string(),
+        value: encode(|os| {
+            os.write_string(1, sender)?;
+            os.write_bytes(2, coin.as_slice())
+        })?,
+    })
+}
+
+/// Burns the coin from the balance of the sender
+pub fn burn_msg(sender: &str, coin: &Coin) -> StdResult<CosmosMsg> {
+    let coin = encode_coin(coin)?;
+    Ok(CosmosMsg::Stargate {
+        type_url: "/osmosis.tokenfactory.v1beta1.MsgBurn".to_string(),
+        value: encode(|os| {
+            os.write_string(1, sender)?;
+            os.write_bytes(2, coin.as_slice())
+        })?,
+    })
+}
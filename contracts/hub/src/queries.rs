@@ -1,17 +1,21 @@
+use std::ops::Div;
+
 use cosmwasm_std::{Addr, Decimal, Deps, Env, Order, StdResult, Uint128};
 use cw_storage_plus::Bound;
 
+use eris::constants::YEAR;
 use eris::governance_helper::get_period;
 use eris::hub::{
-    Batch, ConfigResponse, PendingBatch, StateResponse, UnbondRequestsByBatchResponseItem,
+    Batch, ConfigResponse, ExchangeHistory, ExchangeRatesResponse, PendingBatch,
+    PendingRewardsResponseItem, SlashingEvent, StateResponse, UnbondRequestsByBatchResponseItem,
     UnbondRequestsByUserResponseItem, UnbondRequestsByUserResponseItemDetails,
-    WantedDelegationsResponse,
+    UnbondingSlotsResponse, UnbondingSlotsResponseItem, UserHistoryItem, WantedDelegationsResponse,
 };
 use itertools::Itertools;
 
-use crate::constants::CONTRACT_DENOM;
-use crate::helpers::{get_wanted_delegations, query_all_delegations, query_cw20_total_supply};
-use crate::math::get_uluna_per_validator_prepared;
+use crate::constants::MAX_UNBONDING_ENTRIES;
+use crate::helpers::{get_wanted_delegations, query_all_delegations};
+use crate::math::get_utoken_per_validator_prepared;
 use crate::state::State;
 use crate::types::gauges::PeriodGaugeLoader;
 
@@ -24,7 +28,7 @@ pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
     Ok(ConfigResponse {
         owner: state.owner.load(deps.storage)?.into(),
         new_owner: state.new_owner.may_load(deps.storage)?.map(|addr| addr.into()),
-        stake_token: state.stake_token.load(deps.storage)?.into(),
+        stake_token: state.get_stake_token(deps.storage)?.to_string(),
         epoch_period: state.epoch_period.load(deps.storage)?,
         unbond_period: state.unbond_period.load(deps.storage)?,
         validators: state.validators.load(deps.storage)?,
@@ -50,18 +54,59 @@ pub fn config(deps: Deps) -> StdResult<ConfigResponse> {
                 max_delegation_bps,
                 validator_count,
             },
+            eris::hub::DelegationStrategy::Defined {
+                shares_bps,
+            } => eris::hub::DelegationStrategy::Defined {
+                shares_bps,
+            },
+            eris::hub::DelegationStrategy::Hybrid {
+                core_shares_bps,
+                amp_gauges,
+                emp_gauges,
+                amp_factor_bps,
+                min_delegation_bps,
+                max_delegation_bps,
+                validator_count,
+            } => eris::hub::DelegationStrategy::Hybrid {
+                core_shares_bps,
+                amp_gauges: amp_gauges.to_string(),
+                emp_gauges: emp_gauges.map(|a| a.to_string()),
+                amp_factor_bps,
+                min_delegation_bps,
+                max_delegation_bps,
+                validator_count,
+            },
         },
+        validator_checks: state.validator_checks.may_load(deps.storage)?,
+        guardian: state.guardian.may_load(deps.storage)?.map(|addr| addr.into()),
+        pause_flags: state.pause_flags.may_load(deps.storage)?.unwrap_or_default(),
+        fee_receivers: state
+            .fee_receivers
+            .may_load(deps.storage)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|receiver| receiver.into_unchecked())
+            .collect(),
+        auto_maintenance: state.auto_maintenance.may_load(deps.storage)?,
+        keeper_reward: state.keeper_reward.may_load(deps.storage)?,
+        prop_gauges: state.prop_gauges.may_load(deps.storage)?.map(|addr| addr.into()),
+        dust_cleanup: state.dust_cleanup.may_load(deps.storage)?,
+        split_bond: state.split_bond.may_load(deps.storage)?,
+        denom: state.get_denom(deps.storage)?,
+        config_timelock: state.get_config_timelock(deps.storage)?,
+        pending_config_change: state.pending_config_change.may_load(deps.storage)?,
     })
 }
 
 pub fn state(deps: Deps, env: Env) -> StdResult<StateResponse> {
     let state = State::default();
 
-    let stake_token = state.stake_token.load(deps.storage)?;
-    let total_ustake = query_cw20_total_supply(&deps.querier, &stake_token)?;
+    let stake_token = state.get_stake_token(deps.storage)?;
+    let total_ustake = stake_token.query_supply(&deps.querier)?;
+    let denom = state.get_denom(deps.storage)?;
 
-    let delegations = query_all_delegations(&deps.querier, &env.contract.address)?;
-    let total_uluna: u128 = delegations.iter().map(|d| d.amount).sum();
+    let delegations = query_all_delegations(&deps.querier, &env.contract.address, &denom)?;
+    let total_utoken: u128 = delegations.iter().map(|d| d.amount).sum();
 
     // only not reconciled batches are relevant as they are still unbonding and estimated unbond time in the future.
     let unbonding: u128 = state
@@ -75,34 +120,38 @@ pub fn state(deps: Deps, env: Env) -> StdResult<StateResponse> {
             v
         })
         .filter(|item| item.est_unbond_end_time > env.block.time.seconds())
-        .map(|item| item.uluna_unclaimed.u128())
+        .map(|item| item.utoken_unclaimed.u128())
         .sum();
 
-    let available = deps.querier.query_balance(&env.contract.address, CONTRACT_DENOM)?.amount;
+    let available = deps.querier.query_balance(&env.contract.address, denom)?.amount;
+    let liquidity_buffer = state.liquidity_buffer.may_load(deps.storage)?.unwrap_or_default();
 
     let exchange_rate = if total_ustake.is_zero() {
         Decimal::one()
     } else {
-        Decimal::from_ratio(total_uluna, total_ustake)
+        Decimal::from_ratio(Uint128::new(total_utoken) + liquidity_buffer, total_ustake)
     };
 
     Ok(StateResponse {
         total_ustake,
-        total_uluna: Uint128::new(total_uluna),
+        total_utoken: Uint128::new(total_utoken),
         exchange_rate,
         unlocked_coins: state.unlocked_coins.load(deps.storage)?,
         unbonding: Uint128::from(unbonding),
         available,
-        tvl_uluna: Uint128::from(total_uluna)
+        tvl_utoken: Uint128::from(total_utoken)
             .checked_add(Uint128::from(unbonding))?
             .checked_add(available)?,
+        liquidity_buffer,
+        liquidity_buffer_config: state.liquidity_buffer_config.may_load(deps.storage)?,
+        storage_metrics: state.storage_metrics.may_load(deps.storage)?.unwrap_or_default(),
     })
 }
 
 pub fn wanted_delegations(deps: Deps, env: Env) -> StdResult<WantedDelegationsResponse> {
     let state = State::default();
 
-    let (delegations, _, _, share) = get_uluna_per_validator_prepared(
+    let (delegations, _, _, share) = get_utoken_per_validator_prepared(
         &state,
         deps.storage,
         &deps.querier,
@@ -138,7 +187,7 @@ pub fn simulate_wanted_delegations(
         },
     )?;
 
-    let (delegations, _, _, share) = get_uluna_per_validator_prepared(
+    let (delegations, _, _, share) = get_utoken_per_validator_prepared(
         &state,
         deps.storage,
         &deps.querier,
@@ -200,6 +249,118 @@ pub fn previous_batches(
         .collect()
 }
 
+pub fn exchange_rates(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ExchangeRatesResponse> {
+    let state = State::default();
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let end = start_after.map(Bound::exclusive);
+    let exchange_rates = state
+        .exchange_history
+        .range(deps.storage, None, end, Order::Descending)
+        .take(limit)
+        .collect::<StdResult<Vec<(u64, ExchangeHistory)>>>()?;
+
+    let apr: Option<Decimal> = if exchange_rates.len() > 1 {
+        let (_, current) = exchange_rates.first().unwrap();
+        let (_, last) = exchange_rates.last().unwrap();
+
+        let delta_time_s = current.time_s - last.time_s;
+        // a falling exchange rate is clamped to an APR of 0
+        let delta_rate = current.exchange_rate.checked_sub(last.exchange_rate).unwrap_or_default();
+
+        Some(
+            delta_rate
+                .checked_mul(Decimal::from_ratio(YEAR, delta_time_s).div(last.exchange_rate))?,
+        )
+    } else {
+        None
+    };
+
+    Ok(ExchangeRatesResponse {
+        exchange_rates,
+        apr,
+    })
+}
+
+pub fn pending_rewards(deps: Deps) -> StdResult<Vec<PendingRewardsResponseItem>> {
+    let state = State::default();
+    let denom = state.get_denom(deps.storage)?;
+
+    state
+        .unlocked_coins
+        .load(deps.storage)?
+        .into_iter()
+        .filter(|coin| coin.denom != denom)
+        .map(|coin| {
+            let swap_route = state
+                .reward_swap_routes
+                .may_load(deps.storage, &coin.denom)?
+                .map(|route| route.into_unchecked());
+
+            Ok(PendingRewardsResponseItem {
+                reward: coin,
+                swap_route,
+            })
+        })
+        .collect()
+}
+
+pub fn slashing_events(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<SlashingEvent>> {
+    let state = State::default();
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    state
+        .slashing_events
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
+
+pub fn unbonding_slots(deps: Deps, env: Env) -> StdResult<UnbondingSlotsResponse> {
+    let state = State::default();
+    let now = env.block.time.seconds();
+
+    // removed validators can still have open entries
+    let mut validators = state.validators.load(deps.storage)?;
+    for validator in state.unbonding_entries.keys(deps.storage, None, None, Order::Ascending) {
+        let validator = validator?;
+        if !validators.contains(&validator) {
+            validators.push(validator);
+        }
+    }
+
+    let validators = validators
+        .into_iter()
+        .map(|validator| {
+            let completion_times = state.open_unbonding_entries(deps.storage, &validator, now)?;
+            Ok(UnbondingSlotsResponseItem {
+                validator,
+                used: completion_times.len() as u32,
+                completion_times,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(UnbondingSlotsResponse {
+        max_entries: MAX_UNBONDING_ENTRIES,
+        validators,
+    })
+}
+
 pub fn unbond_requests_by_batch(
     deps: Deps,
     id: u64,
@@ -313,3 +474,27 @@ pub fn unbond_requests_by_user_details(
         })
         .collect()
 }
+
+pub fn user_history(
+    deps: Deps,
+    user: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<UserHistoryItem>> {
+    let state = State::default();
+
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let addr = deps.api.addr_validate(&user)?;
+    let end = start_after.map(Bound::exclusive);
+
+    state
+        .user_history
+        .prefix(&addr)
+        .range(deps.storage, None, end, Order::Descending)
+        .take(limit)
+        .map(|item| {
+            let (_, v) = item?;
+            Ok(v)
+        })
+        .collect()
+}
@@ -1,12 +1,19 @@
-use cosmwasm_std::{Addr, Coin, Storage};
-use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use std::collections::HashSet;
+
+use cosmwasm_std::{Addr, Coin, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 use eris::hub::{
-    Batch, DelegationStrategy, FeeConfig, PendingBatch, UnbondRequest, WantedDelegationsShare,
+    AutoMaintenanceConfig, Batch, DelegationStrategy, DustCleanupConfig, ExchangeHistory,
+    FeeConfig, FeeReceiver, KeeperRewardConfig, LiquidityBufferConfig, PausableOperation,
+    PauseFlags, PendingBatch, PendingConfigChange, RewardSwapRoute, SlashingEvent, SplitBondConfig,
+    StorageMetrics, UnbondRequest, UserHistoryItem, ValidatorChecks, WantedDelegationsShare,
 };
 use itertools::Itertools;
 
-use crate::{error::ContractError, types::BooleanKey};
+use crate::constants::{CONTRACT_DENOM, MAX_UNBONDING_ENTRIES};
+use crate::error::ContractError;
+use crate::types::{BooleanKey, StakeToken};
 
 pub(crate) struct State<'a> {
     /// Account who can call certain privileged functions
@@ -15,6 +22,8 @@ pub(crate) struct State<'a> {
     pub new_owner: Item<'a, Addr>,
     /// Address of the Liquid Staking token
     pub stake_token: Item<'a, Addr>,
+    /// Denom of the Liquid Staking token, if it is a token factory denom
+    pub stake_token_denom: Item<'a, String>,
     /// How often the unbonding queue is to be executed
     pub epoch_period: Item<'a, u64>,
     /// The staking module's unbonding time, in seconds
@@ -29,6 +38,8 @@ pub(crate) struct State<'a> {
     pub previous_batches: IndexedMap<'a, u64, Batch, PreviousBatchesIndexes<'a>>,
     /// Users' shares in unbonding batches
     pub unbond_requests: IndexedMap<'a, (u64, &'a Addr), UnbondRequest, UnbondRequestsIndexes<'a>>,
+    /// Number of entries in `previous_batches` and `unbond_requests`
+    pub storage_metrics: Item<'a, StorageMetrics>,
     /// Fee Config
     pub fee_config: Item<'a, FeeConfig>,
     /// Delegation Strategy
@@ -39,6 +50,69 @@ pub(crate) struct State<'a> {
     pub vote_operator: Item<'a, Addr>,
     /// Specifies wether the contract allows donations
     pub allow_donations: Item<'a, bool>,
+    /// Amount of utoken held by the contract that is available for instant unbonding
+    pub liquidity_buffer: Item<'a, Uint128>,
+    /// Target size, fee and refill rules of the liquidity buffer
+    pub liquidity_buffer_config: Item<'a, LiquidityBufferConfig>,
+    /// Amount of utoken the contract expects to be delegated to each validator
+    pub delegations_snapshot: Map<'a, &'a str, Uint128>,
+    /// Slashings detected by comparing the delegations with the snapshot
+    pub slashing_events: Map<'a, u64, SlashingEvent>,
+    /// Specifies wether slashed validators are removed automatically
+    pub remove_slashed_validators: Item<'a, bool>,
+    /// Routes used to swap reward coins to utoken, indexed by the reward denom
+    pub reward_swap_routes: Map<'a, &'a str, RewardSwapRoute<Addr>>,
+    /// Daily exchange rate checkpoints, indexed by the timestamp in days
+    pub exchange_history: Map<'a, u64, ExchangeHistory>,
+    /// Rules validators need to fulfill to receive delegations
+    pub validator_checks: Item<'a, ValidatorChecks>,
+    /// Whitelisted validators that failed the validator checks of the last `TuneDelegations`
+    pub ineligible_validators: Item<'a, Vec<String>>,
+    /// Whitelisted validators that were not part of the active set during the last `TuneDelegations`
+    pub inactive_validators: Item<'a, Vec<String>>,
+    /// Account who can pause and unpause operations
+    pub guardian: Item<'a, Addr>,
+    /// Operations that are currently paused
+    pub pause_flags: Item<'a, PauseFlags>,
+    /// Receivers the protocol fee is split across, replacing the protocol_fee_contract if set
+    pub fee_receivers: Item<'a, Vec<FeeReceiver<Addr>>>,
+    /// Fees sent with a wake-up message during the last reinvest, indexed by the position of the
+    /// receiver. Used to send the fee without the message if the wake-up fails.
+    pub fee_wake_ups: Map<'a, u64, (Addr, Uint128)>,
+    /// Completion time of the latest redelegation into a validator. Until then, the chain does not
+    /// allow redelegating away from it.
+    pub redelegation_locks: Map<'a, &'a str, u64>,
+    /// Completion times of the unbonding entries that are open with each validator
+    pub unbonding_entries: Map<'a, &'a str, Vec<u64>>,
+    /// Maintenance that is carried out when users bond, unbond or withdraw
+    pub auto_maintenance: Item<'a, AutoMaintenanceConfig>,
+    /// Block time of the last harvest, in seconds
+    pub last_harvest: Item<'a, u64>,
+    /// Account that triggered the running automatic harvest, it receives the keeper tip on reinvest
+    pub keeper: Item<'a, Addr>,
+    /// Reward paid to whoever calls an overdue maintenance action
+    pub keeper_reward: Item<'a, KeeperRewardConfig>,
+    /// Keepers waiting for the reward of a maintenance action, indexed by the action
+    pub keeper_claims: Map<'a, &'a str, Addr>,
+    /// Block time an action has last been rewarded, indexed by the action
+    pub keeper_rewarded: Map<'a, &'a str, u64>,
+    /// Prop gauges contract whose results can be used to vote on proposals
+    pub prop_gauges: Item<'a, Addr>,
+    /// Users who opted in to keep a history of their bond, unbond and withdraw events
+    pub history_users: Map<'a, &'a Addr, bool>,
+    /// History of the users who opted in, indexed by the user and the id of the entry
+    pub user_history: Map<'a, (&'a Addr, u64), UserHistoryItem>,
+    /// When dust of abandoned unbonding requests is swept to the protocol fee contract
+    pub dust_cleanup: Item<'a, DustCleanupConfig>,
+    /// Denom of the native token that is staked, not set for hubs staking `uluna` before it was
+    /// configurable
+    pub denom: Item<'a, String>,
+    /// Time a proposed config change has to wait until it can be applied, in seconds
+    pub config_timelock: Item<'a, u64>,
+    /// Config change that has been proposed and is waiting for its timelock
+    pub pending_config_change: Item<'a, PendingConfigChange>,
+    /// How deposits are split across the validators furthest below their delegation goal
+    pub split_bond: Item<'a, SplitBondConfig>,
 }
 
 impl Default for State<'static> {
@@ -61,6 +135,7 @@ impl Default for State<'static> {
             owner: Item::new("owner"),
             new_owner: Item::new("new_owner"),
             stake_token: Item::new("stake_token"),
+            stake_token_denom: Item::new("stake_token_denom"),
             epoch_period: Item::new("epoch_period"),
             unbond_period: Item::new("unbond_period"),
             validators: Item::new("validators"),
@@ -68,11 +143,42 @@ impl Default for State<'static> {
             pending_batch: Item::new("pending_batch"),
             previous_batches: IndexedMap::new("previous_batches", pb_indexes),
             unbond_requests: IndexedMap::new("unbond_requests", ubr_indexes),
+            storage_metrics: Item::new("storage_metrics"),
             fee_config: Item::new("fee_config"),
             delegation_strategy: Item::new("delegation_strategy"),
             delegation_goal: Item::new("delegation_goal"),
             vote_operator: Item::new("vote_operator"),
             allow_donations: Item::new("allow_donations"),
+            liquidity_buffer: Item::new("liquidity_buffer"),
+            liquidity_buffer_config: Item::new("liquidity_buffer_config"),
+            delegations_snapshot: Map::new("delegations_snapshot"),
+            slashing_events: Map::new("slashing_events"),
+            remove_slashed_validators: Item::new("remove_slashed_validators"),
+            reward_swap_routes: Map::new("reward_swap_routes"),
+            exchange_history: Map::new("exchange_history"),
+            validator_checks: Item::new("validator_checks"),
+            ineligible_validators: Item::new("ineligible_validators"),
+            inactive_validators: Item::new("inactive_validators"),
+            guardian: Item::new("guardian"),
+            pause_flags: Item::new("pause_flags"),
+            fee_receivers: Item::new("fee_receivers"),
+            fee_wake_ups: Map::new("fee_wake_ups"),
+            redelegation_locks: Map::new("redelegation_locks"),
+            unbonding_entries: Map::new("unbonding_entries"),
+            auto_maintenance: Item::new("auto_maintenance"),
+            last_harvest: Item::new("last_harvest"),
+            keeper: Item::new("keeper"),
+            keeper_reward: Item::new("keeper_reward"),
+            keeper_claims: Map::new("keeper_claims"),
+            keeper_rewarded: Map::new("keeper_rewarded"),
+            prop_gauges: Item::new("prop_gauges"),
+            history_users: Map::new("history_users"),
+            user_history: Map::new("user_history"),
+            dust_cleanup: Item::new("dust_cleanup"),
+            denom: Item::new("denom"),
+            config_timelock: Item::new("config_timelock"),
+            pending_config_change: Item::new("pending_config_change"),
+            split_bond: Item::new("split_bond"),
         }
     }
 }
@@ -87,6 +193,26 @@ impl<'a> State<'a> {
         }
     }
 
+    pub fn get_stake_token(&self, storage: &dyn Storage) -> StdResult<StakeToken> {
+        match self.stake_token_denom.may_load(storage)? {
+            Some(denom) => Ok(StakeToken::TokenFactory(denom)),
+            None => Ok(StakeToken::Cw20(self.stake_token.load(storage)?)),
+        }
+    }
+
+    pub fn get_denom(&self, storage: &dyn Storage) -> StdResult<String> {
+        Ok(self.denom.may_load(storage)?.unwrap_or_else(|| CONTRACT_DENOM.to_string()))
+    }
+
+    /// Without a configured timelock, stakers need to be able to queue an unbonding request and
+    /// have it finish before the change is applied
+    pub fn get_config_timelock(&self, storage: &dyn Storage) -> StdResult<u64> {
+        match self.config_timelock.may_load(storage)? {
+            Some(config_timelock) => Ok(config_timelock),
+            None => Ok(self.epoch_period.load(storage)? + self.unbond_period.load(storage)?),
+        }
+    }
+
     pub fn assert_vote_operator(
         &self,
         storage: &dyn Storage,
@@ -102,6 +228,185 @@ impl<'a> State<'a> {
         }
     }
 
+    /// The owner can always pause and unpause, e.g. if there is no guardian
+    pub fn assert_owner_or_guardian(
+        &self,
+        storage: &dyn Storage,
+        sender: &Addr,
+    ) -> Result<(), ContractError> {
+        if *sender == self.owner.load(storage)? {
+            return Ok(());
+        }
+
+        let guardian = self.guardian.load(storage).map_err(|_| ContractError::NoGuardianSet {})?;
+
+        if *sender == guardian {
+            Ok(())
+        } else {
+            Err(ContractError::UnauthorizedSenderNotGuardian {})
+        }
+    }
+
+    pub fn assert_not_paused(
+        &self,
+        storage: &dyn Storage,
+        operation: PausableOperation,
+    ) -> Result<(), ContractError> {
+        let pause_flags = self.pause_flags.may_load(storage)?.unwrap_or_default();
+
+        if pause_flags.is_paused(&operation) {
+            Err(ContractError::OperationPaused(operation))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Removes the validators that failed the last validator checks or were inactive, these neither
+    /// receive new delegations nor are part of the delegation goal
+    pub fn filter_eligible_validators(
+        &self,
+        storage: &dyn Storage,
+        mut validators: Vec<String>,
+    ) -> StdResult<Vec<String>> {
+        let ineligible = self.ineligible_validators.may_load(storage)?.unwrap_or_default();
+        let inactive = self.inactive_validators.may_load(storage)?.unwrap_or_default();
+        validators.retain(|v| !ineligible.contains(v) && !inactive.contains(v));
+        Ok(validators)
+    }
+
+    /// Increases the expected delegation of a validator after the contract delegated to it.
+    pub fn add_expected_delegation(
+        &self,
+        storage: &mut dyn Storage,
+        validator: &str,
+        amount: u128,
+    ) -> StdResult<()> {
+        self.delegations_snapshot.update(storage, validator, |expected| -> StdResult<_> {
+            Ok(expected.unwrap_or_default() + Uint128::new(amount))
+        })?;
+        Ok(())
+    }
+
+    /// Decreases the expected delegation of a validator after the contract moved funds away from it.
+    pub fn sub_expected_delegation(
+        &self,
+        storage: &mut dyn Storage,
+        validator: &str,
+        amount: u128,
+    ) -> StdResult<()> {
+        let expected = self.delegations_snapshot.may_load(storage, validator)?.unwrap_or_default();
+        let remaining = expected.saturating_sub(Uint128::new(amount));
+        if remaining.is_zero() {
+            // validators without delegation are not kept in the snapshot
+            self.delegations_snapshot.remove(storage, validator);
+        } else {
+            self.delegations_snapshot.save(storage, validator, &remaining)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the validators that can't be used as redelegation source at the given time.
+    pub fn locked_validators(&self, storage: &dyn Storage, now: u64) -> StdResult<HashSet<String>> {
+        self.redelegation_locks
+            .range(storage, None, None, Order::Ascending)
+            .filter(|item| match item {
+                Ok((_, completion_time)) => *completion_time > now,
+                Err(_) => true,
+            })
+            .map(|item| item.map(|(validator, _)| validator))
+            .collect()
+    }
+
+    /// Returns the completion times of the unbonding entries with a validator that are still open.
+    pub fn open_unbonding_entries(
+        &self,
+        storage: &dyn Storage,
+        validator: &str,
+        now: u64,
+    ) -> StdResult<Vec<u64>> {
+        Ok(self
+            .unbonding_entries
+            .may_load(storage, validator)?
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|completion_time| *completion_time > now)
+            .collect())
+    }
+
+    /// Records a new unbonding entry with a validator, dropping the ones that completed.
+    pub fn add_unbonding_entry(
+        &self,
+        storage: &mut dyn Storage,
+        validator: &str,
+        now: u64,
+        completion_time: u64,
+    ) -> StdResult<()> {
+        let mut entries = self.open_unbonding_entries(storage, validator, now)?;
+        entries.push(completion_time);
+        self.unbonding_entries.save(storage, validator, &entries)
+    }
+
+    /// Returns the validators that have no free unbonding slot left at the given time.
+    pub fn full_validators(&self, storage: &dyn Storage, now: u64) -> StdResult<HashSet<String>> {
+        let mut full = HashSet::new();
+        for item in self.unbonding_entries.range(storage, None, None, Order::Ascending) {
+            let (validator, entries) = item?;
+            let open = entries.iter().filter(|completion_time| **completion_time > now).count();
+            if open >= MAX_UNBONDING_ENTRIES as usize {
+                full.insert(validator);
+            }
+        }
+        Ok(full)
+    }
+
+    /// Updates the number of entries in `previous_batches` and `unbond_requests`, which is kept
+    /// so that the state query does not need to iterate them.
+    pub fn update_storage_metrics(
+        &self,
+        storage: &mut dyn Storage,
+        update: impl FnOnce(&mut StorageMetrics),
+    ) -> StdResult<()> {
+        let mut metrics = self.storage_metrics.may_load(storage)?.unwrap_or_default();
+        update(&mut metrics);
+        self.storage_metrics.save(storage, &metrics)
+    }
+
+    /// Counts the entries in `previous_batches` and `unbond_requests`. This iterates all of them
+    /// and is only used to initialize the metrics when migrating.
+    pub fn count_storage_metrics(&self, storage: &dyn Storage) -> StdResult<StorageMetrics> {
+        Ok(StorageMetrics {
+            previous_batches: self
+                .previous_batches
+                .keys(storage, None, None, Order::Ascending)
+                .count() as u64,
+            unbond_requests: self
+                .unbond_requests
+                .keys(storage, None, None, Order::Ascending)
+                .count() as u64,
+        })
+    }
+
+    pub fn tracks_history(&self, storage: &dyn Storage, user: &Addr) -> StdResult<bool> {
+        Ok(self.history_users.may_load(storage, user)?.unwrap_or(false))
+    }
+
+    /// Appends the entry to the history of the user, assigning it the next id.
+    pub fn add_user_history(
+        &self,
+        storage: &mut dyn Storage,
+        user: &Addr,
+        mut entry: UserHistoryItem,
+    ) -> StdResult<()> {
+        let last_id = self
+            .user_history
+            .prefix(user)
+            .keys(storage, None, None, Order::Descending)
+            .next()
+            .transpose()?;
+        entry.id = last_id.map_or(0, |id| id + 1);
+        self.user_history.save(storage, (user, entry.id), &entry)
+    }
+
     /// active validators returns the list of delegation goal, or if not available (uniform mode) uses the validators list.
     pub fn _active_validators(&self, storage: &dyn Storage) -> Vec<String> {
         self.delegation_goal
@@ -3,20 +3,26 @@ use std::collections::HashMap;
 use cosmwasm_std::testing::{BankQuerier, StakingQuerier, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
     from_binary, from_slice, Addr, Coin, Empty, FullDelegation, Querier, QuerierResult,
-    QueryRequest, SystemError, WasmQuery,
+    QueryRequest, SystemError, Validator, WasmQuery,
 };
 use cw20::Cw20QueryMsg;
+use eris::prop_gauges::QueryMsg as PropGaugesQueryMsg;
+use eris::voting_escrow::QueryMsg as VotingEscrowQueryMsg;
 
 use crate::types::Delegation;
 
 use super::cw20_querier::Cw20Querier;
 use super::helpers::err_unsupported_query;
+use super::prop_gauges_querier::PropGaugesQuerier;
 
 #[derive(Default)]
 pub(super) struct CustomQuerier {
     pub cw20_querier: Cw20Querier,
+    pub prop_gauges_querier: PropGaugesQuerier,
     pub bank_querier: BankQuerier,
     pub staking_querier: StakingQuerier,
+    pub staking_validators: Vec<Validator>,
+    pub staking_delegations: Vec<FullDelegation>,
 }
 
 impl Querier for CustomQuerier {
@@ -59,18 +65,28 @@ impl CustomQuerier {
     }
 
     pub fn set_staking_delegations(&mut self, delegations: &[Delegation]) {
-        let fds = delegations
-            .iter()
-            .map(|d| FullDelegation {
-                delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
-                validator: d.validator.clone(),
-                amount: Coin::new(d.amount, "uluna"),
-                can_redelegate: Coin::new(0, "uluna"),
-                accumulated_rewards: vec![],
-            })
-            .collect::<Vec<_>>();
-
-        self.staking_querier = StakingQuerier::new("uluna", &[], &fds);
+        self.set_delegations_of(MOCK_CONTRACT_ADDR, delegations);
+    }
+
+    pub fn set_delegations_of(&mut self, delegator: &str, delegations: &[Delegation]) {
+        self.staking_delegations.retain(|fd| fd.delegator != delegator);
+        self.staking_delegations.extend(delegations.iter().map(|d| FullDelegation {
+            delegator: Addr::unchecked(delegator),
+            validator: d.validator.clone(),
+            amount: Coin::new(d.amount, "uluna"),
+            can_redelegate: Coin::new(0, "uluna"),
+            accumulated_rewards: vec![],
+        }));
+
+        self.staking_querier =
+            StakingQuerier::new("uluna", &self.staking_validators, &self.staking_delegations);
+    }
+
+    pub fn set_staking_validators(&mut self, validators: &[Validator]) {
+        self.staking_validators = validators.to_vec();
+
+        self.staking_querier =
+            StakingQuerier::new("uluna", &self.staking_validators, &self.staking_delegations);
     }
 
     pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
@@ -79,10 +95,22 @@ impl CustomQuerier {
                 contract_addr,
                 msg,
             }) => {
+                if let Some(config) = &self.prop_gauges_querier.config {
+                    if *contract_addr == config.escrow_addr {
+                        if let Ok(query) = from_binary::<VotingEscrowQueryMsg>(msg) {
+                            return self.prop_gauges_querier.handle_escrow_query(query);
+                        }
+                    }
+                }
+
                 if let Ok(query) = from_binary::<Cw20QueryMsg>(msg) {
                     return self.cw20_querier.handle_query(contract_addr, query);
                 }
 
+                if let Ok(query) = from_binary::<PropGaugesQueryMsg>(msg) {
+                    return self.prop_gauges_querier.handle_query(query);
+                }
+
                 err_unsupported_query(msg)
             },
 
@@ -1,5 +1,6 @@
 mod custom_querier;
 mod cw20_querier;
 mod helpers;
+mod prop_gauges_querier;
 mod tests_gauges;
 mod tests_uniform;
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+
+use cosmwasm_std::{to_binary, QuerierResult, SystemError, Uint128};
+use eris::prop_gauges::{ConfigResponse, PropDetailResponse, PropInfo, QueryMsg};
+use eris::voting_escrow::{QueryMsg as VotingEscrowQueryMsg, VotingPowerResponse};
+
+use super::helpers::err_unsupported_query;
+
+#[derive(Default)]
+pub(super) struct PropGaugesQuerier {
+    /// Config of the prop gauges contract, the escrow_addr is used for voting power queries
+    pub config: Option<ConfigResponse>,
+    /// Mapping proposal id to the state of its voting in the prop gauges
+    pub props: HashMap<u64, PropInfo>,
+    /// Total voting power in the voting escrow, for all periods
+    pub total_vp: Uint128,
+}
+
+impl PropGaugesQuerier {
+    pub fn handle_query(&self, query: QueryMsg) -> QuerierResult {
+        match &query {
+            QueryMsg::Config {} => {
+                let config = self
+                    .config
+                    .as_ref()
+                    .ok_or_else(|| SystemError::InvalidRequest {
+                        error: "[mock] prop gauges config not set".to_string(),
+                        request: Default::default(),
+                    })
+                    .unwrap();
+
+                Ok(to_binary(config).into()).into()
+            },
+
+            QueryMsg::PropDetail {
+                proposal_id,
+                ..
+            } => {
+                let prop = self
+                    .props
+                    .get(proposal_id)
+                    .ok_or_else(|| SystemError::InvalidRequest {
+                        error: format!("[mock] prop not set for id `{}`", proposal_id),
+                        request: Default::default(),
+                    })
+                    .unwrap();
+
+                Ok(to_binary(&PropDetailResponse {
+                    prop: prop.clone(),
+                    user: None,
+                })
+                .into())
+                .into()
+            },
+
+            other_query => err_unsupported_query(other_query),
+        }
+    }
+
+    pub fn handle_escrow_query(&self, query: VotingEscrowQueryMsg) -> QuerierResult {
+        match &query {
+            VotingEscrowQueryMsg::TotalVampAtPeriod {
+                ..
+            } => Ok(to_binary(&VotingPowerResponse {
+                vamp: self.total_vp,
+            })
+            .into())
+            .into(),
+
+            other_query => err_unsupported_query(other_query),
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::str::FromStr;
 use std::vec;
 
@@ -11,9 +12,10 @@ use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
 use eris::DecimalCheckedOps;
 
 use eris::hub::{
-    Batch, CallbackMsg, ConfigResponse, ExecuteMsg, FeeConfig, InstantiateMsg, PendingBatch,
-    QueryMsg, ReceiveMsg, StateResponse, UnbondRequest, UnbondRequestsByBatchResponseItem,
-    UnbondRequestsByUserResponseItem, UnbondRequestsByUserResponseItemDetails,
+    Batch, CallbackMsg, ConfigResponse, ExecuteMsg, FeeConfig, InstantiateMsg, PauseFlags,
+    PendingBatch, QueryMsg, ReceiveMsg, StateResponse, StorageMetrics, UnbondRequest,
+    UnbondRequestsByBatchResponseItem, UnbondRequestsByUserResponseItem,
+    UnbondRequestsByUserResponseItemDetails,
 };
 use itertools::Itertools;
 
@@ -64,6 +66,10 @@ fn setup_test() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
                 validator_count: 5,
             }),
             vote_operator: None,
+            token_factory_subdenom: None,
+            denom: None,
+            config_timelock: None,
+            stake_token_marketing: None,
         },
     )
     .unwrap();
@@ -148,7 +154,19 @@ fn proper_instantiation() {
                 min_delegation_bps: 100,
                 max_delegation_bps: 2500,
                 validator_count: 5,
-            }
+            },
+            validator_checks: None,
+            guardian: None,
+            pause_flags: PauseFlags::default(),
+            fee_receivers: vec![],
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            denom: CONTRACT_DENOM.to_string(),
+            config_timelock: 2073600,
+            pending_config_change: None,
         }
     );
 
@@ -157,12 +175,18 @@ fn proper_instantiation() {
         res,
         StateResponse {
             total_ustake: Uint128::zero(),
-            total_uluna: Uint128::zero(),
+            total_utoken: Uint128::zero(),
             exchange_rate: Decimal::one(),
             unlocked_coins: vec![],
             unbonding: Uint128::zero(),
             available: Uint128::zero(),
-            tvl_uluna: Uint128::zero(),
+            tvl_utoken: Uint128::zero(),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 0,
+            },
         },
     );
 
@@ -196,7 +220,10 @@ fn bonding() {
     .unwrap();
 
     assert_eq!(res.messages.len(), 3);
-    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("alice", 1000000).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("alice", 1000000).to_cosmos_msg(CONTRACT_DENOM))
+    );
     assert_eq!(
         res.messages[1],
         SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -235,7 +262,10 @@ fn bonding() {
     .unwrap();
 
     assert_eq!(res.messages.len(), 3);
-    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg(CONTRACT_DENOM))
+    );
     assert_eq!(
         res.messages[1],
         SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -264,12 +294,18 @@ fn bonding() {
         res,
         StateResponse {
             total_ustake: Uint128::new(1012043),
-            total_uluna: Uint128::new(1037345),
+            total_utoken: Uint128::new(1037345),
             exchange_rate: Decimal::from_ratio(1037345u128, 1012043u128),
             unlocked_coins: vec![],
             unbonding: Uint128::zero(),
             available: Uint128::new(12567),
-            tvl_uluna: Uint128::new(1037345 + 12567),
+            tvl_utoken: Uint128::new(1037345 + 12567),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 0,
+            },
         }
     );
 }
@@ -292,7 +328,10 @@ fn donating() {
     .unwrap();
 
     assert_eq!(res.messages.len(), 3);
-    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("alice", 1000000).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("alice", 1000000).to_cosmos_msg(CONTRACT_DENOM))
+    );
     assert_eq!(
         res.messages[1],
         SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -322,12 +361,18 @@ fn donating() {
         res,
         StateResponse {
             total_ustake: Uint128::new(1000000),
-            total_uluna: Uint128::new(1025000),
+            total_utoken: Uint128::new(1025000),
             exchange_rate: Decimal::from_ratio(1025000u128, 1000000u128),
             unlocked_coins: vec![],
             unbonding: Uint128::zero(),
             available: Uint128::new(100),
-            tvl_uluna: Uint128::new(1025100),
+            tvl_utoken: Uint128::new(1025100),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 0,
+            },
         }
     );
 
@@ -348,10 +393,23 @@ fn donating() {
         mock_info("owner", &[]),
         ExecuteMsg::UpdateConfig {
             protocol_fee_contract: None,
-            protocol_reward_fee: None,
             allow_donations: Some(true),
-            delegation_strategy: None,
             vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
             epoch_period: None,
             unbond_period: None,
         },
@@ -368,7 +426,10 @@ fn donating() {
     .unwrap();
 
     assert_eq!(res.messages.len(), 2);
-    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg(CONTRACT_DENOM))
+    );
     assert_eq!(res.messages[1], check_received_coin(100));
 
     deps.querier.set_bank_balances(&[coin(100, CONTRACT_DENOM)]);
@@ -385,12 +446,18 @@ fn donating() {
         res,
         StateResponse {
             total_ustake: Uint128::new(1000000),
-            total_uluna: Uint128::new(1037345),
+            total_utoken: Uint128::new(1037345),
             exchange_rate: Decimal::from_ratio(1037345u128, 1000000u128),
             unlocked_coins: vec![],
             unbonding: Uint128::zero(),
             available: Uint128::new(100),
-            tvl_uluna: Uint128::new(1037345 + 100),
+            tvl_utoken: Uint128::new(1037345 + 100),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 0,
+            },
         }
     );
 }
@@ -484,6 +551,7 @@ fn reinvesting() {
         Delegation::new("bob", 333333),
         Delegation::new("charlie", 333333),
     ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
 
     // After the swaps, `unlocked_coins` should contain only utoken and unknown denoms
     state
@@ -518,12 +586,12 @@ fn reinvesting() {
 
     assert_eq!(
         res.messages[0],
-        SubMsg::new(Delegation::new("bob", delegated.u128()).to_cosmos_msg())
+        SubMsg::new(Delegation::new("bob", delegated.u128()).to_cosmos_msg(CONTRACT_DENOM))
     );
 
     assert_eq!(
         res.messages[1],
-        SubMsg::new(SendFee::new(Addr::unchecked("fee"), fee.u128()).to_cosmos_msg())
+        SubMsg::new(SendFee::new(Addr::unchecked("fee"), fee.u128()).to_cosmos_msg(CONTRACT_DENOM))
     );
 
     // Storage should have been updated
@@ -685,6 +753,17 @@ fn submitting_batch() {
             .unwrap();
     }
 
+    state
+        .storage_metrics
+        .save(
+            deps.as_mut().storage,
+            &StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 2,
+            },
+        )
+        .unwrap();
+
     state
         .pending_batch
         .save(
@@ -717,9 +796,18 @@ fn submitting_batch() {
     .unwrap();
 
     assert_eq!(res.messages.len(), 5);
-    assert_eq!(res.messages[0], SubMsg::new(Undelegation::new("alice", 31732).to_cosmos_msg()));
-    assert_eq!(res.messages[1], SubMsg::new(Undelegation::new("bob", 31733).to_cosmos_msg()));
-    assert_eq!(res.messages[2], SubMsg::new(Undelegation::new("charlie", 31732).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Undelegation::new("alice", 31732).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(Undelegation::new("bob", 31733).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(Undelegation::new("charlie", 31732).to_cosmos_msg(CONTRACT_DENOM))
+    );
     assert_eq!(
         res.messages[3],
         SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -752,7 +840,7 @@ fn submitting_batch() {
             id: 1,
             reconciled: false,
             total_shares: Uint128::new(92876),
-            uluna_unclaimed: Uint128::new(95197),
+            utoken_unclaimed: Uint128::new(95197),
             est_unbond_end_time: 2083601 // 269,201 + 1,814,400
         }
     );
@@ -762,12 +850,18 @@ fn submitting_batch() {
         res,
         StateResponse {
             total_ustake: Uint128::from(1012043u128),
-            total_uluna: Uint128::from(1037345u128),
+            total_utoken: Uint128::from(1037345u128),
             exchange_rate: Decimal::from_ratio(1037345u128, 1012043u128),
             unlocked_coins: vec![],
             unbonding: Uint128::from(95197u128),
             available: Uint128::zero(),
-            tvl_uluna: Uint128::from(95197u128 + 1037345u128),
+            tvl_utoken: Uint128::from(95197u128 + 1037345u128),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 1,
+                unbond_requests: 2,
+            },
         },
     );
 }
@@ -782,29 +876,29 @@ fn reconciling() {
             id: 1,
             reconciled: true,
             total_shares: Uint128::new(92876),
-            uluna_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
+            utoken_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
             est_unbond_end_time: 10000,
         },
         Batch {
             id: 2,
             reconciled: false,
             total_shares: Uint128::new(1345),
-            uluna_unclaimed: Uint128::new(1385), // 1.030 Token per Stake
+            utoken_unclaimed: Uint128::new(1385), // 1.030 Token per Stake
             est_unbond_end_time: 20000,
         },
         Batch {
             id: 3,
             reconciled: false,
             total_shares: Uint128::new(1456),
-            uluna_unclaimed: Uint128::new(1506), // 1.035 Token per Stake
+            utoken_unclaimed: Uint128::new(1506), // 1.035 Token per Stake
             est_unbond_end_time: 30000,
         },
         Batch {
             id: 4,
             reconciled: false,
             total_shares: Uint128::new(1567),
-            uluna_unclaimed: Uint128::new(1629), // 1.040 Token per Stake
-            est_unbond_end_time: 40000,          // not yet finished unbonding, ignored
+            utoken_unclaimed: Uint128::new(1629), // 1.040 Token per Stake
+            est_unbond_end_time: 40000,           // not yet finished unbonding, ignored
         },
     ];
 
@@ -863,7 +957,7 @@ fn reconciling() {
             id: 2,
             reconciled: true,
             total_shares: Uint128::new(1345),
-            uluna_unclaimed: Uint128::new(1112), // 1385 - 273
+            utoken_unclaimed: Uint128::new(1112), // 1385 - 273
             est_unbond_end_time: 20000,
         }
     );
@@ -875,7 +969,7 @@ fn reconciling() {
             id: 3,
             reconciled: true,
             total_shares: Uint128::new(1456),
-            uluna_unclaimed: Uint128::new(1233), // 1506 - 273
+            utoken_unclaimed: Uint128::new(1233), // 1506 - 273
             est_unbond_end_time: 30000,
         }
     );
@@ -898,28 +992,28 @@ fn reconciling_even_when_everything_ok() {
             id: 1,
             reconciled: true,
             total_shares: Uint128::new(100000),
-            uluna_unclaimed: Uint128::new(100000),
+            utoken_unclaimed: Uint128::new(100000),
             est_unbond_end_time: 10000,
         },
         Batch {
             id: 2,
             reconciled: false,
             total_shares: Uint128::new(1000),
-            uluna_unclaimed: Uint128::new(1000),
+            utoken_unclaimed: Uint128::new(1000),
             est_unbond_end_time: 20000,
         },
         Batch {
             id: 3,
             reconciled: false,
             total_shares: Uint128::new(1500),
-            uluna_unclaimed: Uint128::new(1500),
+            utoken_unclaimed: Uint128::new(1500),
             est_unbond_end_time: 30000,
         },
         Batch {
             id: 4,
             reconciled: false,
             total_shares: Uint128::new(1500),
-            uluna_unclaimed: Uint128::new(1500),
+            utoken_unclaimed: Uint128::new(1500),
             est_unbond_end_time: 40000, // not yet finished unbonding, ignored
         },
     ];
@@ -953,7 +1047,7 @@ fn reconciling_even_when_everything_ok() {
             id: 2,
             reconciled: true,
             total_shares: Uint128::new(1000),
-            uluna_unclaimed: Uint128::new(1000),
+            utoken_unclaimed: Uint128::new(1000),
             est_unbond_end_time: 20000,
         }
     );
@@ -965,7 +1059,7 @@ fn reconciling_even_when_everything_ok() {
             id: 3,
             reconciled: true,
             total_shares: Uint128::new(1500),
-            uluna_unclaimed: Uint128::new(1500),
+            utoken_unclaimed: Uint128::new(1500),
             est_unbond_end_time: 30000,
         }
     );
@@ -1031,28 +1125,28 @@ fn withdrawing_unbonded() {
             id: 1,
             reconciled: true,
             total_shares: Uint128::new(92876),
-            uluna_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
+            utoken_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
             est_unbond_end_time: 10000,
         },
         Batch {
             id: 2,
             reconciled: true,
             total_shares: Uint128::new(34567),
-            uluna_unclaimed: Uint128::new(35604), // 1.030 Token per Stake
+            utoken_unclaimed: Uint128::new(35604), // 1.030 Token per Stake
             est_unbond_end_time: 20000,
         },
         Batch {
             id: 3,
             reconciled: false, // finished unbonding, but not reconciled; ignored
             total_shares: Uint128::new(45678),
-            uluna_unclaimed: Uint128::new(47276), // 1.035 Token per Stake
+            utoken_unclaimed: Uint128::new(47276), // 1.035 Token per Stake
             est_unbond_end_time: 20000,
         },
         Batch {
             id: 4,
             reconciled: true,
             total_shares: Uint128::new(56789),
-            uluna_unclaimed: Uint128::new(59060), // 1.040 Token per Stake
+            utoken_unclaimed: Uint128::new(59060), // 1.040 Token per Stake
             est_unbond_end_time: 30000, // reconciled, but not yet finished unbonding; ignored
         },
     ];
@@ -1127,7 +1221,7 @@ fn withdrawing_unbonded() {
             id: 1,
             reconciled: true,
             total_shares: Uint128::new(69420),
-            uluna_unclaimed: Uint128::new(71155),
+            utoken_unclaimed: Uint128::new(71155),
             est_unbond_end_time: 10000,
         }
     );
@@ -1427,19 +1521,19 @@ fn update_fee() {
         }
     );
 
+    let propose_fee = |protocol_reward_fee: Decimal| ExecuteMsg::ProposeConfigChange {
+        protocol_reward_fee: Some(protocol_reward_fee),
+        delegation_strategy: None,
+        epoch_period: None,
+        unbond_period: None,
+        config_timelock: None,
+    };
+
     let err = execute(
         deps.as_mut(),
         mock_env(),
         mock_info("jake", &[]),
-        ExecuteMsg::UpdateConfig {
-            protocol_fee_contract: None,
-            protocol_reward_fee: Some(Decimal::from_ratio(11u128, 100u128)),
-            delegation_strategy: None,
-            allow_donations: None,
-            vote_operator: None,
-            epoch_period: None,
-            unbond_period: None,
-        },
+        propose_fee(Decimal::from_ratio(10u128, 100u128)),
     )
     .unwrap_err();
     assert_eq!(err, ContractError::Unauthorized {});
@@ -1448,15 +1542,7 @@ fn update_fee() {
         deps.as_mut(),
         mock_env(),
         mock_info("owner", &[]),
-        ExecuteMsg::UpdateConfig {
-            protocol_fee_contract: None,
-            protocol_reward_fee: Some(Decimal::from_ratio(11u128, 100u128)),
-            delegation_strategy: None,
-            allow_donations: None,
-            vote_operator: None,
-            epoch_period: None,
-            unbond_period: None,
-        },
+        propose_fee(Decimal::from_ratio(11u128, 100u128)),
     )
     .unwrap_err();
     assert_eq!(err, ContractError::ProtocolRewardFeeTooHigh {});
@@ -1467,10 +1553,23 @@ fn update_fee() {
         mock_info("owner", &[]),
         ExecuteMsg::UpdateConfig {
             protocol_fee_contract: Some("fee-new".to_string()),
-            protocol_reward_fee: Some(Decimal::from_ratio(10u128, 100u128)),
-            delegation_strategy: None,
             allow_donations: None,
             vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
             epoch_period: None,
             unbond_period: None,
         },
@@ -1479,6 +1578,41 @@ fn update_fee() {
 
     assert_eq!(res.messages.len(), 0);
 
+    // the fee only changes after the timelock of one epoch and unbonding period
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("owner", &[]),
+        propose_fee(Decimal::from_ratio(10u128, 100u128)),
+    )
+    .unwrap();
+
+    let config = state.fee_config.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config,
+        FeeConfig {
+            protocol_fee_contract: Addr::unchecked("fee-new"),
+            protocol_reward_fee: Decimal::from_ratio(1u128, 100u128)
+        }
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(2083599),
+        mock_info("owner", &[]),
+        ExecuteMsg::ApplyConfigChange {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ConfigChangeTimelocked(2083600));
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(2083600),
+        mock_info("owner", &[]),
+        ExecuteMsg::ApplyConfigChange {},
+    )
+    .unwrap();
+
     let config = state.fee_config.load(deps.as_ref().storage).unwrap();
     assert_eq!(
         config,
@@ -1502,28 +1636,28 @@ fn querying_previous_batches() {
             id: 1,
             reconciled: false,
             total_shares: Uint128::new(123),
-            uluna_unclaimed: Uint128::new(678),
+            utoken_unclaimed: Uint128::new(678),
             est_unbond_end_time: 10000,
         },
         Batch {
             id: 2,
             reconciled: true,
             total_shares: Uint128::new(234),
-            uluna_unclaimed: Uint128::new(789),
+            utoken_unclaimed: Uint128::new(789),
             est_unbond_end_time: 15000,
         },
         Batch {
             id: 3,
             reconciled: false,
             total_shares: Uint128::new(345),
-            uluna_unclaimed: Uint128::new(890),
+            utoken_unclaimed: Uint128::new(890),
             est_unbond_end_time: 20000,
         },
         Batch {
             id: 4,
             reconciled: true,
             total_shares: Uint128::new(456),
-            uluna_unclaimed: Uint128::new(999),
+            utoken_unclaimed: Uint128::new(999),
             est_unbond_end_time: 25000,
         },
     ];
@@ -1731,14 +1865,14 @@ fn querying_unbond_requests_details() {
             id: 1,
             reconciled: false,
             total_shares: Uint128::new(123),
-            uluna_unclaimed: Uint128::new(678),
+            utoken_unclaimed: Uint128::new(678),
             est_unbond_end_time: 10000,
         },
         Batch {
             id: 2,
             reconciled: false,
             total_shares: Uint128::new(234),
-            uluna_unclaimed: Uint128::new(789),
+            utoken_unclaimed: Uint128::new(789),
             est_unbond_end_time: 15000,
         },
     ];
@@ -1819,6 +1953,7 @@ fn computing_undelegations() -> StdResult<()> {
         Uint128::new(451),
         &current_delegations,
         current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+        &HashSet::new(),
     )
     .unwrap();
     let expected = vec![
@@ -1840,7 +1975,7 @@ fn computing_redelegations_for_removal() -> StdResult<()> {
         Delegation::new("dave", 10000),
     ];
     // Suppose Dave will be removed
-    // uluna_per_validator = (13000 + 12000 + 11000 + 10000) / 3 = 15333
+    // utoken_per_validator = (13000 + 12000 + 11000 + 10000) / 3 = 15333
     // remainder = 1
     // to Alice:   15333 + 1 - 13000 = 2334
     // to Bob:     15333 + 0 - 12000 = 3333
@@ -1856,9 +1991,10 @@ fn computing_redelegations_for_removal() -> StdResult<()> {
             deps.as_ref().storage,
             &current_delegations[3],
             &current_delegations[..3],
-            current_delegations[..3].iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations[..3].iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        expected,
+        (expected, vec![]),
     );
     Ok(())
 }
@@ -1874,7 +2010,7 @@ fn computing_redelegations_for_rebalancing() -> StdResult<()> {
         Delegation::new("dave", 40471),
         Delegation::new("evan", 2345),
     ];
-    // uluna_per_validator = (69420 + 88888 + 1234 + 40471 + 2345) / 4 = 40471
+    // utoken_per_validator = (69420 + 88888 + 1234 + 40471 + 2345) / 4 = 40471
     // remainer = 3
     // src_delegations:
     //  - alice:   69420 - (40471 + 3) = 28946
@@ -1908,9 +2044,10 @@ fn computing_redelegations_for_rebalancing() -> StdResult<()> {
             &state,
             deps.as_ref().storage,
             &current_delegations,
-            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        expected,
+        (expected, vec![]),
     );
     Ok(())
 }
@@ -1942,9 +2079,10 @@ fn computing_redelegations_for_rebalancing_complex() -> StdResult<()> {
             &state,
             deps.as_ref().storage,
             &current_delegations,
-            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        vec![],
+        (vec![], vec![]),
     );
     // ratio is bad
     let current_delegations = vec![
@@ -1958,9 +2096,10 @@ fn computing_redelegations_for_rebalancing_complex() -> StdResult<()> {
             &state,
             deps.as_ref().storage,
             &current_delegations,
-            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        vec![Redelegation::new("unlisted", "alice", 25000)],
+        (vec![Redelegation::new("unlisted", "alice", 25000)], vec![]),
     );
     // ratio is bad
     let current_delegations = vec![
@@ -1974,12 +2113,16 @@ fn computing_redelegations_for_rebalancing_complex() -> StdResult<()> {
             &state,
             deps.as_ref().storage,
             &current_delegations,
-            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        vec![
-            Redelegation::new("unlisted", "alice", 25000),
-            Redelegation::new("unlisted", "bob", 25000)
-        ],
+        (
+            vec![
+                Redelegation::new("unlisted", "alice", 25000),
+                Redelegation::new("unlisted", "bob", 25000)
+            ],
+            vec![]
+        ),
     );
     // ratio is bad
     let current_delegations = vec![
@@ -1992,12 +2135,16 @@ fn computing_redelegations_for_rebalancing_complex() -> StdResult<()> {
             &state,
             deps.as_ref().storage,
             &current_delegations,
-            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        vec![
-            Redelegation::new("charlie", "alice", 27500),
-            Redelegation::new("charlie", "bob", 27500)
-        ],
+        (
+            vec![
+                Redelegation::new("charlie", "alice", 27500),
+                Redelegation::new("charlie", "bob", 27500)
+            ],
+            vec![]
+        ),
     );
     Ok(())
 }
@@ -1,32 +1,45 @@
+use std::collections::HashSet;
 use std::str::FromStr;
 use std::vec;
 
+use astroport::asset::{Asset, AssetInfo};
 use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    coin, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, DistributionMsg, Event, Fraction,
-    GovMsg, Order, OwnedDeps, Reply, StdError, StdResult, SubMsg, SubMsgResponse, Uint128,
-    VoteOption, WasmMsg,
+    attr, coin, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, DistributionMsg, Empty, Event,
+    Fraction, GovMsg, Order, OwnedDeps, Reply, StdError, StdResult, SubMsg, SubMsgResponse,
+    Uint128, Validator, VoteOption, WasmMsg,
 };
-use cw20::{Cw20ExecuteMsg, MinterResponse};
-use cw20_base::msg::InstantiateMsg as Cw20InstantiateMsg;
+use cw20::{Cw20ExecuteMsg, Logo, MinterResponse};
+use cw20_base::msg::{InstantiateMarketingInfo, InstantiateMsg as Cw20InstantiateMsg};
+use eris::adapters::pair::Pair;
+use eris::constants::{DAY, YEAR};
+use eris::prop_gauges::{ConfigResponse as PropGaugesConfigResponse, PropInfo};
 use eris::DecimalCheckedOps;
 
 use eris::hub::{
-    Batch, CallbackMsg, ConfigResponse, ExecuteMsg, FeeConfig, InstantiateMsg, PendingBatch,
-    QueryMsg, ReceiveMsg, StateResponse, UnbondRequest, UnbondRequestsByBatchResponseItem,
+    AutoMaintenanceConfig, Batch, CallbackMsg, ConfigResponse, DelegationStrategy,
+    DustCleanupConfig, ExchangeHistory, ExchangeRatesResponse, ExecuteMsg, FeeConfig, FeeReceiver,
+    InstantiateMsg, KeeperReward, KeeperRewardConfig, LiquidityBufferConfig, PausableOperation,
+    PauseFlags, PendingBatch, PendingConfigChange, PendingRewardsResponseItem, QueryMsg,
+    ReceiveMsg, RewardSwapRoute, SlashingEvent, SplitBondConfig, StakeTokenMarketing,
+    StateResponse, StorageMetrics, SwapRouteType, UnbondRequest, UnbondRequestsByBatchResponseItem,
     UnbondRequestsByUserResponseItem, UnbondRequestsByUserResponseItemDetails,
+    UnbondingSlotsResponse, UnbondingSlotsResponseItem, UserHistoryAction, UserHistoryItem,
+    ValidatorChecks,
 };
 use itertools::Itertools;
 use protobuf::SpecialFields;
 
 use crate::constants::CONTRACT_DENOM;
-use crate::contract::{execute, instantiate, reply};
+use crate::contract::{execute, instantiate, migrate, reply};
 use crate::error::ContractError;
 use crate::helpers::{dedupe, parse_received_fund};
 use crate::math::{
-    compute_redelegations_for_rebalancing, compute_redelegations_for_removal, compute_undelegations,
+    compute_keeper_rewards, compute_redelegations_for_rebalancing,
+    compute_redelegations_for_removal, compute_undelegations,
 };
 use crate::protos::proto::{self, MsgVoteWeighted, WeightedVoteOption};
+use crate::protos::{lsm, tokenfactory};
 use crate::state::State;
 use crate::testing::helpers::query_helper_env;
 use crate::types::{Coins, Delegation, Redelegation, SendFee, Undelegation};
@@ -60,6 +73,10 @@ fn setup_test() -> OwnedDeps<MockStorage, MockApi, CustomQuerier> {
             protocol_reward_fee: Decimal::from_ratio(1u128, 100u128),
             delegation_strategy: None,
             vote_operator: None,
+            token_factory_subdenom: None,
+            denom: None,
+            config_timelock: None,
+            stake_token_marketing: None,
         },
     )
     .unwrap();
@@ -137,7 +154,19 @@ fn proper_instantiation() {
                 protocol_fee_contract: Addr::unchecked("fee"),
                 protocol_reward_fee: Decimal::from_ratio(1u128, 100u128)
             },
-            delegation_strategy: eris::hub::DelegationStrategy::Uniform
+            delegation_strategy: eris::hub::DelegationStrategy::Uniform,
+            validator_checks: None,
+            guardian: None,
+            pause_flags: PauseFlags::default(),
+            fee_receivers: vec![],
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            denom: CONTRACT_DENOM.to_string(),
+            config_timelock: 2073600,
+            pending_config_change: None,
         }
     );
 
@@ -146,12 +175,18 @@ fn proper_instantiation() {
         res,
         StateResponse {
             total_ustake: Uint128::zero(),
-            total_uluna: Uint128::zero(),
+            total_utoken: Uint128::zero(),
             exchange_rate: Decimal::one(),
             unlocked_coins: vec![],
             unbonding: Uint128::zero(),
             available: Uint128::zero(),
-            tvl_uluna: Uint128::zero(),
+            tvl_utoken: Uint128::zero(),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 0,
+            },
         },
     );
 
@@ -166,6 +201,104 @@ fn proper_instantiation() {
     );
 }
 
+#[test]
+fn instantiating_stake_token_marketing() {
+    let mut deps = mock_dependencies();
+
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        InstantiateMsg {
+            cw20_code_id: 69420,
+            owner: "owner".to_string(),
+            name: "Stake Token".to_string(),
+            symbol: "STAKE".to_string(),
+            decimals: 6,
+            epoch_period: 259200,
+            unbond_period: 1814400,
+            validators: vec!["alice".to_string(), "bob".to_string(), "charlie".to_string()],
+            protocol_fee_contract: "fee".to_string(),
+            protocol_reward_fee: Decimal::from_ratio(1u128, 100u128),
+            delegation_strategy: None,
+            vote_operator: None,
+            token_factory_subdenom: Some("stake".to_string()),
+            denom: None,
+            config_timelock: None,
+            stake_token_marketing: Some(StakeTokenMarketing {
+                project: Some("Eris Protocol".to_string()),
+                description: Some("Liquid staked token".to_string()),
+                marketing: Some("marketing".to_string()),
+                logo_url: Some("https://example.com/logo.svg".to_string()),
+            }),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::MarketingRequiresCw20 {});
+
+    // The owner is the marketing admin if none is provided
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        InstantiateMsg {
+            cw20_code_id: 69420,
+            owner: "owner".to_string(),
+            name: "Stake Token".to_string(),
+            symbol: "STAKE".to_string(),
+            decimals: 6,
+            epoch_period: 259200,
+            unbond_period: 1814400,
+            validators: vec!["alice".to_string(), "bob".to_string(), "charlie".to_string()],
+            protocol_fee_contract: "fee".to_string(),
+            protocol_reward_fee: Decimal::from_ratio(1u128, 100u128),
+            delegation_strategy: None,
+            vote_operator: None,
+            token_factory_subdenom: None,
+            denom: None,
+            config_timelock: None,
+            stake_token_marketing: Some(StakeTokenMarketing {
+                project: Some("Eris Protocol".to_string()),
+                description: Some("Liquid staked token".to_string()),
+                marketing: None,
+                logo_url: Some("https://example.com/logo.svg".to_string()),
+            }),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::reply_on_success(
+            CosmosMsg::Wasm(WasmMsg::Instantiate {
+                admin: Some("owner".to_string()),
+                code_id: 69420,
+                msg: to_binary(&Cw20InstantiateMsg {
+                    name: "Stake Token".to_string(),
+                    symbol: "STAKE".to_string(),
+                    decimals: 6,
+                    initial_balances: vec![],
+                    mint: Some(MinterResponse {
+                        minter: MOCK_CONTRACT_ADDR.to_string(),
+                        cap: None
+                    }),
+                    marketing: Some(InstantiateMarketingInfo {
+                        project: Some("Eris Protocol".to_string()),
+                        description: Some("Liquid staked token".to_string()),
+                        marketing: Some("owner".to_string()),
+                        logo: Some(Logo::Url("https://example.com/logo.svg".to_string())),
+                    }),
+                })
+                .unwrap(),
+                funds: vec![],
+                label: "Eris Liquid Staking Token".to_string(),
+            }),
+            1
+        )
+    );
+}
+
 #[test]
 fn bonding() {
     let mut deps = setup_test();
@@ -185,7 +318,10 @@ fn bonding() {
     .unwrap();
 
     assert_eq!(res.messages.len(), 3);
-    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("alice", 1000000).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("alice", 1000000).to_cosmos_msg(CONTRACT_DENOM))
+    );
     assert_eq!(
         res.messages[1],
         SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -224,7 +360,10 @@ fn bonding() {
     .unwrap();
 
     assert_eq!(res.messages.len(), 3);
-    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg(CONTRACT_DENOM))
+    );
     assert_eq!(
         res.messages[1],
         SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -253,16 +392,277 @@ fn bonding() {
         res,
         StateResponse {
             total_ustake: Uint128::new(1012043),
-            total_uluna: Uint128::new(1037345),
+            total_utoken: Uint128::new(1037345),
             exchange_rate: Decimal::from_ratio(1037345u128, 1012043u128),
             unlocked_coins: vec![],
             unbonding: Uint128::zero(),
             available: Uint128::new(12567),
-            tvl_uluna: Uint128::new(1037345 + 12567),
+            tvl_utoken: Uint128::new(1037345 + 12567),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 0,
+            },
         }
     );
 }
 
+#[test]
+fn bonding_lsm_shares() {
+    let mut deps = setup_test();
+
+    deps.querier.set_bank_balances(&[coin(222, CONTRACT_DENOM)]);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    let mut bond_shares = |funds: &[Coin]| {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("user_1", funds),
+            ExecuteMsg::BondLsmShares {
+                receiver: Some("user_3".to_string()),
+            },
+        )
+    };
+
+    let err = bond_shares(&[coin(100, "bob/7"), coin(100, "alice/2")]).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::InvalidLsmShares("must deposit exactly one coin; received 2".into())
+    );
+
+    let err = bond_shares(&[coin(100, CONTRACT_DENOM)]).unwrap_err();
+    assert_eq!(err, ContractError::InvalidLsmShares(CONTRACT_DENOM.into()));
+
+    let err = bond_shares(&[coin(100, "bob/abc")]).unwrap_err();
+    assert_eq!(err, ContractError::InvalidLsmShares("bob/abc".into()));
+
+    let err = bond_shares(&[coin(100, "dave/1")]).unwrap_err();
+    assert_eq!(err, ContractError::ValidatorNotWhitelisted("dave".into()));
+
+    // The shares are redeemed, the stake tokens are only minted after the redemption
+    let res = bond_shares(&[coin(12345, "bob/7")]).unwrap();
+
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(
+            lsm::redeem_tokens_for_shares_msg(MOCK_CONTRACT_ADDR, &coin(12345, "bob/7")).unwrap()
+        )
+    );
+    assert_eq!(res.messages[1], check_received_coin(222));
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::Callback(CallbackMsg::MintLsmShares {
+                receiver: Addr::unchecked("user_3"),
+                validator: "bob".to_string(),
+                delegation_before: Uint128::new(341667),
+            }))
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+
+    let callback = ExecuteMsg::Callback(CallbackMsg::MintLsmShares {
+        receiver: Addr::unchecked("user_3"),
+        validator: "bob".to_string(),
+        delegation_before: Uint128::new(341667),
+    });
+
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("user_1", &[]), callback.clone()).unwrap_err();
+    assert_eq!(err, ContractError::CallbackOnlyCalledByContract {});
+
+    // Nothing was redeemed
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info(MOCK_CONTRACT_ADDR, &[]), callback.clone())
+            .unwrap_err();
+    assert_eq!(err, ContractError::InvalidLsmShares("no delegation was redeemed".into()));
+
+    // The shares were worth 12345 utoken, minted at the exchange rate before the redemption
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 354012),
+        Delegation::new("charlie", 341666),
+    ]);
+
+    let res =
+        execute(deps.as_mut(), mock_env(), mock_info(MOCK_CONTRACT_ADDR, &[]), callback).unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "user_3".to_string(),
+                amount: Uint128::new(12043)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/lsm_shares_bonded")
+            .add_attribute("receiver", "user_3")
+            .add_attribute("validator", "bob")
+            .add_attribute("utoken_bonded", "12345")
+            .add_attribute("ustake_minted", "12043")]
+    );
+}
+
+#[test]
+fn splitting_bonds() {
+    let mut deps = setup_test();
+
+    let mut split_bond = |max_delegations: u32| {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::UpdateConfig {
+                protocol_fee_contract: None,
+                allow_donations: None,
+                vote_operator: None,
+                liquidity_buffer: None,
+                remove_slashed_validators: None,
+                insert_swap_routes: None,
+                delete_swap_routes: None,
+                validator_checks: None,
+                guardian: None,
+                fee_receivers: None,
+                auto_maintenance: None,
+                keeper_reward: None,
+                prop_gauges: None,
+                dust_cleanup: None,
+                split_bond: Some(SplitBondConfig {
+                    enabled: true,
+                    max_delegations,
+                }),
+                remove_liquidity_buffer: None,
+                protocol_reward_fee: None,
+                delegation_strategy: None,
+                epoch_period: None,
+                unbond_period: None,
+            },
+        )
+    };
+
+    let err = split_bond(0).unwrap_err();
+    assert_eq!(err, ContractError::CantBeZero("max_delegations".into()));
+
+    split_bond(2).unwrap();
+
+    deps.querier.set_bank_balances(&[coin(30100, CONTRACT_DENOM)]);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 100000),
+        Delegation::new("bob", 50000),
+        Delegation::new("charlie", 20000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 170000);
+
+    // After bonding each validator should have 66666, charlie is 46666 and bob 16666 below that
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(30000, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("charlie", 22106).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(Delegation::new("bob", 7894).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "user_1".to_string(),
+                amount: Uint128::new(30000)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+    assert_eq!(res.messages[3], check_received_coin(100));
+
+    // The message cap limits the split to the validator furthest below its goal
+    let mut deps = setup_test();
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            protocol_fee_contract: None,
+            allow_donations: None,
+            vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: Some(SplitBondConfig {
+                enabled: true,
+                max_delegations: 1,
+            }),
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: None,
+            unbond_period: None,
+        },
+    )
+    .unwrap();
+
+    deps.querier.set_bank_balances(&[coin(30100, CONTRACT_DENOM)]);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 100000),
+        Delegation::new("bob", 50000),
+        Delegation::new("charlie", 20000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 170000);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(30000, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("charlie", 30000).to_cosmos_msg(CONTRACT_DENOM))
+    );
+}
+
 #[test]
 fn donating() {
     let mut deps = setup_test();
@@ -281,7 +681,10 @@ fn donating() {
     .unwrap();
 
     assert_eq!(res.messages.len(), 3);
-    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("alice", 1000000).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("alice", 1000000).to_cosmos_msg(CONTRACT_DENOM))
+    );
     assert_eq!(
         res.messages[1],
         SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
@@ -311,12 +714,18 @@ fn donating() {
         res,
         StateResponse {
             total_ustake: Uint128::new(1000000),
-            total_uluna: Uint128::new(1025000),
+            total_utoken: Uint128::new(1025000),
             exchange_rate: Decimal::from_ratio(1025000u128, 1000000u128),
             unlocked_coins: vec![],
             unbonding: Uint128::zero(),
             available: Uint128::new(100),
-            tvl_uluna: Uint128::new(1025100),
+            tvl_utoken: Uint128::new(1025100),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 0,
+            },
         }
     );
 
@@ -337,10 +746,23 @@ fn donating() {
         mock_info("owner", &[]),
         ExecuteMsg::UpdateConfig {
             protocol_fee_contract: None,
-            protocol_reward_fee: None,
             allow_donations: Some(true),
-            delegation_strategy: None,
             vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
             epoch_period: None,
             unbond_period: None,
         },
@@ -357,7 +779,10 @@ fn donating() {
     .unwrap();
 
     assert_eq!(res.messages.len(), 2);
-    assert_eq!(res.messages[0], SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg()));
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("charlie", 12345).to_cosmos_msg(CONTRACT_DENOM))
+    );
     assert_eq!(res.messages[1], check_received_coin(100));
 
     deps.querier.set_bank_balances(&[coin(100, CONTRACT_DENOM)]);
@@ -374,12 +799,18 @@ fn donating() {
         res,
         StateResponse {
             total_ustake: Uint128::new(1000000),
-            total_uluna: Uint128::new(1037345),
+            total_utoken: Uint128::new(1037345),
             exchange_rate: Decimal::from_ratio(1037345u128, 1000000u128),
             unlocked_coins: vec![],
             unbonding: Uint128::zero(),
             available: Uint128::new(100),
-            tvl_uluna: Uint128::new(1037345 + 100),
+            tvl_utoken: Uint128::new(1037345 + 100),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 0,
+            },
         }
     );
 }
@@ -473,6 +904,7 @@ fn reinvesting() {
         Delegation::new("bob", 333333),
         Delegation::new("charlie", 333333),
     ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
 
     // After the swaps, `unlocked_coins` should contain only utoken and unknown denoms
     state
@@ -507,12 +939,12 @@ fn reinvesting() {
 
     assert_eq!(
         res.messages[0],
-        SubMsg::new(Delegation::new("bob", delegated.u128()).to_cosmos_msg())
+        SubMsg::new(Delegation::new("bob", delegated.u128()).to_cosmos_msg(CONTRACT_DENOM))
     );
 
     assert_eq!(
         res.messages[1],
-        SubMsg::new(SendFee::new(Addr::unchecked("fee"), fee.u128()).to_cosmos_msg())
+        SubMsg::new(SendFee::new(Addr::unchecked("fee"), fee.u128()).to_cosmos_msg(CONTRACT_DENOM))
     );
 
     // Storage should have been updated
@@ -527,928 +959,4158 @@ fn reinvesting() {
 }
 
 #[test]
-fn queuing_unbond() {
+fn splitting_protocol_fee() {
     let mut deps = setup_test();
     let state = State::default();
 
-    // Only Stake token is accepted for unbonding requests
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334),
+        Delegation::new("bob", 333333),
+        Delegation::new("charlie", 333333),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    let update_config = |fee_receivers: Vec<FeeReceiver>| ExecuteMsg::UpdateConfig {
+        protocol_fee_contract: None,
+        allow_donations: None,
+        vote_operator: None,
+        liquidity_buffer: None,
+        remove_slashed_validators: None,
+        insert_swap_routes: None,
+        delete_swap_routes: None,
+        validator_checks: None,
+        guardian: None,
+        fee_receivers: Some(fee_receivers),
+        auto_maintenance: None,
+        keeper_reward: None,
+        prop_gauges: None,
+        dust_cleanup: None,
+        split_bond: None,
+        remove_liquidity_buffer: None,
+        protocol_reward_fee: None,
+        delegation_strategy: None,
+        epoch_period: None,
+        unbond_period: None,
+    };
+
     let err = execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("random_token", &[]),
-        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
-            sender: "hacker".to_string(),
-            amount: Uint128::new(69420),
-            msg: to_binary(&ReceiveMsg::QueueUnbond {
-                receiver: None,
-            })
-            .unwrap(),
-        }),
+        mock_info("owner", &[]),
+        update_config(vec![FeeReceiver {
+            addr: "treasury".to_string(),
+            weight: 0,
+            msg: None,
+        }]),
     )
     .unwrap_err();
+    assert_eq!(err, StdError::generic_err("weight of treasury can't be zero").into());
 
-    assert_eq!(err, ContractError::ExpectingStakeToken("random_token".into()));
-
-    // User 1 creates an unbonding request before `est_unbond_start_time` is reached. The unbond
-    // request is saved, but not the pending batch is not submitted for unbonding
-    let res = execute(
+    let fee_receivers = vec![
+        FeeReceiver {
+            addr: "treasury".to_string(),
+            weight: 2,
+            msg: None,
+        },
+        FeeReceiver {
+            addr: "vamp_pool".to_string(),
+            weight: 1,
+            msg: Some(to_binary(&"distribute").unwrap()),
+        },
+    ];
+    execute(
         deps.as_mut(),
-        mock_env_at_timestamp(12345), // est_unbond_start_time = 269200
-        mock_info(STAKE_DENOM, &[]),
-        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
-            sender: "user_1".to_string(),
-            amount: Uint128::new(23456),
-            msg: to_binary(&ReceiveMsg::QueueUnbond {
-                receiver: None,
-            })
-            .unwrap(),
-        }),
+        mock_env(),
+        mock_info("owner", &[]),
+        update_config(fee_receivers.clone()),
     )
     .unwrap();
 
-    assert_eq!(res.messages.len(), 0);
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.fee_receivers, fee_receivers);
 
-    // User 2 creates an unbonding request after `est_unbond_start_time` is reached. The unbond
-    // request is saved, and the pending is automatically submitted for unbonding
+    // 1% of 30001 = 300, split 2:1 between treasury and vAMP pool
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(30001, CONTRACT_DENOM)])
+        .unwrap();
     let res = execute(
         deps.as_mut(),
-        mock_env_at_timestamp(269201), // est_unbond_start_time = 269200
-        mock_info(STAKE_DENOM, &[]),
-        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
-            sender: "user_2".to_string(),
-            amount: Uint128::new(69420),
-            msg: to_binary(&ReceiveMsg::QueueUnbond {
-                receiver: Some("user_3".to_string()),
-            })
-            .unwrap(),
-        }),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
     )
     .unwrap();
 
-    assert_eq!(res.messages.len(), 1);
+    assert_eq!(res.messages.len(), 3);
     assert_eq!(
         res.messages[0],
-        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
-            msg: to_binary(&ExecuteMsg::SubmitBatch {}).unwrap(),
-            funds: vec![]
+        SubMsg::new(Delegation::new("bob", 29701).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "treasury".to_string(),
+            amount: vec![Coin::new(200, CONTRACT_DENOM)]
         }))
     );
+    // a failing wake-up message does not revert the harvest
+    assert_eq!(
+        res.messages[2],
+        SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "vamp_pool".to_string(),
+                msg: to_binary(&"distribute").unwrap(),
+                funds: vec![Coin::new(100, CONTRACT_DENOM)]
+            }),
+            101
+        )
+    );
 
-    // The users' unbonding requests should have been saved
-    let ubr1 = state
-        .unbond_requests
-        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
-        .unwrap();
-    let ubr2 = state
-        .unbond_requests
-        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_3")))
-        .unwrap();
-
+    // the fee is sent without the message instead
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 101,
+            result: cosmwasm_std::SubMsgResult::Err("pool is paused".to_string()),
+        },
+    )
+    .unwrap();
     assert_eq!(
-        ubr1,
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(23456)
-        }
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "vamp_pool".to_string(),
+            amount: vec![Coin::new(100, CONTRACT_DENOM)]
+        }))]
     );
     assert_eq!(
-        ubr2,
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_3"),
-            shares: Uint128::new(69420)
-        }
+        res.events,
+        vec![Event::new("erishub/fee_wake_up_failed")
+            .add_attribute("receiver", "vamp_pool")
+            .add_attribute("error", "pool is paused")]
     );
 
-    // Pending batch should have been updated
-    let pending_batch = state.pending_batch.load(deps.as_ref().storage).unwrap();
+    // An empty list sends the whole fee to the protocol_fee_contract again
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update_config(vec![])).unwrap();
+
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(30001, CONTRACT_DENOM)])
+        .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
     assert_eq!(
-        pending_batch,
-        PendingBatch {
-            id: 1,
-            ustake_to_burn: Uint128::new(92876), // 23,456 + 69,420
-            est_unbond_start_time: 269200
-        }
+        res.messages[1],
+        SubMsg::new(SendFee::new(Addr::unchecked("fee"), 300).to_cosmos_msg(CONTRACT_DENOM))
     );
 }
 
 #[test]
-fn submitting_batch() {
+fn automating_maintenance() {
     let mut deps = setup_test();
     let state = State::default();
 
-    // utoken bonded: 1,037,345
-    // ustake supply: 1,012,043
-    // utoken per ustake: 1.025
+    deps.querier.set_bank_balances(&[coin(1000100, CONTRACT_DENOM)]);
     deps.querier.set_staking_delegations(&[
-        Delegation::new("alice", 345782),
-        Delegation::new("bob", 345782),
-        Delegation::new("charlie", 345781),
+        Delegation::new("alice", 333334),
+        Delegation::new("bob", 333333),
+        Delegation::new("charlie", 333333),
     ]);
-    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1012043);
-
-    // We continue from the contract state at the end of the last test
-    let unbond_requests = vec![
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(23456),
-        },
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_3"),
-            shares: Uint128::new(69420),
-        },
-    ];
-
-    for unbond_request in &unbond_requests {
-        state
-            .unbond_requests
-            .save(
-                deps.as_mut().storage,
-                (unbond_request.id, &Addr::unchecked(unbond_request.user.clone())),
-                unbond_request,
-            )
-            .unwrap();
-    }
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
 
-    state
-        .pending_batch
-        .save(
-            deps.as_mut().storage,
-            &PendingBatch {
-                id: 1,
-                ustake_to_burn: Uint128::new(92876), // 23,456 + 69,420
-                est_unbond_start_time: 269200,
+    let bond = |deps: &mut OwnedDeps<MockStorage, MockApi, CustomQuerier>, time: u64| {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(time),
+            mock_info("user_1", &[Coin::new(1000, CONTRACT_DENOM)]),
+            ExecuteMsg::Bond {
+                receiver: None,
             },
         )
+        .unwrap()
+    };
+
+    // Not configured, bonding does not carry out any maintenance
+    let res = bond(&mut deps, 10000);
+    assert_eq!(res.messages.len(), 3);
+
+    let config = AutoMaintenanceConfig {
+        enabled: true,
+        gas_limit: 0,
+        harvest_interval: 86400,
+        keeper_tip: Uint128::new(50),
+    };
+    let update_config = |auto_maintenance: AutoMaintenanceConfig| ExecuteMsg::UpdateConfig {
+        protocol_fee_contract: None,
+        allow_donations: None,
+        vote_operator: None,
+        liquidity_buffer: None,
+        remove_slashed_validators: None,
+        insert_swap_routes: None,
+        delete_swap_routes: None,
+        validator_checks: None,
+        guardian: None,
+        fee_receivers: None,
+        auto_maintenance: Some(auto_maintenance),
+        keeper_reward: None,
+        prop_gauges: None,
+        dust_cleanup: None,
+        split_bond: None,
+        remove_liquidity_buffer: None,
+        protocol_reward_fee: None,
+        delegation_strategy: None,
+        epoch_period: None,
+        unbond_period: None,
+    };
+
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update_config(config.clone()))
+            .unwrap_err();
+    assert_eq!(err, ContractError::CantBeZero("gas_limit".into()));
+
+    let config = AutoMaintenanceConfig {
+        gas_limit: 1000000,
+        ..config
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update_config(config.clone()))
         .unwrap();
 
-    // Anyone can invoke `submit_batch`. Here we continue from the previous test and assume it is
-    // invoked automatically as user 2 submits the unbonding request
-    //
-    // ustake to burn: 23,456 + 69,420 = 92,876
-    // utoken to unbond: 1,037,345 * 92,876 / 1,012,043 = 95,197
-    //
-    // Target: (1,037,345 - 95,197) / 3 = 314,049
-    // Remainer: 1
-    // Alice:   345,782 - (314,049 + 1) = 31,732
-    // Bob:     345,782 - (314,049 + 0) = 31,733
-    // Charlie: 345,781 - (314,049 + 0) = 31,732
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.auto_maintenance, Some(config));
+
+    // Nothing has been harvested yet, so the maintenance is appended within the gas limit
+    let res = bond(&mut deps, 10000);
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(
+        res.messages[3],
+        SubMsg::reply_on_error(
+            CallbackMsg::AutoMaintenance {
+                keeper: Addr::unchecked("user_1")
+            }
+            .into_cosmos_msg(&Addr::unchecked(MOCK_CONTRACT_ADDR))
+            .unwrap(),
+            2
+        )
+        .with_gas_limit(1000000)
+    );
+
+    // Only the contract itself can run the maintenance
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::Callback(CallbackMsg::AutoMaintenance {
+            keeper: Addr::unchecked("user_1"),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::CallbackOnlyCalledByContract {});
+
     let res = execute(
         deps.as_mut(),
-        mock_env_at_timestamp(269201),
+        mock_env_at_timestamp(10000),
         mock_info(MOCK_CONTRACT_ADDR, &[]),
-        ExecuteMsg::SubmitBatch {},
+        ExecuteMsg::Callback(CallbackMsg::AutoMaintenance {
+            keeper: Addr::unchecked("user_1"),
+        }),
     )
     .unwrap();
-
-    assert_eq!(res.messages.len(), 5);
-    assert_eq!(res.messages[0], SubMsg::new(Undelegation::new("alice", 31732).to_cosmos_msg()));
-    assert_eq!(res.messages[1], SubMsg::new(Undelegation::new("bob", 31733).to_cosmos_msg()));
-    assert_eq!(res.messages[2], SubMsg::new(Undelegation::new("charlie", 31732).to_cosmos_msg()));
     assert_eq!(
-        res.messages[3],
-        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: STAKE_DENOM.to_string(),
-            msg: to_binary(&Cw20ExecuteMsg::Burn {
-                amount: Uint128::new(92876)
-            })
-            .unwrap(),
-            funds: vec![]
-        }))
+        res.messages,
+        vec![SubMsg::reply_on_error(
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+                msg: to_binary(&ExecuteMsg::Harvest {}).unwrap(),
+                funds: vec![]
+            }),
+            6
+        )]
     );
-    assert_eq!(res.messages[4], check_received_coin(0));
-
-    // A new pending batch should have been created
-    let pending_batch = state.pending_batch.load(deps.as_ref().storage).unwrap();
     assert_eq!(
-        pending_batch,
-        PendingBatch {
-            id: 2,
-            ustake_to_burn: Uint128::zero(),
-            est_unbond_start_time: 528401 // 269,201 + 259,200
-        }
+        res.events,
+        vec![Event::new("erishub/auto_maintenance")
+            .add_attribute("keeper", "user_1")
+            .add_attribute("tasks", "harvest")]
     );
+    assert_eq!(state.keeper.load(deps.as_ref().storage).unwrap(), Addr::unchecked("user_1"));
 
-    // Previous batch should have been updated
-    let previous_batch = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap();
+    // The harvest is executed and the keeper receives the tip from the rewards
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(1000, CONTRACT_DENOM)])
+        .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 3);
     assert_eq!(
-        previous_batch,
-        Batch {
-            id: 1,
-            reconciled: false,
-            total_shares: Uint128::new(92876),
-            uluna_unclaimed: Uint128::new(95197),
-            est_unbond_end_time: 2083601 // 269,201 + 1,814,400
-        }
+        res.messages[0],
+        SubMsg::new(Delegation::new("bob", 940).to_cosmos_msg(CONTRACT_DENOM))
     );
-
-    let res: StateResponse = query_helper_env(deps.as_ref(), QueryMsg::State {}, 2083600);
     assert_eq!(
-        res,
-        StateResponse {
-            total_ustake: Uint128::from(1012043u128),
-            total_uluna: Uint128::from(1037345u128),
-            exchange_rate: Decimal::from_ratio(1037345u128, 1012043u128),
-            unlocked_coins: vec![],
-            unbonding: Uint128::from(95197u128),
-            available: Uint128::zero(),
-            tvl_uluna: Uint128::from(95197u128 + 1037345u128),
-        },
+        res.messages[1],
+        SubMsg::new(SendFee::new(Addr::unchecked("fee"), 10).to_cosmos_msg(CONTRACT_DENOM))
     );
-}
-
-#[test]
-fn reconciling() {
-    let mut deps = setup_test();
-    let state = State::default();
-
-    let previous_batches = vec![
-        Batch {
-            id: 1,
-            reconciled: true,
-            total_shares: Uint128::new(92876),
-            uluna_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
-            est_unbond_end_time: 10000,
-        },
-        Batch {
-            id: 2,
-            reconciled: false,
-            total_shares: Uint128::new(1345),
-            uluna_unclaimed: Uint128::new(1385), // 1.030 Token per Stake
-            est_unbond_end_time: 20000,
-        },
-        Batch {
-            id: 3,
-            reconciled: false,
-            total_shares: Uint128::new(1456),
-            uluna_unclaimed: Uint128::new(1506), // 1.035 Token per Stake
-            est_unbond_end_time: 30000,
-        },
-        Batch {
-            id: 4,
-            reconciled: false,
-            total_shares: Uint128::new(1567),
-            uluna_unclaimed: Uint128::new(1629), // 1.040 Token per Stake
-            est_unbond_end_time: 40000,          // not yet finished unbonding, ignored
-        },
-    ];
-
-    for previous_batch in &previous_batches {
-        state
-            .previous_batches
-            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
-            .unwrap();
-    }
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(SendFee::new(Addr::unchecked("user_1"), 50).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert!(state.keeper.may_load(deps.as_ref().storage).unwrap().is_none());
 
-    state
-        .unlocked_coins
-        .save(
-            deps.as_mut().storage,
-            &vec![
-                Coin::new(10000, CONTRACT_DENOM),
-                Coin::new(234, "ukrw"),
-                Coin::new(345, "uusd"),
-                Coin::new(
-                    69420,
-                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
-                ),
-            ],
-        )
-        .unwrap();
+    // The harvest interval has not passed yet, nothing is overdue
+    let res = bond(&mut deps, 10000 + 86399);
+    assert_eq!(res.messages.len(), 3);
 
-    deps.querier.set_bank_balances(&[
-        Coin::new(12345, CONTRACT_DENOM),
-        Coin::new(234, "ukrw"),
-        Coin::new(345, "uusd"),
-        Coin::new(69420, "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"),
-    ]);
+    let res = bond(&mut deps, 10000 + 86400);
+    assert_eq!(res.messages.len(), 4);
 
-    execute(
+    // A failing maintenance task is only reported, the keeper is kept for the harvest
+    state.keeper.save(deps.as_mut().storage, &Addr::unchecked("user_1")).unwrap();
+    let res = reply(
         deps.as_mut(),
-        mock_env_at_timestamp(35000),
-        mock_info("worker", &[]),
-        ExecuteMsg::Reconcile {},
+        mock_env(),
+        Reply {
+            id: 4,
+            result: cosmwasm_std::SubMsgResult::Err("out of gas".to_string()),
+        },
     )
     .unwrap();
-
-    // Expected received: batch 2 + batch 3 = 1385 + 1506 = 2891
-    // Expected unlocked: 10000
-    // Expected: 12891
-    // Actual: 12345
-    // Shortfall: 12891 - 12345 = 456
-    //
-    // utoken per batch: 546 / 2 = 273
-    // remainder: 0
-    // batch 2: 1385 - 273 = 1112
-    // batch 3: 1506 - 273 = 1233
-    let batch = state.previous_batches.load(deps.as_ref().storage, 2u64).unwrap();
     assert_eq!(
-        batch,
-        Batch {
-            id: 2,
-            reconciled: true,
-            total_shares: Uint128::new(1345),
-            uluna_unclaimed: Uint128::new(1112), // 1385 - 273
-            est_unbond_end_time: 20000,
-        }
+        res.events,
+        vec![Event::new("erishub/auto_maintenance_failed")
+            .add_attribute("task", "submit_batch")
+            .add_attribute("error", "out of gas")]
     );
+    assert_eq!(state.keeper.load(deps.as_ref().storage).unwrap(), Addr::unchecked("user_1"));
 
-    let batch = state.previous_batches.load(deps.as_ref().storage, 3u64).unwrap();
+    // The keeper of a failed harvest is not tipped
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 6,
+            result: cosmwasm_std::SubMsgResult::Err("out of gas".to_string()),
+        },
+    )
+    .unwrap();
     assert_eq!(
-        batch,
-        Batch {
-            id: 3,
-            reconciled: true,
-            total_shares: Uint128::new(1456),
-            uluna_unclaimed: Uint128::new(1233), // 1506 - 273
-            est_unbond_end_time: 30000,
-        }
+        res.events,
+        vec![Event::new("erishub/auto_maintenance_failed")
+            .add_attribute("task", "harvest")
+            .add_attribute("error", "out of gas")]
     );
+    assert!(state.keeper.may_load(deps.as_ref().storage).unwrap().is_none());
 
-    // Batches 1 and 4 should not have changed
-    let batch = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap();
-    assert_eq!(batch, previous_batches[0]);
+    // Replies are only expected on error
+    let err = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 6,
+            result: cosmwasm_std::SubMsgResult::Ok(cosmwasm_std::SubMsgResponse {
+                events: vec![],
+                data: None,
+            }),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::UnexpectedReplySuccess(6));
 
-    let batch = state.previous_batches.load(deps.as_ref().storage, 4u64).unwrap();
-    assert_eq!(batch, previous_batches[3]);
+    // Disabled again
+    let config = AutoMaintenanceConfig {
+        enabled: false,
+        ..config
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update_config(config)).unwrap();
+
+    let res = bond(&mut deps, 10000 + 86400);
+    assert_eq!(res.messages.len(), 3);
 }
 
 #[test]
-fn reconciling_even_when_everything_ok() {
+fn rewarding_keepers() {
     let mut deps = setup_test();
     let state = State::default();
 
-    let previous_batches = vec![
-        Batch {
-            id: 1,
-            reconciled: true,
-            total_shares: Uint128::new(100000),
-            uluna_unclaimed: Uint128::new(100000),
-            est_unbond_end_time: 10000,
-        },
-        Batch {
-            id: 2,
-            reconciled: false,
-            total_shares: Uint128::new(1000),
-            uluna_unclaimed: Uint128::new(1000),
-            est_unbond_end_time: 20000,
-        },
-        Batch {
-            id: 3,
-            reconciled: false,
-            total_shares: Uint128::new(1500),
-            uluna_unclaimed: Uint128::new(1500),
-            est_unbond_end_time: 30000,
-        },
-        Batch {
-            id: 4,
-            reconciled: false,
-            total_shares: Uint128::new(1500),
-            uluna_unclaimed: Uint128::new(1500),
-            est_unbond_end_time: 40000, // not yet finished unbonding, ignored
-        },
-    ];
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 333334),
+        Delegation::new("bob", 333333),
+        Delegation::new("charlie", 333333),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
 
-    for previous_batch in &previous_batches {
-        state
-            .previous_batches
-            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
-            .unwrap();
-    }
+    let update_config = |keeper_reward: KeeperRewardConfig| ExecuteMsg::UpdateConfig {
+        protocol_fee_contract: None,
+        allow_donations: None,
+        vote_operator: None,
+        liquidity_buffer: None,
+        remove_slashed_validators: None,
+        insert_swap_routes: None,
+        delete_swap_routes: None,
+        validator_checks: None,
+        guardian: None,
+        fee_receivers: None,
+        auto_maintenance: None,
+        keeper_reward: Some(keeper_reward),
+        prop_gauges: None,
+        dust_cleanup: None,
+        split_bond: None,
+        remove_liquidity_buffer: None,
+        protocol_reward_fee: None,
+        delegation_strategy: None,
+        epoch_period: None,
+        unbond_period: None,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        update_config(KeeperRewardConfig {
+            reward: KeeperReward::Share(Decimal::percent(11)),
+            cooldown: 3600,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ShareTooHigh("keeper_reward".into()));
+
+    let config = KeeperRewardConfig {
+        reward: KeeperReward::Fixed(Uint128::new(100)),
+        cooldown: 3600,
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update_config(config.clone()))
+        .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.keeper_reward, Some(config));
+
+    let harvest =
+        |deps: &mut OwnedDeps<MockStorage, MockApi, CustomQuerier>, keeper: &str, time: u64| {
+            execute(
+                deps.as_mut(),
+                mock_env_at_timestamp(time),
+                mock_info(keeper, &[]),
+                ExecuteMsg::Harvest {},
+            )
+            .unwrap()
+        };
+
+    let res = harvest(&mut deps, "keeper_1", 10000);
+    assert_eq!(
+        res.attributes,
+        vec![attr("action", "erishub/harvest"), attr("keeper_reward_claimed", "harvest=keeper_1")]
+    );
 
+    // The reward of keeper_1 is still pending
+    let res = harvest(&mut deps, "keeper_2", 10000);
+    assert_eq!(res.attributes, vec![attr("action", "erishub/harvest")]);
+
+    // The keeper is paid from the harvested rewards
     state
         .unlocked_coins
         .save(deps.as_mut().storage, &vec![Coin::new(1000, CONTRACT_DENOM)])
         .unwrap();
-
-    deps.querier.set_bank_balances(&[Coin::new(3500, CONTRACT_DENOM)]);
-
-    execute(
+    let res = execute(
         deps.as_mut(),
-        mock_env_at_timestamp(35000),
-        mock_info("worker", &[]),
-        ExecuteMsg::Reconcile {},
+        mock_env_at_timestamp(10000),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
     )
     .unwrap();
-
-    let batch = state.previous_batches.load(deps.as_ref().storage, 2u64).unwrap();
+    assert_eq!(res.messages.len(), 3);
     assert_eq!(
-        batch,
-        Batch {
-            id: 2,
-            reconciled: true,
-            total_shares: Uint128::new(1000),
-            uluna_unclaimed: Uint128::new(1000),
-            est_unbond_end_time: 20000,
-        }
+        res.messages[0],
+        SubMsg::new(Delegation::new("bob", 890).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(SendFee::new(Addr::unchecked("fee"), 10).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(SendFee::new(Addr::unchecked("keeper_1"), 100).to_cosmos_msg(CONTRACT_DENOM))
     );
+    assert!(state.keeper_claims.may_load(deps.as_ref().storage, "harvest").unwrap().is_none());
 
-    let batch = state.previous_batches.load(deps.as_ref().storage, 3u64).unwrap();
+    // Cooldown has not passed yet, the harvest is not overdue
+    let res = harvest(&mut deps, "keeper_2", 10000 + 3599);
+    assert_eq!(res.attributes, vec![attr("action", "erishub/harvest")]);
+
+    // Harvests of the contract itself are never rewarded
+    let res = harvest(&mut deps, MOCK_CONTRACT_ADDR, 10000 + 7200);
+    assert_eq!(res.attributes, vec![attr("action", "erishub/harvest")]);
+
+    // The cooldown has passed, but the contract has just harvested
+    let res = harvest(&mut deps, "keeper_2", 10000 + 7200);
+    assert_eq!(res.attributes, vec![attr("action", "erishub/harvest")]);
+
+    let res = harvest(&mut deps, "keeper_2", 10000 + 7200 + 3600);
     assert_eq!(
-        batch,
-        Batch {
-            id: 3,
-            reconciled: true,
-            total_shares: Uint128::new(1500),
-            uluna_unclaimed: Uint128::new(1500),
-            est_unbond_end_time: 30000,
-        }
+        res.attributes,
+        vec![attr("action", "erishub/harvest"), attr("keeper_reward_claimed", "harvest=keeper_2")]
     );
 
-    // Batches 1 and 4 should not have changed
-    let batch = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap();
-    assert_eq!(batch, previous_batches[0]);
+    // Submitting a due batch is rewarded
+    state
+        .pending_batch
+        .save(
+            deps.as_mut().storage,
+            &PendingBatch {
+                id: 1,
+                ustake_to_burn: Uint128::new(1000),
+                est_unbond_start_time: 10000 + 7200 + 3600,
+            },
+        )
+        .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000 + 7200 + 3600),
+        mock_info("keeper_3", &[]),
+        ExecuteMsg::SubmitBatch {},
+    )
+    .unwrap();
+    assert!(res.attributes.contains(&attr("keeper_reward_claimed", "submit_batch=keeper_3")));
 
-    let batch = state.previous_batches.load(deps.as_ref().storage, 4u64).unwrap();
-    assert_eq!(batch, previous_batches[3]);
+    // Tuning delegations is not rewarded
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000 + 7200 + 3600),
+        mock_info("owner", &[]),
+        ExecuteMsg::TuneDelegations {},
+    )
+    .unwrap();
+    assert!(!res.attributes.iter().any(|attr| attr.key == "keeper_reward_claimed"));
 }
 
 #[test]
-fn reconciling_underflow() {
-    let mut deps = setup_test();
-    let state = State::default();
-    let previous_batches = vec![
+fn computing_keeper_rewards() {
+    let keepers = vec![Addr::unchecked("keeper_1"), Addr::unchecked("keeper_2")];
+
+    assert_eq!(
+        compute_keeper_rewards(
+            &KeeperReward::Share(Decimal::percent(5)),
+            &keepers,
+            Uint128::new(1000)
+        )
+        .unwrap(),
+        vec![
+            SendFee::new(Addr::unchecked("keeper_1"), 50),
+            SendFee::new(Addr::unchecked("keeper_2"), 50),
+        ]
+    );
+
+    // the keepers receive at most 10% of the rewards together, the later ones receive less
+    assert_eq!(
+        compute_keeper_rewards(
+            &KeeperReward::Fixed(Uint128::new(60)),
+            &keepers,
+            Uint128::new(1000)
+        )
+        .unwrap(),
+        vec![
+            SendFee::new(Addr::unchecked("keeper_1"), 60),
+            SendFee::new(Addr::unchecked("keeper_2"), 40),
+        ]
+    );
+    assert_eq!(
+        compute_keeper_rewards(
+            &KeeperReward::Share(Decimal::percent(10)),
+            &keepers,
+            Uint128::new(1000)
+        )
+        .unwrap(),
+        vec![SendFee::new(Addr::unchecked("keeper_1"), 100)]
+    );
+    assert_eq!(
+        compute_keeper_rewards(&KeeperReward::Fixed(Uint128::new(600)), &keepers, Uint128::zero())
+            .unwrap(),
+        vec![]
+    );
+}
+
+#[test]
+fn querying_exchange_rates() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 400000),
+        Delegation::new("bob", 300000),
+        Delegation::new("charlie", 300000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    let reinvest =
+        |deps: &mut OwnedDeps<MockStorage, MockApi, CustomQuerier>, amount: u128, time: u64| {
+            state
+                .unlocked_coins
+                .save(deps.as_mut().storage, &vec![Coin::new(amount, CONTRACT_DENOM)])
+                .unwrap();
+            execute(
+                deps.as_mut(),
+                mock_env_at_timestamp(time),
+                mock_info(MOCK_CONTRACT_ADDR, &[]),
+                ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+            )
+            .unwrap();
+        };
+
+    // No history yet, so no APR can be computed
+    let res: ExchangeRatesResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ExchangeRates {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res.exchange_rates, vec![]);
+    assert_eq!(res.apr, None);
+
+    // 1% protocol fee, so 1000000 uluna are bonded for the 1010101 uluna harvested
+    reinvest(&mut deps, 10101, 10000);
+    // A second harvest on the same day overwrites the day's entry
+    reinvest(&mut deps, 10101, 20000);
+
+    let res: ExchangeRatesResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ExchangeRates {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res.exchange_rates,
+        vec![(
+            0,
+            ExchangeHistory {
+                exchange_rate: Decimal::from_ratio(1010000u128, 1000000u128),
+                time_s: 20000,
+            }
+        )]
+    );
+    assert_eq!(res.apr, None);
+
+    // Half a year later the delegations have grown by another 1%
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 404000),
+        Delegation::new("bob", 303000),
+        Delegation::new("charlie", 303000),
+    ]);
+    reinvest(&mut deps, 10101, 20000 + YEAR / 2);
+
+    let res: ExchangeRatesResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ExchangeRates {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res.exchange_rates.len(), 2);
+    assert_eq!(res.exchange_rates[0].0, (20000 + YEAR / 2) / DAY);
+    assert_eq!(
+        res.exchange_rates[0].1.exchange_rate,
+        Decimal::from_ratio(1020000u128, 1000000u128)
+    );
+    // (1.02 - 1.01) / 1.01 annualized over half a year
+    assert_eq!(
+        res.apr,
+        Some(Decimal::percent(1) * (Decimal::from_ratio(2u128, 1u128) / Decimal::percent(101)))
+    );
+
+    // Pagination goes backwards in time
+    let res: ExchangeRatesResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ExchangeRates {
+            start_after: Some((20000 + YEAR / 2) / DAY),
+            limit: None,
+        },
+    );
+    assert_eq!(res.exchange_rates.len(), 1);
+    assert_eq!(res.exchange_rates[0].0, 0);
+
+    // After a slashing the exchange rate falls, the APR is clamped to 0
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 300000),
+        Delegation::new("bob", 300000),
+        Delegation::new("charlie", 300000),
+    ]);
+    reinvest(&mut deps, 10101, 20000 + YEAR);
+
+    let res: ExchangeRatesResponse = query_helper(
+        deps.as_ref(),
+        QueryMsg::ExchangeRates {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res.exchange_rates.len(), 3);
+    assert_eq!(res.apr, Some(Decimal::zero()));
+}
+
+#[test]
+fn swapping_rewards() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let ibc_denom = "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B";
+    let route = RewardSwapRoute {
+        denom: ibc_denom.to_string(),
+        route_type: SwapRouteType::Pair {
+            pair_contract: "pair".to_string(),
+        },
+        max_spread: Decimal::percent(50),
+    };
+
+    let update_config = |route: RewardSwapRoute| ExecuteMsg::UpdateConfig {
+        protocol_fee_contract: None,
+        allow_donations: None,
+        vote_operator: None,
+        liquidity_buffer: None,
+        remove_slashed_validators: None,
+        insert_swap_routes: Some(vec![route]),
+        delete_swap_routes: None,
+        validator_checks: None,
+        guardian: None,
+        fee_receivers: None,
+        auto_maintenance: None,
+        keeper_reward: None,
+        prop_gauges: None,
+        dust_cleanup: None,
+        split_bond: None,
+        remove_liquidity_buffer: None,
+        protocol_reward_fee: None,
+        delegation_strategy: None,
+        epoch_period: None,
+        unbond_period: None,
+    };
+
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update_config(route.clone()))
+            .unwrap_err();
+    assert_eq!(err, ContractError::MaxSpreadTooHigh {});
+
+    let route = RewardSwapRoute {
+        max_spread: Decimal::percent(5),
+        ..route
+    };
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), update_config(route.clone()))
+        .unwrap();
+
+    // Harvesting tracks the reward denoms with a swap route and swaps them before reinvesting
+    deps.querier.set_staking_delegations(&[Delegation::new("alice", 1000000)]);
+    deps.querier.set_bank_balances(&[coin(100, CONTRACT_DENOM), coin(200, ibc_denom)]);
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Harvest {},
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 5);
+    assert_eq!(res.messages[1], check_received_coin(100));
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::Callback(CallbackMsg::CheckReceivedCoin {
+                snapshot: coin(200, ibc_denom),
+            }))
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+    assert_eq!(
+        res.messages[3],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::Callback(CallbackMsg::Swap {})).unwrap(),
+            funds: vec![]
+        }))
+    );
+
+    state
+        .unlocked_coins
+        .save(
+            deps.as_mut().storage,
+            &vec![
+                Coin::new(234, CONTRACT_DENOM),
+                Coin::new(345, "uusd"),
+                Coin::new(69420, ibc_denom),
+            ],
+        )
+        .unwrap();
+
+    let res: Vec<PendingRewardsResponseItem> =
+        query_helper(deps.as_ref(), QueryMsg::PendingRewards {});
+    assert_eq!(
+        res,
+        vec![
+            PendingRewardsResponseItem {
+                reward: Coin::new(345, "uusd"),
+                swap_route: None,
+            },
+            PendingRewardsResponseItem {
+                reward: Coin::new(69420, ibc_denom),
+                swap_route: Some(route),
+            },
+        ]
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Swap {}),
+    )
+    .unwrap();
+
+    // Every route is swapped in its own callback, a failing swap does not revert the harvest
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::reply_on_error(
+            CallbackMsg::SwapCoin {
+                coin: Coin::new(69420, ibc_denom),
+            }
+            .into_cosmos_msg(&Addr::unchecked(MOCK_CONTRACT_ADDR))
+            .unwrap(),
+            3
+        )
+    );
+    assert_eq!(res.messages[1], check_received_coin(100));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::SwapCoin {
+            coin: Coin::new(69420, ibc_denom),
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(
+            Pair(Addr::unchecked("pair"))
+                .swap_msg(
+                    &Asset {
+                        info: AssetInfo::NativeToken {
+                            denom: ibc_denom.to_string(),
+                        },
+                        amount: Uint128::new(69420),
+                    },
+                    None,
+                    Some(Decimal::percent(5)),
+                    None,
+                )
+                .unwrap()
+        )]
+    );
+
+    // Swapped rewards are removed from the unlocked coins, the received Luna is added afterwards
+    let unlocked_coins = state.unlocked_coins.load(deps.as_ref().storage).unwrap();
+    assert_eq!(unlocked_coins, vec![Coin::new(234, CONTRACT_DENOM), Coin::new(345, "uusd")]);
+
+    // If the swap fails, its changes are reverted and the failure is only reported
+    let res = reply(
+        deps.as_mut(),
+        mock_env(),
+        Reply {
+            id: 3,
+            result: cosmwasm_std::SubMsgResult::Err("max spread assertion".to_string()),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/swap_failed").add_attribute("error", "max spread assertion")]
+    );
+}
+
+#[test]
+fn queuing_unbond() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // Only Stake token is accepted for unbonding requests
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("random_token", &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "hacker".to_string(),
+            amount: Uint128::new(69420),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::ExpectingStakeToken("random_token".into()));
+
+    // User 1 creates an unbonding request before `est_unbond_start_time` is reached. The unbond
+    // request is saved, but not the pending batch is not submitted for unbonding
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345), // est_unbond_start_time = 269200
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(23456),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 0);
+
+    // User 2 creates an unbonding request after `est_unbond_start_time` is reached. The unbond
+    // request is saved, and the pending is automatically submitted for unbonding
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201), // est_unbond_start_time = 269200
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_2".to_string(),
+            amount: Uint128::new(69420),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: Some("user_3".to_string()),
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::SubmitBatch {}).unwrap(),
+            funds: vec![]
+        }))
+    );
+
+    // The users' unbonding requests should have been saved
+    let ubr1 = state
+        .unbond_requests
+        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
+        .unwrap();
+    let ubr2 = state
+        .unbond_requests
+        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_3")))
+        .unwrap();
+
+    assert_eq!(
+        ubr1,
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(23456)
+        }
+    );
+    assert_eq!(
+        ubr2,
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_3"),
+            shares: Uint128::new(69420)
+        }
+    );
+
+    // Pending batch should have been updated
+    let pending_batch = state.pending_batch.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        pending_batch,
+        PendingBatch {
+            id: 1,
+            ustake_to_burn: Uint128::new(92876), // 23,456 + 69,420
+            est_unbond_start_time: 269200
+        }
+    );
+}
+
+#[test]
+fn cancelling_unbond() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    for (user, amount) in [("user_1", 23456u128), ("user_2", 69420u128)] {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(12345), // est_unbond_start_time = 269200
+            mock_info(STAKE_DENOM, &[]),
+            ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+                sender: user.to_string(),
+                amount: Uint128::new(amount),
+                msg: to_binary(&ReceiveMsg::QueueUnbond {
+                    receiver: None,
+                })
+                .unwrap(),
+            }),
+        )
+        .unwrap();
+    }
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CancelUnbond {
+            batch_id: 0,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::BatchNotPending(0));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_3", &[]),
+        ExecuteMsg::CancelUnbond {
+            batch_id: 1,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::UnbondRequestNotFound(1));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CancelUnbond {
+            batch_id: 1,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "user_1".to_string(),
+                amount: Uint128::new(23456)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+
+    // The request is removed and the pending batch only contains user 2's shares
+    let ubr = state
+        .unbond_requests
+        .may_load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
+        .unwrap();
+    assert_eq!(ubr, None);
+
+    let res: Vec<UnbondRequestsByUserResponseItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UnbondRequestsByUser {
+            user: "user_1".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![]);
+
+    let pending_batch = state.pending_batch.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        pending_batch,
+        PendingBatch {
+            id: 1,
+            ustake_to_burn: Uint128::new(69420),
+            est_unbond_start_time: 269200
+        }
+    );
+
+    // A request can only be cancelled once
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CancelUnbond {
+            batch_id: 1,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::UnbondRequestNotFound(1));
+}
+
+#[test]
+fn transferring_unbond_request() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(1000),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    let transfer = |recipient: &str, shares: u128| ExecuteMsg::TransferUnbondRequest {
+        batch_id: 1,
+        recipient: recipient.to_string(),
+        shares: Uint128::new(shares),
+    };
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("user_1", &[]), transfer("user_2", 0))
+        .unwrap_err();
+    assert_eq!(err, ContractError::CantBeZero("shares".into()));
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("user_1", &[]), transfer("user_1", 400))
+        .unwrap_err();
+    assert_eq!(err, ContractError::CantTransferToSelf {});
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("user_3", &[]), transfer("user_2", 400))
+        .unwrap_err();
+    assert_eq!(err, ContractError::UnbondRequestNotFound(1));
+
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("user_1", &[]), transfer("user_2", 1001))
+            .unwrap_err();
+    assert_eq!(err, ContractError::NotEnoughShares(Uint128::new(1000)));
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("user_1", &[]), transfer("user_2", 400))
+        .unwrap();
+    assert_eq!(res.messages.len(), 0);
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/unbond_request_transferred")
+            .add_attribute("id", "1")
+            .add_attribute("sender", "user_1")
+            .add_attribute("recipient", "user_2")
+            .add_attribute("shares", "400")]
+    );
+
+    let query_user = |deps: &OwnedDeps<MockStorage, MockApi, CustomQuerier>, user: &str| {
+        query_helper::<Vec<UnbondRequestsByUserResponseItem>>(
+            deps.as_ref(),
+            QueryMsg::UnbondRequestsByUser {
+                user: user.to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+    };
+
+    assert_eq!(
+        query_user(&deps, "user_1"),
+        vec![UnbondRequestsByUserResponseItem {
+            id: 1,
+            shares: Uint128::new(600),
+        }]
+    );
+    assert_eq!(
+        query_user(&deps, "user_2"),
+        vec![UnbondRequestsByUserResponseItem {
+            id: 1,
+            shares: Uint128::new(400),
+        }]
+    );
+
+    // Transferring the remaining shares removes the request of the sender
+    execute(deps.as_mut(), mock_env(), mock_info("user_1", &[]), transfer("user_2", 600)).unwrap();
+
+    assert_eq!(query_user(&deps, "user_1"), vec![]);
+    assert_eq!(
+        query_user(&deps, "user_2"),
+        vec![UnbondRequestsByUserResponseItem {
+            id: 1,
+            shares: Uint128::new(1000),
+        }]
+    );
+
+    // The batch is not affected by the transfer
+    let pending_batch = state.pending_batch.load(deps.as_ref().storage).unwrap();
+    assert_eq!(pending_batch.ustake_to_burn, Uint128::new(1000));
+}
+
+#[test]
+fn instant_unbonding() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let instant_unbond_msg = ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+        sender: "user_1".to_string(),
+        amount: Uint128::new(10000),
+        msg: to_binary(&ReceiveMsg::InstantUnbond {
+            receiver: None,
+        })
+        .unwrap(),
+    });
+
+    // Instant unbonding is only possible when a liquidity buffer is configured
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info(STAKE_DENOM, &[]), instant_unbond_msg.clone())
+            .unwrap_err();
+    assert_eq!(err, ContractError::InstantUnbondDisabled {});
+
+    let mut buffer_config = LiquidityBufferConfig {
+        target: Uint128::new(100000),
+        instant_unbond_fee: Decimal::percent(20),
+        refill_bond_share: Decimal::percent(50),
+        refill_reinvest_share: Decimal::percent(50),
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            protocol_fee_contract: None,
+            allow_donations: None,
+            vote_operator: None,
+            liquidity_buffer: Some(buffer_config.clone()),
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: None,
+            unbond_period: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InstantUnbondFeeTooHigh {});
+
+    buffer_config.instant_unbond_fee = Decimal::percent(1);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            protocol_fee_contract: None,
+            allow_donations: None,
+            vote_operator: None,
+            liquidity_buffer: Some(buffer_config.clone()),
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: None,
+            unbond_period: None,
+        },
+    )
+    .unwrap();
+
+    // Half of the deposit is used to refill the buffer, but not more than the target
+    deps.querier.set_bank_balances(&[coin(1000100, CONTRACT_DENOM)]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000000, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("alice", 900000).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: "user_1".to_string(),
+                amount: Uint128::new(1000000)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+    assert_eq!(res.messages[2], check_received_coin(100100));
+    assert_eq!(state.liquidity_buffer.load(deps.as_ref().storage).unwrap(), Uint128::new(100000));
+
+    deps.querier.set_staking_delegations(&[Delegation::new("alice", 900000)]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(res.exchange_rate, Decimal::one());
+    assert_eq!(res.liquidity_buffer, Uint128::new(100000));
+    assert_eq!(res.liquidity_buffer_config, Some(buffer_config.clone()));
+
+    // Only Stake token is accepted for instant unbonding
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("random_token", &[]),
+        instant_unbond_msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ExpectingStakeToken("random_token".into()));
+
+    // 10000 ustake are worth 10000 uluna, 1% fee stays in the buffer
+    let res = execute(deps.as_mut(), mock_env(), mock_info(STAKE_DENOM, &[]), instant_unbond_msg)
+        .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(10000)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![Coin::new(9900, CONTRACT_DENOM)]
+        }))
+    );
+    assert_eq!(state.liquidity_buffer.load(deps.as_ref().storage).unwrap(), Uint128::new(90100));
+
+    // Unbonding more than the buffer holds fails
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(200000),
+            msg: to_binary(&ReceiveMsg::InstantUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NotEnoughLiquidity(Uint128::new(90100)));
+
+    // Removing the buffer disables instant unbonding and delegates the buffer again
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            protocol_fee_contract: None,
+            allow_donations: None,
+            vote_operator: None,
+            liquidity_buffer: Some(buffer_config),
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: Some(true),
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: None,
+            unbond_period: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::LiquidityBufferUpdatedAndRemoved {});
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            protocol_fee_contract: None,
+            allow_donations: None,
+            vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: Some(true),
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: None,
+            unbond_period: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(Delegation::new("bob", 90100).to_cosmos_msg(CONTRACT_DENOM))]
+    );
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/liquidity_buffer_removed")
+            .add_attribute("utoken_delegated", "90100")]
+    );
+    assert_eq!(state.liquidity_buffer.may_load(deps.as_ref().storage).unwrap(), None);
+    assert_eq!(state.liquidity_buffer_config.may_load(deps.as_ref().storage).unwrap(), None);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(10000),
+            msg: to_binary(&ReceiveMsg::InstantUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InstantUnbondDisabled {});
+}
+
+#[test]
+fn token_factory_stake_token() {
+    // A CW20 stake token needs to be sent to the hub for unbonding
+    let mut deps = setup_test();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(10000, STAKE_DENOM)]),
+        ExecuteMsg::QueueUnbond {
+            receiver: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::StakeTokenIsCw20 {});
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(10000, STAKE_DENOM)]),
+        ExecuteMsg::InstantUnbond {
+            receiver: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::StakeTokenIsCw20 {});
+
+    let mut deps = mock_dependencies();
+    let stake_denom = format!("factory/{0}/stake", MOCK_CONTRACT_ADDR);
+
+    let res = instantiate(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        InstantiateMsg {
+            cw20_code_id: 69420,
+            owner: "owner".to_string(),
+            name: "Stake Token".to_string(),
+            symbol: "STAKE".to_string(),
+            decimals: 6,
+            epoch_period: 259200,
+            unbond_period: 1814400,
+            validators: vec!["alice".to_string(), "bob".to_string(), "charlie".to_string()],
+            protocol_fee_contract: "fee".to_string(),
+            protocol_reward_fee: Decimal::from_ratio(1u128, 100u128),
+            delegation_strategy: None,
+            vote_operator: None,
+            token_factory_subdenom: Some("stake".to_string()),
+            denom: None,
+            config_timelock: None,
+            stake_token_marketing: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(tokenfactory::create_denom_msg(MOCK_CONTRACT_ADDR, "stake").unwrap())
+    );
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.stake_token, stake_denom);
+
+    // Bonding mints the native stake token to the hub and forwards it to the receiver
+    deps.querier.set_bank_balances(&[coin(1000100, CONTRACT_DENOM)]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000000, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("alice", 1000000).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(
+            tokenfactory::mint_msg(MOCK_CONTRACT_ADDR, &coin(1000000, &stake_denom)).unwrap()
+        )
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![coin(1000000, &stake_denom)]
+        }))
+    );
+    assert_eq!(res.messages[3], check_received_coin(100));
+
+    // Unbonding takes the native stake token as funds
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_1", &[Coin::new(10000, CONTRACT_DENOM)]),
+        ExecuteMsg::QueueUnbond {
+            receiver: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(format!(
+            "expected {} deposit, received {}",
+            stake_denom, CONTRACT_DENOM
+        )))
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(12345),
+        mock_info("user_1", &[Coin::new(10000, &stake_denom)]),
+        ExecuteMsg::QueueUnbond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let res: PendingBatch = query_helper(deps.as_ref(), QueryMsg::PendingBatch {});
+    assert_eq!(res.ustake_to_burn, Uint128::new(10000));
+
+    // Instant unbonding takes the native stake token as funds as well
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(10000, CONTRACT_DENOM)]),
+        ExecuteMsg::InstantUnbond {
+            receiver: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(format!(
+            "expected {} deposit, received {}",
+            stake_denom, CONTRACT_DENOM
+        )))
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(10000, &stake_denom)]),
+        ExecuteMsg::InstantUnbond {
+            receiver: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InstantUnbondDisabled {});
+
+    // CW20 tokens are not accepted anymore
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(10000),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ExpectingStakeToken(STAKE_DENOM.into()));
+}
+
+#[test]
+fn encoding_token_factory_msgs() {
+    // field 1: sender, field 2: subdenom
+    assert_eq!(
+        tokenfactory::create_denom_msg("hub", "stake").unwrap(),
+        CosmosMsg::Stargate {
+            type_url: "/osmosis.tokenfactory.v1beta1.MsgCreateDenom".to_string(),
+            value: b"\x0a\x03hub\x12\x05stake".to_vec().into(),
+        }
+    );
+
+    // field 1: sender, field 2: coin with field 1: denom, field 2: amount as string
+    let coin_bytes = b"\x0a\x11factory/hub/stake\x12\x041000";
+    let expected = [b"\x0a\x03hub\x12\x19".as_slice(), coin_bytes.as_slice()].concat();
+
+    assert_eq!(
+        tokenfactory::mint_msg("hub", &coin(1000, "factory/hub/stake")).unwrap(),
+        CosmosMsg::Stargate {
+            type_url: "/osmosis.tokenfactory.v1beta1.MsgMint".to_string(),
+            value: expected.clone().into(),
+        }
+    );
+    assert_eq!(
+        tokenfactory::burn_msg("hub", &coin(1000, "factory/hub/stake")).unwrap(),
+        CosmosMsg::Stargate {
+            type_url: "/osmosis.tokenfactory.v1beta1.MsgBurn".to_string(),
+            value: expected.into(),
+        }
+    );
+}
+
+#[test]
+fn custom_staking_denom() {
+    let mut deps = mock_dependencies();
+
+    let msg = InstantiateMsg {
+        cw20_code_id: 69420,
+        owner: "owner".to_string(),
+        name: "Stake Token".to_string(),
+        symbol: "STAKE".to_string(),
+        decimals: 6,
+        epoch_period: 259200,
+        unbond_period: 1814400,
+        validators: vec!["alice".to_string(), "bob".to_string(), "charlie".to_string()],
+        protocol_fee_contract: "fee".to_string(),
+        protocol_reward_fee: Decimal::from_ratio(1u128, 100u128),
+        delegation_strategy: None,
+        vote_operator: None,
+        token_factory_subdenom: Some("stake".to_string()),
+        denom: Some("".to_string()),
+        config_timelock: None,
+        stake_token_marketing: None,
+    };
+
+    let err = instantiate(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidDenom("".to_string()));
+
+    instantiate(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("deployer", &[]),
+        InstantiateMsg {
+            denom: Some("uatom".to_string()),
+            ..msg
+        },
+    )
+    .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.denom, "uatom".to_string());
+
+    // Luna is not accepted anymore
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000000, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(format!(
+            "expected {} deposit, received {}",
+            "uatom", CONTRACT_DENOM
+        )))
+    );
+
+    deps.querier.set_bank_balances(&[coin(1000100, "uatom")]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000000, "uatom")]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("alice", 1000000).to_cosmos_msg("uatom"))
+    );
+    assert_eq!(
+        res.messages[3],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: MOCK_CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::Callback(CallbackMsg::CheckReceivedCoin {
+                snapshot: coin(100, "uatom"),
+            }))
+            .unwrap(),
+            funds: vec![],
+        }))
+    );
+
+    let res: StateResponse = query_helper(deps.as_ref(), QueryMsg::State {});
+    assert_eq!(res.available, Uint128::new(1000100));
+}
+
+#[test]
+fn submitting_batch() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // utoken bonded: 1,037,345
+    // ustake supply: 1,012,043
+    // utoken per ustake: 1.025
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 345782),
+        Delegation::new("bob", 345782),
+        Delegation::new("charlie", 345781),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1012043);
+
+    // We continue from the contract state at the end of the last test
+    let unbond_requests = vec![
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(23456),
+        },
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_3"),
+            shares: Uint128::new(69420),
+        },
+    ];
+
+    for unbond_request in &unbond_requests {
+        state
+            .unbond_requests
+            .save(
+                deps.as_mut().storage,
+                (unbond_request.id, &Addr::unchecked(unbond_request.user.clone())),
+                unbond_request,
+            )
+            .unwrap();
+    }
+
+    state
+        .storage_metrics
+        .save(
+            deps.as_mut().storage,
+            &StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 2,
+            },
+        )
+        .unwrap();
+
+    state
+        .pending_batch
+        .save(
+            deps.as_mut().storage,
+            &PendingBatch {
+                id: 1,
+                ustake_to_burn: Uint128::new(92876), // 23,456 + 69,420
+                est_unbond_start_time: 269200,
+            },
+        )
+        .unwrap();
+
+    // Anyone can invoke `submit_batch`. Here we continue from the previous test and assume it is
+    // invoked automatically as user 2 submits the unbonding request
+    //
+    // ustake to burn: 23,456 + 69,420 = 92,876
+    // utoken to unbond: 1,037,345 * 92,876 / 1,012,043 = 95,197
+    //
+    // Target: (1,037,345 - 95,197) / 3 = 314,049
+    // Remainer: 1
+    // Alice:   345,782 - (314,049 + 1) = 31,732
+    // Bob:     345,782 - (314,049 + 0) = 31,733
+    // Charlie: 345,781 - (314,049 + 0) = 31,732
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch {},
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 5);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Undelegation::new("alice", 31732).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(Undelegation::new("bob", 31733).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[2],
+        SubMsg::new(Undelegation::new("charlie", 31732).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[3],
+        SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: STAKE_DENOM.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(92876)
+            })
+            .unwrap(),
+            funds: vec![]
+        }))
+    );
+    assert_eq!(res.messages[4], check_received_coin(0));
+
+    // A new pending batch should have been created
+    let pending_batch = state.pending_batch.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        pending_batch,
+        PendingBatch {
+            id: 2,
+            ustake_to_burn: Uint128::zero(),
+            est_unbond_start_time: 528401 // 269,201 + 259,200
+        }
+    );
+
+    // Previous batch should have been updated
+    let previous_batch = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap();
+    assert_eq!(
+        previous_batch,
+        Batch {
+            id: 1,
+            reconciled: false,
+            total_shares: Uint128::new(92876),
+            utoken_unclaimed: Uint128::new(95197),
+            est_unbond_end_time: 2083601 // 269,201 + 1,814,400
+        }
+    );
+
+    let res: StateResponse = query_helper_env(deps.as_ref(), QueryMsg::State {}, 2083600);
+    assert_eq!(
+        res,
+        StateResponse {
+            total_ustake: Uint128::from(1012043u128),
+            total_utoken: Uint128::from(1037345u128),
+            exchange_rate: Decimal::from_ratio(1037345u128, 1012043u128),
+            unlocked_coins: vec![],
+            unbonding: Uint128::from(95197u128),
+            available: Uint128::zero(),
+            tvl_utoken: Uint128::from(95197u128 + 1037345u128),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 1,
+                unbond_requests: 2,
+            },
+        },
+    );
+}
+
+#[test]
+fn submitting_batch_with_full_unbonding_slots() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 1000),
+        Delegation::new("bob", 1000),
+        Delegation::new("charlie", 1000),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 3000);
+
+    state
+        .pending_batch
+        .save(
+            deps.as_mut().storage,
+            &PendingBatch {
+                id: 1,
+                ustake_to_burn: Uint128::new(300),
+                est_unbond_start_time: 269200,
+            },
+        )
+        .unwrap();
+
+    // bob has 7 open unbonding entries, the first one already completed
+    let bob_entries = (0..8).map(|i| 100 + i * 1000000).collect::<Vec<u64>>();
+    state.unbonding_entries.save(deps.as_mut().storage, "bob", &bob_entries).unwrap();
+
+    // Target: (3,000 - 300) / 3 = 900
+    // Alice:   1,000 - 900 = 100
+    // Bob:     no free slot
+    // Charlie: 1,000 - 900 = 100
+    // Alice takes the part of Bob: 100 + 100 = 200
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(269201),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::SubmitBatch {},
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 4);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Undelegation::new("alice", 200).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(Undelegation::new("charlie", 100).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(res.messages[3], check_received_coin(0));
+
+    let res: UnbondingSlotsResponse =
+        query_helper_env(deps.as_ref(), QueryMsg::UnbondingSlots {}, 269201);
+    assert_eq!(
+        res,
+        UnbondingSlotsResponse {
+            max_entries: 7,
+            validators: vec![
+                UnbondingSlotsResponseItem {
+                    validator: "alice".to_string(),
+                    used: 1,
+                    completion_times: vec![2083601], // 269,201 + 1,814,400
+                },
+                UnbondingSlotsResponseItem {
+                    validator: "bob".to_string(),
+                    used: 7,
+                    completion_times: bob_entries[1..].to_vec(),
+                },
+                UnbondingSlotsResponseItem {
+                    validator: "charlie".to_string(),
+                    used: 1,
+                    completion_times: vec![2083601],
+                },
+            ],
+        }
+    );
+
+    // once the entries completed, all slots are free again
+    let res: UnbondingSlotsResponse =
+        query_helper_env(deps.as_ref(), QueryMsg::UnbondingSlots {}, 7000100);
+    assert_eq!(res.validators.iter().map(|v| v.used).collect::<Vec<_>>(), vec![0, 0, 0]);
+}
+
+#[test]
+fn reconciling() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let previous_batches = vec![
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(92876),
+            utoken_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
+            est_unbond_end_time: 10000,
+        },
+        Batch {
+            id: 2,
+            reconciled: false,
+            total_shares: Uint128::new(1345),
+            utoken_unclaimed: Uint128::new(1385), // 1.030 Token per Stake
+            est_unbond_end_time: 20000,
+        },
+        Batch {
+            id: 3,
+            reconciled: false,
+            total_shares: Uint128::new(1456),
+            utoken_unclaimed: Uint128::new(1506), // 1.035 Token per Stake
+            est_unbond_end_time: 30000,
+        },
+        Batch {
+            id: 4,
+            reconciled: false,
+            total_shares: Uint128::new(1567),
+            utoken_unclaimed: Uint128::new(1629), // 1.040 Token per Stake
+            est_unbond_end_time: 40000,           // not yet finished unbonding, ignored
+        },
+    ];
+
+    for previous_batch in &previous_batches {
+        state
+            .previous_batches
+            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
+            .unwrap();
+    }
+
+    state
+        .unlocked_coins
+        .save(
+            deps.as_mut().storage,
+            &vec![
+                Coin::new(10000, CONTRACT_DENOM),
+                Coin::new(234, "ukrw"),
+                Coin::new(345, "uusd"),
+                Coin::new(
+                    69420,
+                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
+                ),
+            ],
+        )
+        .unwrap();
+
+    deps.querier.set_bank_balances(&[
+        Coin::new(12345, CONTRACT_DENOM),
+        Coin::new(234, "ukrw"),
+        Coin::new(345, "uusd"),
+        Coin::new(69420, "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"),
+    ]);
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(35000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+
+    // Expected received: batch 2 + batch 3 = 1385 + 1506 = 2891
+    // Expected unlocked: 10000
+    // Expected: 12891
+    // Actual: 12345
+    // Shortfall: 12891 - 12345 = 456
+    //
+    // utoken per batch: 546 / 2 = 273
+    // remainder: 0
+    // batch 2: 1385 - 273 = 1112
+    // batch 3: 1506 - 273 = 1233
+    let batch = state.previous_batches.load(deps.as_ref().storage, 2u64).unwrap();
+    assert_eq!(
+        batch,
+        Batch {
+            id: 2,
+            reconciled: true,
+            total_shares: Uint128::new(1345),
+            utoken_unclaimed: Uint128::new(1112), // 1385 - 273
+            est_unbond_end_time: 20000,
+        }
+    );
+
+    let batch = state.previous_batches.load(deps.as_ref().storage, 3u64).unwrap();
+    assert_eq!(
+        batch,
+        Batch {
+            id: 3,
+            reconciled: true,
+            total_shares: Uint128::new(1456),
+            utoken_unclaimed: Uint128::new(1233), // 1506 - 273
+            est_unbond_end_time: 30000,
+        }
+    );
+
+    // Batches 1 and 4 should not have changed
+    let batch = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap();
+    assert_eq!(batch, previous_batches[0]);
+
+    let batch = state.previous_batches.load(deps.as_ref().storage, 4u64).unwrap();
+    assert_eq!(batch, previous_batches[3]);
+}
+
+#[test]
+fn reconciling_even_when_everything_ok() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let previous_batches = vec![
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(100000),
+            utoken_unclaimed: Uint128::new(100000),
+            est_unbond_end_time: 10000,
+        },
+        Batch {
+            id: 2,
+            reconciled: false,
+            total_shares: Uint128::new(1000),
+            utoken_unclaimed: Uint128::new(1000),
+            est_unbond_end_time: 20000,
+        },
+        Batch {
+            id: 3,
+            reconciled: false,
+            total_shares: Uint128::new(1500),
+            utoken_unclaimed: Uint128::new(1500),
+            est_unbond_end_time: 30000,
+        },
+        Batch {
+            id: 4,
+            reconciled: false,
+            total_shares: Uint128::new(1500),
+            utoken_unclaimed: Uint128::new(1500),
+            est_unbond_end_time: 40000, // not yet finished unbonding, ignored
+        },
+    ];
+
+    for previous_batch in &previous_batches {
+        state
+            .previous_batches
+            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
+            .unwrap();
+    }
+
+    state
+        .unlocked_coins
+        .save(deps.as_mut().storage, &vec![Coin::new(1000, CONTRACT_DENOM)])
+        .unwrap();
+
+    deps.querier.set_bank_balances(&[Coin::new(3500, CONTRACT_DENOM)]);
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(35000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+
+    let batch = state.previous_batches.load(deps.as_ref().storage, 2u64).unwrap();
+    assert_eq!(
+        batch,
+        Batch {
+            id: 2,
+            reconciled: true,
+            total_shares: Uint128::new(1000),
+            utoken_unclaimed: Uint128::new(1000),
+            est_unbond_end_time: 20000,
+        }
+    );
+
+    let batch = state.previous_batches.load(deps.as_ref().storage, 3u64).unwrap();
+    assert_eq!(
+        batch,
+        Batch {
+            id: 3,
+            reconciled: true,
+            total_shares: Uint128::new(1500),
+            utoken_unclaimed: Uint128::new(1500),
+            est_unbond_end_time: 30000,
+        }
+    );
+
+    // Batches 1 and 4 should not have changed
+    let batch = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap();
+    assert_eq!(batch, previous_batches[0]);
+
+    let batch = state.previous_batches.load(deps.as_ref().storage, 4u64).unwrap();
+    assert_eq!(batch, previous_batches[3]);
+}
+
+#[test]
+fn reconciling_underflow() {
+    let mut deps = setup_test();
+    let state = State::default();
+    let previous_batches = vec![
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(92876),
+            utoken_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
+            est_unbond_end_time: 10000,
+        },
+        Batch {
+            id: 2,
+            reconciled: false,
+            total_shares: Uint128::new(1345),
+            utoken_unclaimed: Uint128::new(1385), // 1.030 Token per Stake
+            est_unbond_end_time: 20000,
+        },
+        Batch {
+            id: 3,
+            reconciled: false,
+            total_shares: Uint128::new(1456),
+            utoken_unclaimed: Uint128::new(1506), // 1.035 Token per Stake
+            est_unbond_end_time: 30000,
+        },
+        Batch {
+            id: 4,
+            reconciled: false,
+            total_shares: Uint128::new(1),
+            utoken_unclaimed: Uint128::new(1),
+            est_unbond_end_time: 30001,
+        },
+    ];
+    for previous_batch in &previous_batches {
+        state
+            .previous_batches
+            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
+            .unwrap();
+    }
+    state
+        .unlocked_coins
+        .save(
+            deps.as_mut().storage,
+            &vec![
+                Coin::new(10000, CONTRACT_DENOM),
+                Coin::new(234, "ukrw"),
+                Coin::new(345, "uusd"),
+                Coin::new(
+                    69420,
+                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
+                ),
+            ],
+        )
+        .unwrap();
+    deps.querier.set_bank_balances(&[
+        Coin::new(12345, CONTRACT_DENOM),
+        Coin::new(234, "ukrw"),
+        Coin::new(345, "uusd"),
+        Coin::new(69420, "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"),
+    ]);
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(35000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+}
+
+#[test]
+fn reconciling_underflow_second() {
+    let mut deps = setup_test();
+    let state = State::default();
+    let previous_batches = vec![
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(92876),
+            utoken_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
+            est_unbond_end_time: 10000,
+        },
+        Batch {
+            id: 2,
+            reconciled: false,
+            total_shares: Uint128::new(1345),
+            utoken_unclaimed: Uint128::new(1385), // 1.030 Token per Stake
+            est_unbond_end_time: 20000,
+        },
+        Batch {
+            id: 3,
+            reconciled: false,
+            total_shares: Uint128::new(176),
+            utoken_unclaimed: Uint128::new(183), // 1.035 Token per Stake
+            est_unbond_end_time: 30000,
+        },
+        Batch {
+            id: 4,
+            reconciled: false,
+            total_shares: Uint128::new(1),
+            utoken_unclaimed: Uint128::new(1),
+            est_unbond_end_time: 30001,
+        },
+    ];
+    for previous_batch in &previous_batches {
+        state
+            .previous_batches
+            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
+            .unwrap();
+    }
+    state
+        .unlocked_coins
+        .save(
+            deps.as_mut().storage,
+            &vec![
+                Coin::new(10000, CONTRACT_DENOM),
+                Coin::new(234, "ukrw"),
+                Coin::new(345, "uusd"),
+                Coin::new(
+                    69420,
+                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
+                ),
+            ],
+        )
+        .unwrap();
+    deps.querier.set_bank_balances(&[
+        Coin::new(12345 - 1323, CONTRACT_DENOM),
+        Coin::new(234, "ukrw"),
+        Coin::new(345, "uusd"),
+        Coin::new(69420, "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"),
+    ]);
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(35000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
+    )
+    .unwrap();
+}
+
+#[test]
+fn withdrawing_unbonded() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // We simulate a most general case:
+    // - batches 1 and 2 have finished unbonding
+    // - batch 3 have been submitted for unbonding but have not finished
+    // - batch 4 is still pending
+    let unbond_requests = vec![
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(23456),
+        },
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_3"),
+            shares: Uint128::new(69420),
+        },
+        UnbondRequest {
+            id: 2,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(34567),
+        },
+        UnbondRequest {
+            id: 3,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(45678),
+        },
+        UnbondRequest {
+            id: 4,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(56789),
+        },
+    ];
+
+    for unbond_request in &unbond_requests {
+        state
+            .unbond_requests
+            .save(
+                deps.as_mut().storage,
+                (unbond_request.id, &Addr::unchecked(unbond_request.user.clone())),
+                unbond_request,
+            )
+            .unwrap();
+    }
+
+    let previous_batches = vec![
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(92876),
+            utoken_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
+            est_unbond_end_time: 10000,
+        },
+        Batch {
+            id: 2,
+            reconciled: true,
+            total_shares: Uint128::new(34567),
+            utoken_unclaimed: Uint128::new(35604), // 1.030 Token per Stake
+            est_unbond_end_time: 20000,
+        },
+        Batch {
+            id: 3,
+            reconciled: false, // finished unbonding, but not reconciled; ignored
+            total_shares: Uint128::new(45678),
+            utoken_unclaimed: Uint128::new(47276), // 1.035 Token per Stake
+            est_unbond_end_time: 20000,
+        },
+        Batch {
+            id: 4,
+            reconciled: true,
+            total_shares: Uint128::new(56789),
+            utoken_unclaimed: Uint128::new(59060), // 1.040 Token per Stake
+            est_unbond_end_time: 30000, // reconciled, but not yet finished unbonding; ignored
+        },
+    ];
+
+    for previous_batch in &previous_batches {
+        state
+            .previous_batches
+            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
+            .unwrap();
+    }
+
+    state
+        .pending_batch
+        .save(
+            deps.as_mut().storage,
+            &PendingBatch {
+                id: 4,
+                ustake_to_burn: Uint128::new(56789),
+                est_unbond_start_time: 100000,
+            },
+        )
+        .unwrap();
+
+    // Attempt to withdraw before any batch has completed unbonding. Should error
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(5000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::CantBeZero("withdrawable amount".into()));
+
+    // Attempt to withdraw once batches 1 and 2 have finished unbonding, but 3 has not yet
+    //
+    // Withdrawable from batch 1: 95,197 * 23,456 / 92,876 = 24,042
+    // Withdrawable from batch 2: 35,604
+    // Total withdrawable: 24,042 + 35,604 = 59,646
+    //
+    // Batch 1 should be updated:
+    // Total shares: 92,876 - 23,456 = 69,420
+    // Unclaimed utoken: 95,197 - 24,042 = 71,155
+    //
+    // Batch 2 is completely withdrawn, should be purged from storage
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![Coin::new(59646, CONTRACT_DENOM)]
+        }))
+    );
+
+    // Previous batches should have been updated
+    let batch = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap();
+    assert_eq!(
+        batch,
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(69420),
+            utoken_unclaimed: Uint128::new(71155),
+            est_unbond_end_time: 10000,
+        }
+    );
+
+    let err = state.previous_batches.load(deps.as_ref().storage, 2u64).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::NotFound {
+            kind: "eris::hub::Batch".to_string()
+        }
+    );
+
+    // User 1's unbond requests in batches 1 and 2 should have been deleted
+    let err1 = state
+        .unbond_requests
+        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
+        .unwrap_err();
+    let err2 = state
+        .unbond_requests
+        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
+        .unwrap_err();
+
+    assert_eq!(
+        err1,
+        StdError::NotFound {
+            kind: "eris::hub::UnbondRequest".to_string()
+        }
+    );
+    assert_eq!(
+        err2,
+        StdError::NotFound {
+            kind: "eris::hub::UnbondRequest".to_string()
+        }
+    );
+
+    // User 3 attempt to withdraw; also specifying a receiver
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("user_3", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: Some("user_2".to_string()),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_2".to_string(),
+            amount: vec![Coin::new(71155, CONTRACT_DENOM)]
+        }))
+    );
+
+    // Batch 1 and user 2's unbonding request should have been purged from storage
+    let err = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::NotFound {
+            kind: "eris::hub::Batch".to_string()
+        }
+    );
+
+    let err = state
+        .unbond_requests
+        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_3")))
+        .unwrap_err();
+
+    assert_eq!(
+        err,
+        StdError::NotFound {
+            kind: "eris::hub::UnbondRequest".to_string()
+        }
+    );
+}
+
+#[test]
+fn withdrawing_unbonded_for_users() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    // batch 1 has finished unbonding, batch 2 has not
+    let unbond_requests = vec![
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(23456),
+        },
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_3"),
+            shares: Uint128::new(69420),
+        },
+        UnbondRequest {
+            id: 2,
+            user: Addr::unchecked("user_2"),
+            shares: Uint128::new(34567),
+        },
+    ];
+
+    for unbond_request in &unbond_requests {
+        state
+            .unbond_requests
+            .save(
+                deps.as_mut().storage,
+                (unbond_request.id, &Addr::unchecked(unbond_request.user.clone())),
+                unbond_request,
+            )
+            .unwrap();
+    }
+
+    let previous_batches = vec![
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(92876),
+            utoken_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
+            est_unbond_end_time: 10000,
+        },
+        Batch {
+            id: 2,
+            reconciled: true,
+            total_shares: Uint128::new(34567),
+            utoken_unclaimed: Uint128::new(35604), // 1.030 Token per Stake
+            est_unbond_end_time: 20000,
+        },
+    ];
+
+    for previous_batch in &previous_batches {
+        state
+            .previous_batches
+            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
+            .unwrap();
+    }
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(15000),
+        mock_info("relayer", &[]),
+        ExecuteMsg::WithdrawUnbondedFor {
+            users: (0..31).map(|i| format!("user_{0}", i)).collect(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::TooManyUsers(30));
+
+    // user_2 has nothing to withdraw yet and is skipped
+    // Withdrawable by user_1: 95,197 * 23,456 / 92,876 = 24,042
+    // Withdrawable by user_3: 95,197 - 24,042 = 71,155
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(15000),
+        mock_info("relayer", &[]),
+        ExecuteMsg::WithdrawUnbondedFor {
+            users: vec!["user_1".to_string(), "user_2".to_string(), "user_3".to_string()],
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_1".to_string(),
+            amount: vec![Coin::new(24042, CONTRACT_DENOM)]
+        }))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_3".to_string(),
+            amount: vec![Coin::new(71155, CONTRACT_DENOM)]
+        }))
+    );
+    assert_eq!(res.events.len(), 2);
+
+    // batch 1 is fully withdrawn and removed from storage
+    let err = state.previous_batches.load(deps.as_ref().storage, 1).unwrap_err();
+    assert_eq!(
+        err,
+        StdError::NotFound {
+            kind: "eris::hub::Batch".to_string()
+        }
+    );
+
+    let requests = state
+        .unbond_requests
+        .range(deps.as_ref().storage, None, None, Order::Ascending)
+        .map(|item| item.unwrap().1)
+        .collect::<Vec<_>>();
+    assert_eq!(requests, vec![unbond_requests[2].clone()]);
+
+    // once batch 2 has finished unbonding, user_2 is paid out as well
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(25000),
+        mock_info("relayer", &[]),
+        ExecuteMsg::WithdrawUnbondedFor {
+            users: vec!["user_2".to_string(), "user_2".to_string()],
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user_2".to_string(),
+            amount: vec![Coin::new(35604, CONTRACT_DENOM)]
+        }))
+    );
+}
+
+#[test]
+fn pruning_batches() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let update_config = |dust_cleanup: DustCleanupConfig| ExecuteMsg::UpdateConfig {
+        protocol_fee_contract: None,
+        allow_donations: None,
+        vote_operator: None,
+        liquidity_buffer: None,
+        remove_slashed_validators: None,
+        insert_swap_routes: None,
+        delete_swap_routes: None,
+        validator_checks: None,
+        guardian: None,
+        fee_receivers: None,
+        auto_maintenance: None,
+        keeper_reward: None,
+        prop_gauges: None,
+        dust_cleanup: Some(dust_cleanup),
+        split_bond: None,
+        remove_liquidity_buffer: None,
+        protocol_reward_fee: None,
+        delegation_strategy: None,
+        epoch_period: None,
+        unbond_period: None,
+    };
+    let prune_batches = ExecuteMsg::PruneBatches {
+        start_after: None,
+        limit: None,
+    };
+
+    // Nothing is pruned without a dust cleanup config
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), prune_batches.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::DustCleanupNotConfigured {});
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        update_config(DustCleanupConfig {
+            threshold: Uint128::new(100),
+            grace_period: 0,
+        }),
+    )
+    .unwrap();
+    let err = execute(deps.as_mut(), mock_env(), mock_info("anyone", &[]), prune_batches.clone())
+        .unwrap_err();
+    assert_eq!(err, ContractError::DustCleanupNotConfigured {});
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        update_config(DustCleanupConfig {
+            threshold: Uint128::new(100),
+            grace_period: 1000,
+        }),
+    )
+    .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(
+        res.dust_cleanup,
+        Some(DustCleanupConfig {
+            threshold: Uint128::new(100),
+            grace_period: 1000,
+        })
+    );
+
+    // - batch 1 has a dust request besides a regular one
+    // - batch 2 only has a dust request left
+    // - batch 3 has a dust request, but finished unbonding within the grace period
+    // - batch 4 is not reconciled yet
+    let unbond_requests = vec![
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_1"),
+            shares: Uint128::new(10000),
+        },
+        UnbondRequest {
+            id: 1,
+            user: Addr::unchecked("user_2"),
+            shares: Uint128::new(50),
+        },
+        UnbondRequest {
+            id: 2,
+            user: Addr::unchecked("user_3"),
+            shares: Uint128::new(30),
+        },
+        UnbondRequest {
+            id: 3,
+            user: Addr::unchecked("user_4"),
+            shares: Uint128::new(20),
+        },
+        UnbondRequest {
+            id: 4,
+            user: Addr::unchecked("user_5"),
+            shares: Uint128::new(10),
+        },
+    ];
+
+    for unbond_request in &unbond_requests {
+        state
+            .unbond_requests
+            .save(
+                deps.as_mut().storage,
+                (unbond_request.id, &Addr::unchecked(unbond_request.user.clone())),
+                unbond_request,
+            )
+            .unwrap();
+    }
+
+    let previous_batches = vec![
         Batch {
             id: 1,
             reconciled: true,
-            total_shares: Uint128::new(92876),
-            uluna_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
+            total_shares: Uint128::new(10050),
+            utoken_unclaimed: Uint128::new(10300),
+            est_unbond_end_time: 10000,
+        },
+        Batch {
+            id: 2,
+            reconciled: true,
+            total_shares: Uint128::new(30),
+            utoken_unclaimed: Uint128::new(31),
             est_unbond_end_time: 10000,
         },
-        Batch {
-            id: 2,
-            reconciled: false,
-            total_shares: Uint128::new(1345),
-            uluna_unclaimed: Uint128::new(1385), // 1.030 Token per Stake
-            est_unbond_end_time: 20000,
+        Batch {
+            id: 3,
+            reconciled: true,
+            total_shares: Uint128::new(20),
+            utoken_unclaimed: Uint128::new(21),
+            est_unbond_end_time: 14500,
+        },
+        Batch {
+            id: 4,
+            reconciled: false,
+            total_shares: Uint128::new(10),
+            utoken_unclaimed: Uint128::new(10),
+            est_unbond_end_time: 10000,
+        },
+    ];
+
+    for previous_batch in &previous_batches {
+        state
+            .previous_batches
+            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
+            .unwrap();
+    }
+
+    // The entries are not counted by the state query, but once by the migration
+    let res: StateResponse = query_helper_env(deps.as_ref(), QueryMsg::State {}, 15000);
+    assert_eq!(res.storage_metrics, StorageMetrics::default());
+
+    migrate(deps.as_mut(), mock_env(), Empty {}).unwrap();
+
+    let res: StateResponse = query_helper_env(deps.as_ref(), QueryMsg::State {}, 15000);
+    assert_eq!(
+        res.storage_metrics,
+        StorageMetrics {
+            previous_batches: 4,
+            unbond_requests: 5,
+        }
+    );
+
+    // Dust of user_2: 10,300 * 50 / 10,050 = 51
+    // Dust of user_3: 31
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(15000),
+        mock_info("anyone", &[]),
+        prune_batches,
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "fee".to_string(),
+            amount: vec![Coin::new(82, CONTRACT_DENOM)]
+        }))
+    );
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/batches_pruned")
+            .add_attribute("batches_removed", "2")
+            .add_attribute("requests_removed", "2")
+            .add_attribute("utoken_swept", "82")]
+    );
+
+    let batch = state.previous_batches.load(deps.as_ref().storage, 1).unwrap();
+    assert_eq!(
+        batch,
+        Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(10000),
+            utoken_unclaimed: Uint128::new(10249),
+            est_unbond_end_time: 10000,
+        }
+    );
+
+    let res: StateResponse = query_helper_env(deps.as_ref(), QueryMsg::State {}, 15000);
+    assert_eq!(
+        res.storage_metrics,
+        StorageMetrics {
+            previous_batches: 3,
+            unbond_requests: 3,
+        }
+    );
+
+    // batch 1 is skipped, batch 3 is past the grace period now
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(16000),
+        mock_info("anyone", &[]),
+        ExecuteMsg::PruneBatches {
+            start_after: Some(1),
+            limit: Some(1),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: "fee".to_string(),
+            amount: vec![Coin::new(21, CONTRACT_DENOM)]
+        }))
+    );
+
+    let batches = state
+        .previous_batches
+        .keys(deps.as_ref().storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()
+        .unwrap();
+    assert_eq!(batches, vec![1, 4]);
+}
+
+#[test]
+fn tracking_user_history() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::TrackHistory {
+            enabled: true,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/history_tracking_updated")
+            .add_attribute("user", "user_1")
+            .add_attribute("enabled", "true")]
+    );
+
+    deps.querier.set_bank_balances(&[coin(12345, CONTRACT_DENOM)]);
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("user_2", &[Coin::new(12345, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: Some("user_1".to_string()),
+        },
+    )
+    .unwrap();
+
+    // user_2 did not opt in
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("user_2", &[Coin::new(12345, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    state
+        .previous_batches
+        .save(
+            deps.as_mut().storage,
+            1,
+            &Batch {
+                id: 1,
+                reconciled: true,
+                total_shares: Uint128::new(20000),
+                utoken_unclaimed: Uint128::new(20600),
+                est_unbond_end_time: 15000,
+            },
+        )
+        .unwrap();
+    state
+        .unbond_requests
+        .save(
+            deps.as_mut().storage,
+            (1, &Addr::unchecked("user_1")),
+            &UnbondRequest {
+                id: 1,
+                user: Addr::unchecked("user_1"),
+                shares: Uint128::new(20000),
+            },
+        )
+        .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::WithdrawUnbonded {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(30000),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(10000),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(40000),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CancelUnbond {
+            batch_id: 1,
         },
-        Batch {
+    )
+    .unwrap();
+
+    let history = vec![
+        UserHistoryItem {
             id: 3,
-            reconciled: false,
-            total_shares: Uint128::new(1456),
-            uluna_unclaimed: Uint128::new(1506), // 1.035 Token per Stake
-            est_unbond_end_time: 30000,
+            action: UserHistoryAction::CancelUnbond,
+            utoken: Uint128::new(10250),
+            ustake: Uint128::new(10000),
+            exchange_rate: Decimal::from_ratio(1025u128, 1000u128),
+            time: 40000,
         },
-        Batch {
-            id: 4,
-            reconciled: false,
-            total_shares: Uint128::new(1),
-            uluna_unclaimed: Uint128::new(1),
-            est_unbond_end_time: 30001,
+        UserHistoryItem {
+            id: 2,
+            action: UserHistoryAction::Unbond,
+            utoken: Uint128::new(10250),
+            ustake: Uint128::new(10000),
+            exchange_rate: Decimal::from_ratio(1025u128, 1000u128),
+            time: 30000,
+        },
+        UserHistoryItem {
+            id: 1,
+            action: UserHistoryAction::Withdraw,
+            utoken: Uint128::new(20600),
+            ustake: Uint128::new(20000),
+            exchange_rate: Decimal::from_ratio(103u128, 100u128),
+            time: 20000,
+        },
+        UserHistoryItem {
+            id: 0,
+            action: UserHistoryAction::Bond,
+            utoken: Uint128::new(12345),
+            ustake: Uint128::new(12043),
+            exchange_rate: Decimal::from_ratio(1025u128, 1000u128),
+            time: 10000,
         },
     ];
-    for previous_batch in &previous_batches {
-        state
-            .previous_batches
-            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
+
+    let res: Vec<UserHistoryItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UserHistory {
+            user: "user_1".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, history);
+
+    let res: Vec<UserHistoryItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UserHistory {
+            user: "user_1".to_string(),
+            start_after: Some(2),
+            limit: Some(1),
+        },
+    );
+    assert_eq!(res, vec![history[2].clone()]);
+
+    let res: Vec<UserHistoryItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UserHistory {
+            user: "user_2".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, vec![]);
+
+    // after opting out, the history is kept but not extended
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::TrackHistory {
+            enabled: false,
+        },
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(40000),
+        mock_info("user_1", &[Coin::new(12345, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    let res: Vec<UserHistoryItem> = query_helper(
+        deps.as_ref(),
+        QueryMsg::UserHistory {
+            user: "user_1".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(res, history);
+}
+
+#[test]
+fn adding_validator() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "alice".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::ValidatorAlreadyWhitelisted("alice".into()));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 0);
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        validators,
+        vec![
+            String::from("alice"),
+            String::from("bob"),
+            String::from("charlie"),
+            String::from("dave")
+        ],
+    );
+}
+
+#[test]
+fn removing_validator() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 341667),
+        Delegation::new("bob", 341667),
+        Delegation::new("charlie", 341666),
+    ]);
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::RemoveValidator {
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::RemoveValidator {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap_err();
+
+    assert_eq!(err, ContractError::ValidatorNotWhitelisted("dave".into()));
+
+    // Target: (341667 + 341667 + 341666) / 2 = 512500
+    // Remainder: 0
+    // Alice:   512500 + 0 - 341667 = 170833
+    // Bob:     512500 + 0 - 341667 = 170833
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::RemoveValidator {
+            validator: "charlie".to_string(),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 3);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Redelegation::new("charlie", "alice", 170833).to_cosmos_msg(CONTRACT_DENOM)),
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(Redelegation::new("charlie", "bob", 170833).to_cosmos_msg(CONTRACT_DENOM)),
+    );
+    assert_eq!(res.messages[2], check_received_coin(0));
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(validators, vec![String::from("alice"), String::from("bob")],);
+}
+
+#[test]
+fn checking_validator_eligibility() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    let validator = |address: &str, commission: u64| Validator {
+        address: address.to_string(),
+        commission: Decimal::percent(commission),
+        max_commission: Decimal::percent(20),
+        max_change_rate: Decimal::percent(1),
+    };
+
+    // charlie is jailed, so he is not part of the active set
+    deps.querier.set_staking_validators(&[
+        validator("alice", 5),
+        validator("bob", 15),
+        validator("dave", 5),
+        validator("erin", 11),
+    ]);
+    deps.querier.set_cw20_total_supply(STAKE_DENOM, 1000000);
+
+    let update_config = |validator_checks: ValidatorChecks| ExecuteMsg::UpdateConfig {
+        protocol_fee_contract: None,
+        allow_donations: None,
+        vote_operator: None,
+        liquidity_buffer: None,
+        remove_slashed_validators: None,
+        insert_swap_routes: None,
+        delete_swap_routes: None,
+        validator_checks: Some(validator_checks),
+        guardian: None,
+        fee_receivers: None,
+        auto_maintenance: None,
+        keeper_reward: None,
+        prop_gauges: None,
+        dust_cleanup: None,
+        split_bond: None,
+        remove_liquidity_buffer: None,
+        protocol_reward_fee: None,
+        delegation_strategy: None,
+        epoch_period: None,
+        unbond_period: None,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        update_config(ValidatorChecks {
+            max_commission: Decimal::percent(101),
+            min_self_delegation: Uint128::zero(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ShareTooHigh("max_commission".into()));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        update_config(ValidatorChecks {
+            max_commission: Decimal::percent(10),
+            min_self_delegation: Uint128::zero(),
+        }),
+    )
+    .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(
+        res.validator_checks,
+        Some(ValidatorChecks {
+            max_commission: Decimal::percent(10),
+            min_self_delegation: Uint128::zero(),
+        })
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "erin".to_string(),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidatorNotEligible("erin".into(), "commission 0.11 above 0.1".into())
+    );
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: "dave".to_string(),
+        },
+    )
+    .unwrap();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 300),
+        Delegation::new("bob", 100),
+        Delegation::new("charlie", 50),
+        Delegation::new("dave", 200),
+    ]);
+
+    // bob is dropped from the goal and his delegation is moved away
+    // Bob: (300 + 200 + 100) / 2 = 300 -> dave receives 100
+    // charlie is inactive, he is left out of the goal but keeps his delegation
+    let res =
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::TuneDelegations {})
             .unwrap();
-    }
+
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Redelegation::new("bob", "dave", 100).to_cosmos_msg(CONTRACT_DENOM)),
+    );
+    assert_eq!(res.messages[1], check_received_coin(0));
+    assert_eq!(
+        res.attributes,
+        vec![
+            attr("action", "erishub/tune_delegations"),
+            attr("goal_delegation", "alice=0.5"),
+            attr("goal_delegation", "dave=0.5"),
+            attr("ineligible_validator", "bob=commission 0.15 above 0.1"),
+            attr("inactive_validator", "charlie"),
+        ]
+    );
+
+    let goal = state.delegation_goal.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        goal.shares,
+        vec![
+            ("alice".to_string(), Decimal::percent(50)),
+            ("dave".to_string(), Decimal::percent(50))
+        ]
+    );
+
+    // The result of the checks is stored, other operations do not query the validators
+    assert_eq!(state.ineligible_validators.load(deps.as_ref().storage).unwrap(), vec!["bob"]);
+    assert_eq!(state.inactive_validators.load(deps.as_ref().storage).unwrap(), vec!["charlie"]);
+
+    // bob and charlie have the smallest delegations, but they are not eligible
     state
         .unlocked_coins
-        .save(
-            deps.as_mut().storage,
-            &vec![
-                Coin::new(10000, CONTRACT_DENOM),
-                Coin::new(234, "ukrw"),
-                Coin::new(345, "uusd"),
-                Coin::new(
-                    69420,
-                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
-                ),
-            ],
-        )
+        .save(deps.as_mut().storage, &vec![Coin::new(100, CONTRACT_DENOM)])
         .unwrap();
-    deps.querier.set_bank_balances(&[
-        Coin::new(12345, CONTRACT_DENOM),
-        Coin::new(234, "ukrw"),
-        Coin::new(345, "uusd"),
-        Coin::new(69420, "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"),
-    ]);
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(MOCK_CONTRACT_ADDR, &[]),
+        ExecuteMsg::Callback(CallbackMsg::Reinvest {}),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Delegation::new("dave", 99).to_cosmos_msg(CONTRACT_DENOM))
+    );
+
+    // validator operators need to self-delegate enough
+    let valoper = "terravaloper1qyqszqgpqyqszqgpqyqszqgpqyqszqgp5chrl0";
+    let operator = "terra1qyqszqgpqyqszqgpqyqszqgpqyqszqgp5hm70u";
+    deps.querier.set_staking_validators(&[validator(valoper, 5)]);
+    deps.querier.set_delegations_of(operator, &[Delegation::new(valoper, 500)]);
+
     execute(
         deps.as_mut(),
-        mock_env_at_timestamp(35000),
-        mock_info("worker", &[]),
-        ExecuteMsg::Reconcile {},
+        mock_env(),
+        mock_info("owner", &[]),
+        update_config(ValidatorChecks {
+            max_commission: Decimal::percent(10),
+            min_self_delegation: Uint128::new(1000),
+        }),
     )
     .unwrap();
-}
 
-#[test]
-fn reconciling_underflow_second() {
-    let mut deps = setup_test();
-    let state = State::default();
-    let previous_batches = vec![
-        Batch {
-            id: 1,
-            reconciled: true,
-            total_shares: Uint128::new(92876),
-            uluna_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
-            est_unbond_end_time: 10000,
-        },
-        Batch {
-            id: 2,
-            reconciled: false,
-            total_shares: Uint128::new(1345),
-            uluna_unclaimed: Uint128::new(1385), // 1.030 Token per Stake
-            est_unbond_end_time: 20000,
-        },
-        Batch {
-            id: 3,
-            reconciled: false,
-            total_shares: Uint128::new(176),
-            uluna_unclaimed: Uint128::new(183), // 1.035 Token per Stake
-            est_unbond_end_time: 30000,
-        },
-        Batch {
-            id: 4,
-            reconciled: false,
-            total_shares: Uint128::new(1),
-            uluna_unclaimed: Uint128::new(1),
-            est_unbond_end_time: 30001,
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: valoper.to_string(),
         },
-    ];
-    for previous_batch in &previous_batches {
-        state
-            .previous_batches
-            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
-            .unwrap();
-    }
-    state
-        .unlocked_coins
-        .save(
-            deps.as_mut().storage,
-            &vec![
-                Coin::new(10000, CONTRACT_DENOM),
-                Coin::new(234, "ukrw"),
-                Coin::new(345, "uusd"),
-                Coin::new(
-                    69420,
-                    "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B",
-                ),
-            ],
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidatorNotEligible(
+            valoper.into(),
+            "self-delegation 500 below 1000".into()
         )
-        .unwrap();
-    deps.querier.set_bank_balances(&[
-        Coin::new(12345 - 1323, CONTRACT_DENOM),
-        Coin::new(234, "ukrw"),
-        Coin::new(345, "uusd"),
-        Coin::new(69420, "ibc/0471F1C4E7AFD3F07702BEF6DC365268D64570F7C1FDC98EA6098DD6DE59817B"),
-    ]);
+    );
+
+    deps.querier.set_delegations_of(operator, &[Delegation::new(valoper, 1000)]);
     execute(
         deps.as_mut(),
-        mock_env_at_timestamp(35000),
-        mock_info("worker", &[]),
-        ExecuteMsg::Reconcile {},
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::AddValidator {
+            validator: valoper.to_string(),
+        },
     )
     .unwrap();
+
+    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    assert_eq!(validators, vec!["alice", "bob", "charlie", "dave", valoper]);
 }
 
 #[test]
-fn withdrawing_unbonded() {
+fn defining_delegation_shares() {
     let mut deps = setup_test();
     let state = State::default();
 
-    // We simulate a most general case:
-    // - batches 1 and 2 have finished unbonding
-    // - batch 3 have been submitted for unbonding but have not finished
-    // - batch 4 is still pending
-    let unbond_requests = vec![
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(23456),
-        },
-        UnbondRequest {
-            id: 1,
-            user: Addr::unchecked("user_3"),
-            shares: Uint128::new(69420),
-        },
-        UnbondRequest {
-            id: 2,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(34567),
-        },
-        UnbondRequest {
-            id: 3,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(45678),
-        },
-        UnbondRequest {
-            id: 4,
-            user: Addr::unchecked("user_1"),
-            shares: Uint128::new(56789),
-        },
-    ];
-
-    for unbond_request in &unbond_requests {
-        state
-            .unbond_requests
-            .save(
-                deps.as_mut().storage,
-                (unbond_request.id, &Addr::unchecked(unbond_request.user.clone())),
-                unbond_request,
-            )
-            .unwrap();
-    }
+    let propose_change =
+        |delegation_strategy: DelegationStrategy| ExecuteMsg::ProposeConfigChange {
+            protocol_reward_fee: None,
+            delegation_strategy: Some(delegation_strategy),
+            epoch_period: None,
+            unbond_period: None,
+            config_timelock: None,
+        };
 
-    let previous_batches = vec![
-        Batch {
-            id: 1,
-            reconciled: true,
-            total_shares: Uint128::new(92876),
-            uluna_unclaimed: Uint128::new(95197), // 1.025 Token per Stake
-            est_unbond_end_time: 10000,
-        },
-        Batch {
-            id: 2,
-            reconciled: true,
-            total_shares: Uint128::new(34567),
-            uluna_unclaimed: Uint128::new(35604), // 1.030 Token per Stake
-            est_unbond_end_time: 20000,
-        },
-        Batch {
-            id: 3,
-            reconciled: false, // finished unbonding, but not reconciled; ignored
-            total_shares: Uint128::new(45678),
-            uluna_unclaimed: Uint128::new(47276), // 1.035 Token per Stake
-            est_unbond_end_time: 20000,
-        },
-        Batch {
-            id: 4,
-            reconciled: true,
-            total_shares: Uint128::new(56789),
-            uluna_unclaimed: Uint128::new(59060), // 1.040 Token per Stake
-            est_unbond_end_time: 30000, // reconciled, but not yet finished unbonding; ignored
-        },
-    ];
+    // shares can only be defined for whitelisted validators
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        propose_change(DelegationStrategy::Defined {
+            shares_bps: vec![("alice".to_string(), 5000), ("dave".to_string(), 5000)],
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ValidatorNotWhitelisted("dave".into()));
 
-    for previous_batch in &previous_batches {
-        state
-            .previous_batches
-            .save(deps.as_mut().storage, previous_batch.id, previous_batch)
-            .unwrap();
-    }
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        propose_change(DelegationStrategy::Defined {
+            shares_bps: vec![("alice".to_string(), 5000), ("bob".to_string(), 4000)],
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err("shares need to sum up to 10000 bps, got 9000"))
+    );
 
-    state
-        .pending_batch
-        .save(
-            deps.as_mut().storage,
-            &PendingBatch {
-                id: 4,
-                ustake_to_burn: Uint128::new(56789),
-                est_unbond_start_time: 100000,
-            },
-        )
-        .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        propose_change(DelegationStrategy::Defined {
+            shares_bps: vec![("alice".to_string(), 5000), ("alice".to_string(), 5000)],
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Std(StdError::generic_err("duplicated share of alice")));
 
-    // Attempt to withdraw before any batch has completed unbonding. Should error
+    // the core shares of the hybrid strategy leave a remainder for the gauges
     let err = execute(
         deps.as_mut(),
-        mock_env_at_timestamp(5000),
-        mock_info("user_1", &[]),
-        ExecuteMsg::WithdrawUnbonded {
-            receiver: None,
-        },
+        mock_env(),
+        mock_info("owner", &[]),
+        propose_change(DelegationStrategy::Hybrid {
+            core_shares_bps: vec![("alice".to_string(), 6000), ("bob".to_string(), 5000)],
+            amp_gauges: "amp_gauges".to_string(),
+            emp_gauges: None,
+            amp_factor_bps: 10000,
+            min_delegation_bps: 0,
+            max_delegation_bps: 10000,
+            validator_count: 3,
+        }),
     )
     .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::Std(StdError::generic_err(
+            "shares can't sum up to more than 10000 bps, got 11000"
+        ))
+    );
 
-    assert_eq!(err, ContractError::CantBeZero("withdrawable amount".into()));
-
-    // Attempt to withdraw once batches 1 and 2 have finished unbonding, but 3 has not yet
-    //
-    // Withdrawable from batch 1: 95,197 * 23,456 / 92,876 = 24,042
-    // Withdrawable from batch 2: 35,604
-    // Total withdrawable: 24,042 + 35,604 = 59,646
-    //
-    // Batch 1 should be updated:
-    // Total shares: 92,876 - 23,456 = 69,420
-    // Unclaimed utoken: 95,197 - 24,042 = 71,155
-    //
-    // Batch 2 is completely withdrawn, should be purged from storage
-    let res = execute(
+    execute(
         deps.as_mut(),
-        mock_env_at_timestamp(25000),
-        mock_info("user_1", &[]),
-        ExecuteMsg::WithdrawUnbonded {
-            receiver: None,
-        },
+        mock_env_at_timestamp(10000),
+        mock_info("owner", &[]),
+        propose_change(DelegationStrategy::Defined {
+            shares_bps: vec![("alice".to_string(), 6000), ("bob".to_string(), 4000)],
+        }),
     )
     .unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::ApplyConfigChange {})
+        .unwrap();
 
-    assert_eq!(res.messages.len(), 1);
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
     assert_eq!(
-        res.messages[0],
-        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-            to_address: "user_1".to_string(),
-            amount: vec![Coin::new(59646, CONTRACT_DENOM)]
-        }))
+        res.delegation_strategy,
+        DelegationStrategy::Defined {
+            shares_bps: vec![("alice".to_string(), 6000), ("bob".to_string(), 4000)],
+        }
     );
 
-    // Previous batches should have been updated
-    let batch = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap();
+    // charlie has no defined share, so his delegation is moved to alice and bob
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 300),
+        Delegation::new("bob", 200),
+        Delegation::new("charlie", 500),
+    ]);
+
+    let res =
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), ExecuteMsg::TuneDelegations {})
+            .unwrap();
     assert_eq!(
-        batch,
-        Batch {
-            id: 1,
-            reconciled: true,
-            total_shares: Uint128::new(69420),
-            uluna_unclaimed: Uint128::new(71155),
-            est_unbond_end_time: 10000,
-        }
+        res.attributes[0..3],
+        vec![
+            attr("action", "erishub/tune_delegations"),
+            attr("goal_delegation", "alice=0.6"),
+            attr("goal_delegation", "bob=0.4"),
+        ]
     );
 
-    let err = state.previous_batches.load(deps.as_ref().storage, 2u64).unwrap_err();
+    let goal = state.delegation_goal.load(deps.as_ref().storage).unwrap();
     assert_eq!(
-        err,
-        StdError::NotFound {
-            kind: "eris::hub::Batch".to_string()
-        }
+        goal.shares,
+        vec![
+            ("alice".to_string(), Decimal::percent(60)),
+            ("bob".to_string(), Decimal::percent(40))
+        ]
     );
+}
 
-    // User 1's unbond requests in batches 1 and 2 should have been deleted
-    let err1 = state
-        .unbond_requests
-        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
-        .unwrap_err();
-    let err2 = state
-        .unbond_requests
-        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_1")))
-        .unwrap_err();
+#[test]
+fn pausing_operations() {
+    let mut deps = setup_test();
 
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::Pause {
+            operations: vec![PausableOperation::Bond],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoGuardianSet {});
+
+    // The owner can pause without a guardian
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::Pause {
+            operations: vec![PausableOperation::SubmitBatch],
+        },
+    )
+    .unwrap();
     assert_eq!(
-        err1,
-        StdError::NotFound {
-            kind: "eris::hub::UnbondRequest".to_string()
-        }
-    );
-    assert_eq!(
-        err2,
-        StdError::NotFound {
-            kind: "eris::hub::UnbondRequest".to_string()
-        }
+        res.events,
+        vec![Event::new("erishub/paused").add_attribute("operation", "submit_batch")]
     );
 
-    // User 3 attempt to withdraw; also specifying a receiver
-    let res = execute(
+    execute(
         deps.as_mut(),
-        mock_env_at_timestamp(25000),
-        mock_info("user_3", &[]),
-        ExecuteMsg::WithdrawUnbonded {
-            receiver: Some("user_2".to_string()),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            protocol_fee_contract: None,
+            allow_donations: None,
+            vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: Some("guardian".to_string()),
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: None,
+            unbond_period: None,
         },
     )
     .unwrap();
 
-    assert_eq!(res.messages.len(), 1);
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::Pause {
+            operations: vec![PausableOperation::Bond],
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::UnauthorizedSenderNotGuardian {});
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::Pause {
+            operations: vec![
+                PausableOperation::Bond,
+                PausableOperation::QueueUnbond,
+                PausableOperation::Harvest,
+                PausableOperation::Rebalance,
+                PausableOperation::PruneBatches,
+            ],
+        },
+    )
+    .unwrap();
     assert_eq!(
-        res.messages[0],
-        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-            to_address: "user_2".to_string(),
-            amount: vec![Coin::new(71155, CONTRACT_DENOM)]
-        }))
+        res.events,
+        vec![Event::new("erishub/paused")
+            .add_attribute("operation", "bond")
+            .add_attribute("operation", "queue_unbond")
+            .add_attribute("operation", "harvest")
+            .add_attribute("operation", "rebalance")
+            .add_attribute("operation", "prune_batches")]
     );
 
-    // Batch 1 and user 2's unbonding request should have been purged from storage
-    let err = state.previous_batches.load(deps.as_ref().storage, 1u64).unwrap_err();
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.guardian, Some("guardian".to_string()));
     assert_eq!(
-        err,
-        StdError::NotFound {
-            kind: "eris::hub::Batch".to_string()
+        res.pause_flags,
+        PauseFlags {
+            bond: true,
+            queue_unbond: true,
+            harvest: true,
+            rebalance: true,
+            submit_batch: true,
+            prune_batches: true,
         }
     );
 
-    let err = state
-        .unbond_requests
-        .load(deps.as_ref().storage, (1u64, &Addr::unchecked("user_3")))
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000000, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OperationPaused(PausableOperation::Bond));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000000, CONTRACT_DENOM)]),
+        ExecuteMsg::Donate {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OperationPaused(PausableOperation::Bond));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(1000),
+            msg: to_binary(&ReceiveMsg::QueueUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OperationPaused(PausableOperation::QueueUnbond));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(STAKE_DENOM, &[]),
+        ExecuteMsg::Receive(cw20::Cw20ReceiveMsg {
+            sender: "user_1".to_string(),
+            amount: Uint128::new(1000),
+            msg: to_binary(&ReceiveMsg::InstantUnbond {
+                receiver: None,
+            })
+            .unwrap(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OperationPaused(PausableOperation::QueueUnbond));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::CancelUnbond {
+            batch_id: 1,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OperationPaused(PausableOperation::QueueUnbond));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[]),
+        ExecuteMsg::TransferUnbondRequest {
+            batch_id: 1,
+            recipient: "user_2".to_string(),
+            shares: Uint128::new(1000),
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OperationPaused(PausableOperation::QueueUnbond));
+
+    let err = execute(deps.as_mut(), mock_env(), mock_info("jake", &[]), ExecuteMsg::Harvest {})
         .unwrap_err();
+    assert_eq!(err, ContractError::OperationPaused(PausableOperation::Harvest));
 
-    assert_eq!(
-        err,
-        StdError::NotFound {
-            kind: "eris::hub::UnbondRequest".to_string()
-        }
-    );
-}
+    let err =
+        execute(deps.as_mut(), mock_env(), mock_info("jake", &[]), ExecuteMsg::SubmitBatch {})
+            .unwrap_err();
+    assert_eq!(err, ContractError::OperationPaused(PausableOperation::SubmitBatch));
 
-#[test]
-fn adding_validator() {
-    let mut deps = setup_test();
-    let state = State::default();
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        ExecuteMsg::PruneBatches {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OperationPaused(PausableOperation::PruneBatches));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::Rebalance {
+            min_redelegation: None,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::OperationPaused(PausableOperation::Rebalance));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::Unpause {
+            operations: vec![PausableOperation::Bond],
+        },
+    )
+    .unwrap();
+
+    deps.querier.set_bank_balances(&[coin(1000000, CONTRACT_DENOM)]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("user_1", &[Coin::new(1000000, CONTRACT_DENOM)]),
+        ExecuteMsg::Bond {
+            receiver: None,
+        },
+    )
+    .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(
+        res.pause_flags,
+        PauseFlags {
+            bond: false,
+            queue_unbond: true,
+            harvest: true,
+            rebalance: true,
+            submit_batch: true,
+            prune_batches: true,
+        }
+    );
 
-    let err = execute(
+    // An empty string removes the guardian, only the owner can unpause afterwards
+    execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("jake", &[]),
-        ExecuteMsg::AddValidator {
-            validator: "dave".to_string(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            protocol_fee_contract: None,
+            allow_donations: None,
+            vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: Some("".to_string()),
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: None,
+            unbond_period: None,
         },
     )
-    .unwrap_err();
+    .unwrap();
 
-    assert_eq!(err, ContractError::Unauthorized {});
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.guardian, None);
 
     let err = execute(
         deps.as_mut(),
         mock_env(),
-        mock_info("owner", &[]),
-        ExecuteMsg::AddValidator {
-            validator: "alice".to_string(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::Unpause {
+            operations: vec![PausableOperation::Harvest],
         },
     )
     .unwrap_err();
+    assert_eq!(err, ContractError::NoGuardianSet {});
 
-    assert_eq!(err, ContractError::ValidatorAlreadyWhitelisted("alice".into()));
-
-    let res = execute(
+    execute(
         deps.as_mut(),
         mock_env(),
         mock_info("owner", &[]),
-        ExecuteMsg::AddValidator {
-            validator: "dave".to_string(),
+        ExecuteMsg::Unpause {
+            operations: vec![PausableOperation::Harvest],
         },
     )
     .unwrap();
+}
+
+#[test]
+fn deferring_locked_redelegations() {
+    let mut deps = setup_test();
+    let state = State::default();
+
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 600),
+        Delegation::new("bob", 300),
+        Delegation::new("charlie", 0),
+    ]);
+
+    let rebalance = |deps: &mut OwnedDeps<MockStorage, MockApi, CustomQuerier>, time: u64| {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(time),
+            mock_info("owner", &[]),
+            ExecuteMsg::Rebalance {
+                min_redelegation: None,
+            },
+        )
+        .unwrap()
+    };
+
+    let res = rebalance(&mut deps, 10000);
+    assert_eq!(res.messages.len(), 2);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(Redelegation::new("alice", "charlie", 300).to_cosmos_msg(CONTRACT_DENOM))
+    );
+
+    // charlie received a redelegation, so it is locked until the unbonding period has passed
+    let lock = state.redelegation_locks.load(deps.as_ref().storage, "charlie").unwrap();
+    assert_eq!(lock, 10000 + 1814400);
 
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 300),
+        Delegation::new("bob", 300),
+        Delegation::new("charlie", 600),
+    ]);
+
+    let res = rebalance(&mut deps, 20000);
     assert_eq!(res.messages.len(), 0);
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/rebalanced")
+            .add_attribute("utoken_moved", "0")
+            .add_attribute("deferred", "charlie=200")]
+    );
 
-    let validators = state.validators.load(deps.as_ref().storage).unwrap();
+    // Once the redelegation completed, charlie can be used as source again
+    let res = rebalance(&mut deps, 10000 + 1814400);
+    assert_eq!(res.messages.len(), 3);
     assert_eq!(
-        validators,
-        vec![
-            String::from("alice"),
-            String::from("bob"),
-            String::from("charlie"),
-            String::from("dave")
-        ],
+        res.messages[0],
+        SubMsg::new(Redelegation::new("charlie", "alice", 100).to_cosmos_msg(CONTRACT_DENOM))
+    );
+    assert_eq!(
+        res.messages[1],
+        SubMsg::new(Redelegation::new("charlie", "bob", 100).to_cosmos_msg(CONTRACT_DENOM))
+    );
+
+    // the expired lock of charlie is cleaned up
+    let locks = state
+        .redelegation_locks
+        .range(deps.as_ref().storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()
+        .unwrap();
+    assert_eq!(
+        locks,
+        vec![("alice".to_string(), 10000 + 1814400 * 2), ("bob".to_string(), 10000 + 1814400 * 2)]
     );
 }
 
 #[test]
-fn removing_validator() {
+fn detecting_slashing() {
     let mut deps = setup_test();
     let state = State::default();
 
-    deps.querier.set_staking_delegations(&[
-        Delegation::new("alice", 341667),
-        Delegation::new("bob", 341667),
-        Delegation::new("charlie", 341666),
-    ]);
-
-    let err = execute(
-        deps.as_mut(),
-        mock_env(),
-        mock_info("jake", &[]),
-        ExecuteMsg::RemoveValidator {
-            validator: "charlie".to_string(),
-        },
-    )
-    .unwrap_err();
-
-    assert_eq!(err, ContractError::Unauthorized {});
+    for validator in ["alice", "bob", "charlie"] {
+        state.add_expected_delegation(deps.as_mut().storage, validator, 100000).unwrap();
+    }
 
-    let err = execute(
+    execute(
         deps.as_mut(),
         mock_env(),
         mock_info("owner", &[]),
-        ExecuteMsg::RemoveValidator {
-            validator: "dave".to_string(),
+        ExecuteMsg::UpdateConfig {
+            protocol_fee_contract: None,
+            allow_donations: None,
+            vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: Some(true),
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: None,
+            unbond_period: None,
         },
     )
-    .unwrap_err();
+    .unwrap();
 
-    assert_eq!(err, ContractError::ValidatorNotWhitelisted("dave".into()));
+    // Alice has been slashed by 10%, charlie is missing a rounding error only
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("alice", 90000),
+        Delegation::new("bob", 100000),
+        Delegation::new("charlie", 99999),
+    ]);
 
-    // Target: (341667 + 341667 + 341666) / 2 = 512500
-    // Remainder: 0
-    // Alice:   512500 + 0 - 341667 = 170833
-    // Bob:     512500 + 0 - 341667 = 170833
+    // Target: (90000 + 100000 + 99999) / 2 = 144999
+    // Remainder: 1
+    // Bob:     144999 + 1 - 100000 = 45000
+    // Charlie: 144999 + 0 - 99999 = 45000
     let res = execute(
         deps.as_mut(),
-        mock_env(),
-        mock_info("owner", &[]),
-        ExecuteMsg::RemoveValidator {
-            validator: "charlie".to_string(),
-        },
+        mock_env_at_timestamp(20000),
+        mock_info("worker", &[]),
+        ExecuteMsg::Reconcile {},
     )
     .unwrap();
 
     assert_eq!(res.messages.len(), 3);
     assert_eq!(
         res.messages[0],
-        SubMsg::new(Redelegation::new("charlie", "alice", 170833).to_cosmos_msg()),
+        SubMsg::new(Redelegation::new("alice", "bob", 45000).to_cosmos_msg(CONTRACT_DENOM))
     );
     assert_eq!(
         res.messages[1],
-        SubMsg::new(Redelegation::new("charlie", "bob", 170833).to_cosmos_msg()),
+        SubMsg::new(Redelegation::new("alice", "charlie", 45000).to_cosmos_msg(CONTRACT_DENOM))
     );
     assert_eq!(res.messages[2], check_received_coin(0));
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/slashed")
+            .add_attribute("validator", "alice")
+            .add_attribute("utoken_expected", "100000")
+            .add_attribute("utoken_slashed", "10000")
+            .add_attribute("removed", "true")]
+    );
 
     let validators = state.validators.load(deps.as_ref().storage).unwrap();
-    assert_eq!(validators, vec![String::from("alice"), String::from("bob")],);
+    assert_eq!(validators, vec![String::from("bob"), String::from("charlie")]);
+
+    // The snapshot follows the delegations including the redelegations, alice is pruned
+    let snapshot = state
+        .delegations_snapshot
+        .range(deps.as_ref().storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()
+        .unwrap();
+    assert_eq!(
+        snapshot,
+        vec![
+            ("bob".to_string(), Uint128::new(145000)),
+            ("charlie".to_string(), Uint128::new(144999)),
+        ]
+    );
+
+    let res: Vec<SlashingEvent> = query_helper(
+        deps.as_ref(),
+        QueryMsg::SlashingEvents {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        res,
+        vec![SlashingEvent {
+            id: 1,
+            validator: "alice".to_string(),
+            time: 20000,
+            utoken_expected: Uint128::new(100000),
+            utoken_slashed: Uint128::new(10000),
+            removed: true,
+        }]
+    );
+
+    // Other operations check for slashing as well, but leave the removal to the next reconcile
+    deps.querier.set_staking_delegations(&[
+        Delegation::new("bob", 130000),
+        Delegation::new("charlie", 144999),
+    ]);
+
+    let harvest = |deps: &mut OwnedDeps<MockStorage, MockApi, CustomQuerier>| {
+        execute(
+            deps.as_mut(),
+            mock_env_at_timestamp(30000),
+            mock_info("worker", &[]),
+            ExecuteMsg::Harvest {},
+        )
+        .unwrap()
+    };
+
+    let res = harvest(&mut deps);
+    assert_eq!(res.events, vec![]);
+    assert_eq!(
+        state.delegations_snapshot.load(deps.as_ref().storage, "bob").unwrap(),
+        Uint128::new(145000)
+    );
+
+    state.remove_slashed_validators.save(deps.as_mut().storage, &false).unwrap();
+
+    let res = harvest(&mut deps);
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/slashed")
+            .add_attribute("validator", "bob")
+            .add_attribute("utoken_expected", "145000")
+            .add_attribute("utoken_slashed", "15000")
+            .add_attribute("removed", "false")]
+    );
+    assert_eq!(
+        state.delegations_snapshot.load(deps.as_ref().storage, "bob").unwrap(),
+        Uint128::new(130000)
+    );
 }
 
 #[test]
@@ -1564,66 +5226,289 @@ fn update_fee() {
         }
     );
 
+    let propose_fee = |protocol_reward_fee: Decimal| ExecuteMsg::ProposeConfigChange {
+        protocol_reward_fee: Some(protocol_reward_fee),
+        delegation_strategy: None,
+        epoch_period: None,
+        unbond_period: None,
+        config_timelock: None,
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("jake", &[]),
+        propose_fee(Decimal::from_ratio(10u128, 100u128)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        propose_fee(Decimal::from_ratio(11u128, 100u128)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ProtocolRewardFeeTooHigh {});
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            protocol_fee_contract: Some("fee-new".to_string()),
+            allow_donations: None,
+            vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: None,
+            unbond_period: None,
+        },
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 0);
+
+    // the fee only changes after the timelock of one epoch and unbonding period
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("owner", &[]),
+        propose_fee(Decimal::from_ratio(10u128, 100u128)),
+    )
+    .unwrap();
+
+    let config = state.fee_config.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config,
+        FeeConfig {
+            protocol_fee_contract: Addr::unchecked("fee-new"),
+            protocol_reward_fee: Decimal::from_ratio(1u128, 100u128)
+        }
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(2083599),
+        mock_info("owner", &[]),
+        ExecuteMsg::ApplyConfigChange {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ConfigChangeTimelocked(2083600));
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(2083600),
+        mock_info("owner", &[]),
+        ExecuteMsg::ApplyConfigChange {},
+    )
+    .unwrap();
+
+    let config = state.fee_config.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config,
+        FeeConfig {
+            protocol_fee_contract: Addr::unchecked("fee-new"),
+            protocol_reward_fee: Decimal::from_ratio(10u128, 100u128)
+        }
+    );
+}
+
+#[test]
+fn timelocked_config_change() {
+    let mut deps = setup_test();
+
+    let propose_change =
+        |epoch_period: Option<u64>, unbond_period: Option<u64>, config_timelock: Option<u64>| {
+            ExecuteMsg::ProposeConfigChange {
+                protocol_reward_fee: None,
+                delegation_strategy: None,
+                epoch_period,
+                unbond_period,
+                config_timelock,
+            }
+        };
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("owner", &[]),
+        propose_change(None, None, None),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::EmptyConfigChange {});
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("owner", &[]),
+        propose_change(None, Some(0), None),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::CantBeZero("unbond_period".into()));
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("owner", &[]),
+        ExecuteMsg::ApplyConfigChange {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoPendingConfigChange {});
+
+    // the parameters can't bypass the timelock through `UpdateConfig`
     let err = execute(
         deps.as_mut(),
-        mock_env(),
-        mock_info("jake", &[]),
+        mock_env_at_timestamp(10000),
+        mock_info("owner", &[]),
         ExecuteMsg::UpdateConfig {
             protocol_fee_contract: None,
-            protocol_reward_fee: Some(Decimal::from_ratio(11u128, 100u128)),
-            delegation_strategy: None,
             allow_donations: None,
             vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
             epoch_period: None,
-            unbond_period: None,
+            unbond_period: Some(1209600),
         },
     )
     .unwrap_err();
+    assert_eq!(err, ContractError::TimelockedConfigParam("unbond_period".into()));
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(10000),
+        mock_info("owner", &[]),
+        propose_change(Some(86400), Some(1209600), None),
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/config_change_proposed").add_attribute("apply_after", "2083600")]
+    );
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.config_timelock, 2073600);
+    assert_eq!(
+        res.pending_config_change,
+        Some(PendingConfigChange {
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: Some(86400),
+            unbond_period: Some(1209600),
+            config_timelock: None,
+            apply_after: 2083600,
+        })
+    );
+
+    // the change can be cancelled by the owner during the timelock
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("jake", &[]),
+        ExecuteMsg::CancelConfigChange {},
+    )
+    .unwrap_err();
     assert_eq!(err, ContractError::Unauthorized {});
 
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(20000),
+        mock_info("owner", &[]),
+        ExecuteMsg::CancelConfigChange {},
+    )
+    .unwrap();
+
     let err = execute(
         deps.as_mut(),
-        mock_env(),
+        mock_env_at_timestamp(2083600),
         mock_info("owner", &[]),
-        ExecuteMsg::UpdateConfig {
-            protocol_fee_contract: None,
-            protocol_reward_fee: Some(Decimal::from_ratio(11u128, 100u128)),
-            delegation_strategy: None,
-            allow_donations: None,
-            vote_operator: None,
-            epoch_period: None,
-            unbond_period: None,
-        },
+        ExecuteMsg::ApplyConfigChange {},
     )
     .unwrap_err();
-    assert_eq!(err, ContractError::ProtocolRewardFeeTooHigh {});
+    assert_eq!(err, ContractError::NoPendingConfigChange {});
 
-    let res = execute(
+    // a pending change has to be cancelled before proposing a new one
+    execute(
         deps.as_mut(),
-        mock_env(),
+        mock_env_at_timestamp(20000),
         mock_info("owner", &[]),
-        ExecuteMsg::UpdateConfig {
-            protocol_fee_contract: Some("fee-new".to_string()),
-            protocol_reward_fee: Some(Decimal::from_ratio(10u128, 100u128)),
-            delegation_strategy: None,
-            allow_donations: None,
-            vote_operator: None,
-            epoch_period: None,
-            unbond_period: None,
-        },
+        propose_change(Some(86400), None, None),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(30000),
+        mock_info("owner", &[]),
+        propose_change(Some(86400), Some(1209600), Some(604800)),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ConfigChangePending {});
+
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(30000),
+        mock_info("owner", &[]),
+        ExecuteMsg::CancelConfigChange {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(30000),
+        mock_info("owner", &[]),
+        propose_change(Some(86400), Some(1209600), Some(604800)),
     )
     .unwrap();
 
-    assert_eq!(res.messages.len(), 0);
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(2093600),
+        mock_info("owner", &[]),
+        ExecuteMsg::ApplyConfigChange {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ConfigChangeTimelocked(2103600));
 
-    let config = state.fee_config.load(deps.as_ref().storage).unwrap();
-    assert_eq!(
-        config,
-        FeeConfig {
-            protocol_fee_contract: Addr::unchecked("fee-new"),
-            protocol_reward_fee: Decimal::from_ratio(10u128, 100u128)
-        }
-    );
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(2103600),
+        mock_info("owner", &[]),
+        ExecuteMsg::ApplyConfigChange {},
+    )
+    .unwrap();
+    assert_eq!(res.events, vec![Event::new("erishub/config_change_applied")]);
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.epoch_period, 86400);
+    assert_eq!(res.unbond_period, 1209600);
+    assert_eq!(res.config_timelock, 604800);
+    assert_eq!(res.pending_config_change, None);
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -1651,10 +5536,23 @@ fn vote() {
         mock_info("owner", &[]),
         ExecuteMsg::UpdateConfig {
             protocol_fee_contract: None,
-            protocol_reward_fee: None,
-            delegation_strategy: None,
             allow_donations: None,
             vote_operator: Some("vote_operator".to_string()),
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
             epoch_period: None,
             unbond_period: None,
         },
@@ -1718,10 +5616,23 @@ fn vote_weighted() {
         mock_info("owner", &[]),
         ExecuteMsg::UpdateConfig {
             protocol_fee_contract: None,
-            protocol_reward_fee: None,
-            delegation_strategy: None,
             allow_donations: None,
             vote_operator: Some("vote_operator".to_string()),
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
             epoch_period: None,
             unbond_period: None,
         },
@@ -1792,6 +5703,177 @@ fn vote_weighted() {
         .to_cosmos_msg()
     );
 }
+
+#[test]
+fn vote_from_gauge() {
+    let mut deps = setup_test();
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::VoteFromGauge {
+            proposal_id: 3,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::NoPropGaugesSet {});
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("owner", &[]),
+        ExecuteMsg::UpdateConfig {
+            protocol_fee_contract: None,
+            allow_donations: None,
+            vote_operator: None,
+            liquidity_buffer: None,
+            remove_slashed_validators: None,
+            insert_swap_routes: None,
+            delete_swap_routes: None,
+            validator_checks: None,
+            guardian: None,
+            fee_receivers: None,
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: Some("prop_gauges".to_string()),
+            dust_cleanup: None,
+            split_bond: None,
+            remove_liquidity_buffer: None,
+            protocol_reward_fee: None,
+            delegation_strategy: None,
+            epoch_period: None,
+            unbond_period: None,
+        },
+    )
+    .unwrap();
+
+    let res: ConfigResponse = query_helper(deps.as_ref(), QueryMsg::Config {});
+    assert_eq!(res.prop_gauges, Some("prop_gauges".to_string()));
+
+    let end_time_s = mock_env().block.time.seconds() + 100;
+    deps.querier.prop_gauges_querier.config = Some(PropGaugesConfigResponse {
+        owner: Addr::unchecked("owner"),
+        escrow_addr: Addr::unchecked("escrow"),
+        hub_addr: Addr::unchecked(MOCK_CONTRACT_ADDR),
+        quorum_bps: 1000,
+        use_weighted_vote: false,
+    });
+    deps.querier.prop_gauges_querier.total_vp = Uint128::new(1000);
+    deps.querier.prop_gauges_querier.props.insert(
+        3,
+        PropInfo {
+            period: 10,
+            end_time_s,
+            yes_vp: Uint128::new(80),
+            no_vp: Uint128::new(20),
+            abstain_vp: Uint128::zero(),
+            nwv_vp: Uint128::zero(),
+            total_vp: Uint128::zero(),
+            current_vote: None,
+        },
+    );
+
+    // voting in the prop gauges has not ended yet
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(end_time_s),
+        mock_info("anyone", &[]),
+        ExecuteMsg::VoteFromGauge {
+            proposal_id: 3,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PropVotingNotEnded(3, end_time_s));
+
+    // 10% of the voting power voted, 80% of it yes
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(end_time_s + 1),
+        mock_info("anyone", &[]),
+        ExecuteMsg::VoteFromGauge {
+            proposal_id: 3,
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Gov(GovMsg::Vote {
+            proposal_id: 3,
+            vote: VoteOption::Yes
+        })
+    );
+    assert_eq!(
+        res.events,
+        vec![Event::new("erishub/voted_from_gauge")
+            .add_attribute("prop", "3")
+            .add_attribute("voted_vp", "100")
+            .add_attribute("total_vp", "1000")
+            .add_attribute("vote", "Yes")]
+    );
+
+    deps.querier.prop_gauges_querier.config = Some(PropGaugesConfigResponse {
+        owner: Addr::unchecked("owner"),
+        escrow_addr: Addr::unchecked("escrow"),
+        hub_addr: Addr::unchecked(MOCK_CONTRACT_ADDR),
+        quorum_bps: 2000,
+        use_weighted_vote: true,
+    });
+
+    let err = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(end_time_s + 1),
+        mock_info("anyone", &[]),
+        ExecuteMsg::VoteFromGauge {
+            proposal_id: 3,
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::PropQuorumNotReached(3));
+
+    // with a quorum of 5%, the weighted vote follows the voting power
+    deps.querier.prop_gauges_querier.config = Some(PropGaugesConfigResponse {
+        owner: Addr::unchecked("owner"),
+        escrow_addr: Addr::unchecked("escrow"),
+        hub_addr: Addr::unchecked(MOCK_CONTRACT_ADDR),
+        quorum_bps: 500,
+        use_weighted_vote: true,
+    });
+
+    let res = execute(
+        deps.as_mut(),
+        mock_env_at_timestamp(end_time_s + 1),
+        mock_info("anyone", &[]),
+        ExecuteMsg::VoteFromGauge {
+            proposal_id: 3,
+        },
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0].msg,
+        MsgVoteWeighted {
+            proposal_id: 3,
+            voter: MOCK_CONTRACT_ADDR.into(),
+            options: vec![
+                WeightedVoteOption {
+                    option: proto::VoteOption::VOTE_OPTION_YES.into(),
+                    weight: Decimal::from_str("0.8").unwrap().numerator().to_string(),
+                    special_fields: SpecialFields::default()
+                },
+                WeightedVoteOption {
+                    option: proto::VoteOption::VOTE_OPTION_NO.into(),
+                    weight: Decimal::from_str("0.2").unwrap().numerator().to_string(),
+                    special_fields: SpecialFields::default()
+                },
+            ],
+            special_fields: SpecialFields::default()
+        }
+        .to_cosmos_msg()
+    );
+}
+
 //--------------------------------------------------------------------------------------------------
 // Queries
 //--------------------------------------------------------------------------------------------------
@@ -1805,28 +5887,28 @@ fn querying_previous_batches() {
             id: 1,
             reconciled: false,
             total_shares: Uint128::new(123),
-            uluna_unclaimed: Uint128::new(678),
+            utoken_unclaimed: Uint128::new(678),
             est_unbond_end_time: 10000,
         },
         Batch {
             id: 2,
             reconciled: true,
             total_shares: Uint128::new(234),
-            uluna_unclaimed: Uint128::new(789),
+            utoken_unclaimed: Uint128::new(789),
             est_unbond_end_time: 15000,
         },
         Batch {
             id: 3,
             reconciled: false,
             total_shares: Uint128::new(345),
-            uluna_unclaimed: Uint128::new(890),
+            utoken_unclaimed: Uint128::new(890),
             est_unbond_end_time: 20000,
         },
         Batch {
             id: 4,
             reconciled: true,
             total_shares: Uint128::new(456),
-            uluna_unclaimed: Uint128::new(999),
+            utoken_unclaimed: Uint128::new(999),
             est_unbond_end_time: 25000,
         },
     ];
@@ -2034,14 +6116,14 @@ fn querying_unbond_requests_details() {
             id: 1,
             reconciled: false,
             total_shares: Uint128::new(123),
-            uluna_unclaimed: Uint128::new(678),
+            utoken_unclaimed: Uint128::new(678),
             est_unbond_end_time: 10000,
         },
         Batch {
             id: 2,
             reconciled: false,
             total_shares: Uint128::new(234),
-            uluna_unclaimed: Uint128::new(789),
+            utoken_unclaimed: Uint128::new(789),
             est_unbond_end_time: 15000,
         },
     ];
@@ -2122,6 +6204,7 @@ fn computing_undelegations() -> StdResult<()> {
         Uint128::new(451),
         &current_delegations,
         current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+        &HashSet::new(),
     )
     .unwrap();
     let expected = vec![
@@ -2130,6 +6213,33 @@ fn computing_undelegations() -> StdResult<()> {
         Undelegation::new("charlie", 51),
     ];
     assert_eq!(new_undelegations, expected);
+
+    // Bob has no free unbonding slot, his part is taken from the first validator with enough delegation
+    // Alice:   249 + 151 = 400
+    // Charlie: 51
+    let new_undelegations = compute_undelegations(
+        &state,
+        deps.as_ref().storage,
+        Uint128::new(451),
+        &current_delegations,
+        current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+        &HashSet::from(["bob".to_string()]),
+    )
+    .unwrap();
+    let expected = vec![Undelegation::new("alice", 400), Undelegation::new("charlie", 51)];
+    assert_eq!(new_undelegations, expected);
+
+    // Not enough delegations with free unbonding slots
+    let err = compute_undelegations(
+        &state,
+        deps.as_ref().storage,
+        Uint128::new(451),
+        &current_delegations,
+        current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+        &HashSet::from(["alice".to_string(), "bob".to_string()]),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::ComputeUndelegationsWrong(251));
     Ok(())
 }
 
@@ -2144,7 +6254,7 @@ fn computing_redelegations_for_removal() -> StdResult<()> {
         Delegation::new("dave", 10000),
     ];
     // Suppose Dave will be removed
-    // uluna_per_validator = (13000 + 12000 + 11000 + 10000) / 3 = 15333
+    // utoken_per_validator = (13000 + 12000 + 11000 + 10000) / 3 = 15333
     // remainder = 1
     // to Alice:   15333 + 1 - 13000 = 2334
     // to Bob:     15333 + 0 - 12000 = 3333
@@ -2160,9 +6270,10 @@ fn computing_redelegations_for_removal() -> StdResult<()> {
             deps.as_ref().storage,
             &current_delegations[3],
             &current_delegations[..3],
-            current_delegations[..3].iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations[..3].iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        expected,
+        (expected, vec![]),
     );
     Ok(())
 }
@@ -2178,7 +6289,7 @@ fn computing_redelegations_for_rebalancing() -> StdResult<()> {
         Delegation::new("dave", 40471),
         Delegation::new("evan", 2345),
     ];
-    // uluna_per_validator = (69420 + 88888 + 1234 + 40471 + 2345) / 4 = 40471
+    // utoken_per_validator = (69420 + 88888 + 1234 + 40471 + 2345) / 4 = 40471
     // remainer = 3
     // src_delegations:
     //  - alice:   69420 - (40471 + 3) = 28946
@@ -2212,9 +6323,10 @@ fn computing_redelegations_for_rebalancing() -> StdResult<()> {
             &state,
             deps.as_ref().storage,
             &current_delegations,
-            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        expected,
+        (expected, vec![]),
     );
     Ok(())
 }
@@ -2246,9 +6358,10 @@ fn computing_redelegations_for_rebalancing_complex() -> StdResult<()> {
             &state,
             deps.as_ref().storage,
             &current_delegations,
-            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        vec![],
+        (vec![], vec![]),
     );
     // ratio is bad
     let current_delegations = vec![
@@ -2262,9 +6375,10 @@ fn computing_redelegations_for_rebalancing_complex() -> StdResult<()> {
             &state,
             deps.as_ref().storage,
             &current_delegations,
-            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        vec![Redelegation::new("unlisted", "alice", 25000)],
+        (vec![Redelegation::new("unlisted", "alice", 25000)], vec![]),
     );
     // ratio is bad
     let current_delegations = vec![
@@ -2278,12 +6392,16 @@ fn computing_redelegations_for_rebalancing_complex() -> StdResult<()> {
             &state,
             deps.as_ref().storage,
             &current_delegations,
-            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        vec![
-            Redelegation::new("unlisted", "alice", 25000),
-            Redelegation::new("unlisted", "bob", 25000)
-        ],
+        (
+            vec![
+                Redelegation::new("unlisted", "alice", 25000),
+                Redelegation::new("unlisted", "bob", 25000)
+            ],
+            vec![]
+        ),
     );
     // ratio is bad
     let current_delegations = vec![
@@ -2296,12 +6414,70 @@ fn computing_redelegations_for_rebalancing_complex() -> StdResult<()> {
             &state,
             deps.as_ref().storage,
             &current_delegations,
-            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec()
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &HashSet::new()
         )?,
-        vec![
-            Redelegation::new("charlie", "alice", 27500),
-            Redelegation::new("charlie", "bob", 27500)
-        ],
+        (
+            vec![
+                Redelegation::new("charlie", "alice", 27500),
+                Redelegation::new("charlie", "bob", 27500)
+            ],
+            vec![]
+        ),
+    );
+    Ok(())
+}
+
+#[test]
+fn computing_redelegations_with_locked_validators() -> StdResult<()> {
+    let deps = mock_dependencies();
+    let state = State::default();
+    let current_delegations = vec![
+        Delegation::new("alice", 69420),
+        Delegation::new("bob", 1234),
+        Delegation::new("charlie", 88888),
+        Delegation::new("dave", 40471),
+        Delegation::new("evan", 2345),
+    ];
+    let locked_validators = HashSet::from(["alice".to_string()]);
+
+    // Same as `computing_redelegations_for_rebalancing`, but alice can't be used as source
+    // src_delegations:
+    //  - charlie: 48417
+    // dst_delegations:
+    //  - bob:     39237
+    //  - evan:    38126
+    //
+    // Round 1: charlie --(39237)--> bob
+    // Round 2: charlie --(9180)--> evan
+    assert_eq!(
+        compute_redelegations_for_rebalancing(
+            &state,
+            deps.as_ref().storage,
+            &current_delegations,
+            current_delegations.iter().map(|a| a.validator.to_string()).collect_vec(),
+            &locked_validators
+        )?,
+        (
+            vec![
+                Redelegation::new("charlie", "bob", 39237),
+                Redelegation::new("charlie", "evan", 9180),
+            ],
+            vec![Delegation::new("alice", 28946)]
+        ),
+    );
+
+    // A locked validator can't be removed right away
+    assert_eq!(
+        compute_redelegations_for_removal(
+            &state,
+            deps.as_ref().storage,
+            &current_delegations[0],
+            &current_delegations[1..],
+            current_delegations[1..].iter().map(|a| a.validator.to_string()).collect_vec(),
+            &locked_validators
+        )?,
+        (vec![], vec![Delegation::new("alice", 69420)]),
     );
     Ok(())
 }
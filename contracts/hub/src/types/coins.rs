@@ -22,6 +22,14 @@ impl Coins {
         Ok(())
     }
 
+    pub fn deduct(&mut self, coin_to_deduct: &Coin) -> StdResult<()> {
+        if let Some(coin) = self.0.iter_mut().find(|coin| coin.denom == coin_to_deduct.denom) {
+            coin.amount = coin.amount.checked_sub(coin_to_deduct.amount)?;
+        }
+        self.0.retain(|coin| !coin.amount.is_zero());
+        Ok(())
+    }
+
     pub fn find(&self, denom: &str) -> Coin {
         self.0
             .iter()
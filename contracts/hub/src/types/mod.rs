@@ -1,8 +1,10 @@
 mod coins;
 pub mod gauges;
 mod keys;
+mod stake_token;
 mod staking;
 
 pub use coins::Coins;
 pub use keys::BooleanKey;
+pub use stake_token::StakeToken;
 pub use staking::{Delegation, Redelegation, SendFee, Undelegation};
@@ -0,0 +1,101 @@
+use std::fmt;
+
+use cosmwasm_std::{
+    to_binary, Addr, BankMsg, Coin, CosmosMsg, QuerierWrapper, StdResult, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+
+use crate::helpers::query_cw20_total_supply;
+use crate::protos::tokenfactory;
+
+/// The liquid staking token issued by the hub
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq, Eq))]
+pub enum StakeToken {
+    /// CW20 token instantiated by the hub
+    Cw20(Addr),
+    /// Native token factory denom created by the hub
+    TokenFactory(String),
+}
+
+impl StakeToken {
+    pub fn query_supply(&self, querier: &QuerierWrapper) -> StdResult<Uint128> {
+        match self {
+            StakeToken::Cw20(token_addr) => query_cw20_total_supply(querier, token_addr),
+            StakeToken::TokenFactory(denom) => Ok(querier.query_supply(denom)?.amount),
+        }
+    }
+
+    pub fn mint_msgs(
+        &self,
+        hub: &Addr,
+        recipient: &Addr,
+        amount: Uint128,
+    ) -> StdResult<Vec<CosmosMsg>> {
+        match self {
+            StakeToken::Cw20(token_addr) => Ok(vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token_addr.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: recipient.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            })]),
+            StakeToken::TokenFactory(denom) => {
+                // minting to another address is not supported by all token factory versions,
+                // so the hub mints to itself and forwards the tokens
+                let coin = Coin::new(amount.u128(), denom);
+                Ok(vec![
+                    tokenfactory::mint_msg(hub.as_str(), &coin)?,
+                    CosmosMsg::Bank(BankMsg::Send {
+                        to_address: recipient.to_string(),
+                        amount: vec![coin],
+                    }),
+                ])
+            },
+        }
+    }
+
+    /// Sends stake tokens held by the hub to the recipient
+    pub fn transfer_msg(&self, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+        match self {
+            StakeToken::Cw20(token_addr) => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token_addr.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount,
+                })?,
+                funds: vec![],
+            })),
+            StakeToken::TokenFactory(denom) => Ok(CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient.to_string(),
+                amount: vec![Coin::new(amount.u128(), denom)],
+            })),
+        }
+    }
+
+    /// Burns stake tokens held by the hub
+    pub fn burn_msg(&self, hub: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+        match self {
+            StakeToken::Cw20(token_addr) => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token_addr.to_string(),
+                msg: to_binary(&Cw20ExecuteMsg::Burn {
+                    amount,
+                })?,
+                funds: vec![],
+            })),
+            StakeToken::TokenFactory(denom) => {
+                tokenfactory::burn_msg(hub.as_str(), &Coin::new(amount.u128(), denom))
+            },
+        }
+    }
+}
+
+impl fmt::Display for StakeToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StakeToken::Cw20(token_addr) => write!(f, "{}", token_addr),
+            StakeToken::TokenFactory(denom) => write!(f, "{}", denom),
+        }
+    }
+}
@@ -1,6 +1,4 @@
-use cosmwasm_std::{Addr, BankMsg, Coin, CosmosMsg, StakingMsg};
-
-use crate::constants::CONTRACT_DENOM;
+use cosmwasm_std::{Addr, BankMsg, Binary, Coin, CosmosMsg, StakingMsg, WasmMsg};
 
 #[derive(Clone)]
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
@@ -17,10 +15,10 @@ impl Delegation {
         }
     }
 
-    pub fn to_cosmos_msg(&self) -> CosmosMsg {
+    pub fn to_cosmos_msg(&self, denom: &str) -> CosmosMsg {
         CosmosMsg::Staking(StakingMsg::Delegate {
             validator: self.validator.clone(),
-            amount: Coin::new(self.amount, CONTRACT_DENOM),
+            amount: Coin::new(self.amount, denom),
         })
     }
 }
@@ -30,6 +28,7 @@ impl Delegation {
 pub struct SendFee {
     pub to_address: String,
     pub amount: u128,
+    pub msg: Option<Binary>,
 }
 
 impl SendFee {
@@ -37,14 +36,30 @@ impl SendFee {
         Self {
             to_address: to_address.to_string(),
             amount,
+            msg: None,
         }
     }
 
-    pub fn to_cosmos_msg(&self) -> CosmosMsg {
-        CosmosMsg::Bank(BankMsg::Send {
-            to_address: self.to_address.clone(),
-            amount: vec![Coin::new(self.amount, CONTRACT_DENOM)],
-        })
+    pub fn with_msg(to_address: Addr, amount: u128, msg: Option<Binary>) -> Self {
+        Self {
+            to_address: to_address.to_string(),
+            amount,
+            msg,
+        }
+    }
+
+    pub fn to_cosmos_msg(&self, denom: &str) -> CosmosMsg {
+        match &self.msg {
+            Some(msg) => CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: self.to_address.clone(),
+                msg: msg.clone(),
+                funds: vec![Coin::new(self.amount, denom)],
+            }),
+            None => CosmosMsg::Bank(BankMsg::Send {
+                to_address: self.to_address.clone(),
+                amount: vec![Coin::new(self.amount, denom)],
+            }),
+        }
     }
 }
 
@@ -63,10 +78,10 @@ impl Undelegation {
         }
     }
 
-    pub fn to_cosmos_msg(&self) -> CosmosMsg {
+    pub fn to_cosmos_msg(&self, denom: &str) -> CosmosMsg {
         CosmosMsg::Staking(StakingMsg::Undelegate {
             validator: self.validator.clone(),
-            amount: Coin::new(self.amount, CONTRACT_DENOM),
+            amount: Coin::new(self.amount, denom),
         })
     }
 }
@@ -88,11 +103,11 @@ impl Redelegation {
         }
     }
 
-    pub fn to_cosmos_msg(&self) -> CosmosMsg {
+    pub fn to_cosmos_msg(&self, denom: &str) -> CosmosMsg {
         CosmosMsg::Staking(StakingMsg::Redelegate {
             src_validator: self.src.clone(),
             dst_validator: self.dst.clone(),
-            amount: Coin::new(self.amount, CONTRACT_DENOM),
+            amount: Coin::new(self.amount, denom),
         })
     }
 }
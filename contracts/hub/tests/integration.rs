@@ -1,7 +1,8 @@
 use anyhow::{Ok, Result};
 use cosmwasm_std::{attr, coin, coins, Addr, Decimal, Delegation, Event, FullDelegation, Uint128};
 use cw_multi_test::App;
-use eris::hub::{DelegationStrategy, StateResponse, WantedDelegationsResponse};
+use eris::hub::{DelegationStrategy, StateResponse, StorageMetrics, WantedDelegationsResponse};
+use eris_tests::base::check_balance;
 use eris_tests::{gov_helper::EscrowHelper, TerraAppExtension};
 use eris_tests::{mock_app, mock_app_validators, EventChecker};
 use itertools::Itertools;
@@ -11,7 +12,7 @@ use std::vec;
 use eris::{
     emp_gauges::EmpInfo,
     governance_helper::WEEK,
-    hub::{ConfigResponse, ExecuteMsg, FeeConfig},
+    hub::{ConfigResponse, ExecuteMsg, FeeConfig, PauseFlags},
 };
 
 #[test]
@@ -46,20 +47,30 @@ fn update_configs() -> Result<()> {
                 max_delegation_bps: 2500,
                 validator_count: 5,
             },
+            validator_checks: None,
+            guardian: None,
+            pause_flags: PauseFlags::default(),
+            fee_receivers: vec![],
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            denom: "uluna".to_string(),
+            config_timelock: 0,
+            pending_config_change: None,
         }
     );
 
     let result = helper
         .hub_execute_sender(
             &mut router,
-            ExecuteMsg::UpdateConfig {
+            ExecuteMsg::ProposeConfigChange {
                 protocol_reward_fee: Some(Decimal::from_str("0")?),
                 delegation_strategy: None,
-                protocol_fee_contract: None,
-                allow_donations: None,
-                vote_operator: None,
                 epoch_period: None,
                 unbond_period: None,
+                config_timelock: None,
             },
             Addr::unchecked("user"),
         )
@@ -71,17 +82,45 @@ fn update_configs() -> Result<()> {
         .hub_execute(
             &mut router,
             ExecuteMsg::UpdateConfig {
-                protocol_reward_fee: Some(Decimal::from_str("0.1")?),
                 protocol_fee_contract: Some("fee_new".to_string()),
-                delegation_strategy: Some(eris::hub::DelegationStrategy::Uniform),
                 allow_donations: None,
                 vote_operator: None,
+                liquidity_buffer: None,
+                remove_slashed_validators: None,
+                insert_swap_routes: None,
+                delete_swap_routes: None,
+                validator_checks: None,
+                guardian: None,
+                fee_receivers: None,
+                auto_maintenance: None,
+                keeper_reward: None,
+                prop_gauges: None,
+                dust_cleanup: None,
+                split_bond: None,
+                remove_liquidity_buffer: None,
+                protocol_reward_fee: None,
+                delegation_strategy: None,
+                epoch_period: None,
+                unbond_period: None,
+            },
+        )
+        .unwrap();
+
+    helper
+        .hub_execute(
+            &mut router,
+            ExecuteMsg::ProposeConfigChange {
+                protocol_reward_fee: Some(Decimal::from_str("0.1")?),
+                delegation_strategy: Some(eris::hub::DelegationStrategy::Uniform),
                 epoch_period: None,
                 unbond_period: None,
+                config_timelock: None,
             },
         )
         .unwrap();
 
+    helper.hub_execute(&mut router, ExecuteMsg::ApplyConfigChange {}).unwrap();
+
     let config = helper.hub_query_config(&mut router)?;
     assert_eq!(
         config,
@@ -101,7 +140,19 @@ fn update_configs() -> Result<()> {
                 protocol_fee_contract: Addr::unchecked("fee_new"),
                 protocol_reward_fee: Decimal::from_ratio(10u128, 100u128)
             },
-            delegation_strategy: eris::hub::DelegationStrategy::Uniform
+            delegation_strategy: eris::hub::DelegationStrategy::Uniform,
+            validator_checks: None,
+            guardian: None,
+            pause_flags: PauseFlags::default(),
+            fee_receivers: vec![],
+            auto_maintenance: None,
+            keeper_reward: None,
+            prop_gauges: None,
+            dust_cleanup: None,
+            split_bond: None,
+            denom: "uluna".to_string(),
+            config_timelock: 0,
+            pending_config_change: None,
         }
     );
 
@@ -175,7 +226,7 @@ fn happy_case() -> Result<()> {
     );
 
     let results = helper.hub_rebalance(router_ref)?;
-    results.assert_attribute("wasm-erishub/rebalanced", attr("uluna_moved", "83198238")).unwrap();
+    results.assert_attribute("wasm-erishub/rebalanced", attr("utoken_moved", "83198238")).unwrap();
 
     let delegations = helper.hub_query_all_delegations(router_ref)?;
 
@@ -200,6 +251,45 @@ fn happy_case() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn bonding_lsm_shares() -> Result<()> {
+    let mut router = mock_app_validators(Some(5));
+    let router_ref = &mut router;
+    let helper = EscrowHelper::init(router_ref, true);
+
+    helper.hub_bond(router_ref, "user1", 100_000000, "uluna")?;
+
+    let shares = helper.lsm_tokenize(router_ref, "user2", "val5", 50_000000)?;
+    let result = helper.hub_bond_lsm_shares(router_ref, "user2", shares).unwrap_err();
+    assert_eq!("validator val5 is not whitelisted", result.root_cause().to_string());
+
+    let shares = helper.lsm_tokenize(router_ref, "user2", "val2", 50_000000)?;
+    let result = helper.hub_bond_lsm_shares(router_ref, "user2", shares)?;
+    result
+        .assert_attribute("wasm-erishub/lsm_shares_bonded", attr("utoken_bonded", "50000000"))
+        .unwrap();
+    result
+        .assert_attribute("wasm-erishub/lsm_shares_bonded", attr("ustake_minted", "50000000"))
+        .unwrap();
+
+    check_balance(
+        router_ref,
+        &helper.base.amp_token.get_address(),
+        &Addr::unchecked("user2"),
+        50_000000,
+    );
+    assert_eq!(router_ref.wrap().query_balance("user2", "val2/1")?.amount, Uint128::zero());
+
+    let delegation = helper.hub_query_delegation(router_ref, "val2")?.unwrap();
+    assert_eq!(delegation.amount.amount, Uint128::new(50_000000));
+
+    let state = helper.hub_query_state(router_ref)?;
+    assert_eq!(state.total_ustake, Uint128::new(150_000000));
+    assert_eq!(state.total_utoken, Uint128::new(150_000000));
+
+    Ok(())
+}
+
 #[test]
 fn config_does_not_change_exchange_rate() -> Result<()> {
     let mut router = mock_app_validators(Some(100));
@@ -217,11 +307,17 @@ fn config_does_not_change_exchange_rate() -> Result<()> {
     let expected_response = StateResponse {
         exchange_rate: Decimal::from_ratio(new_luna, ustake_minted),
         total_ustake: ustake_minted,
-        total_uluna: new_luna,
+        total_utoken: new_luna,
         unlocked_coins: vec![],
         unbonding: Uint128::zero(),
         available: Uint128::zero(),
-        tvl_uluna: new_luna,
+        tvl_utoken: new_luna,
+        liquidity_buffer: Uint128::zero(),
+        liquidity_buffer_config: None,
+        storage_metrics: StorageMetrics {
+            previous_batches: 0,
+            unbond_requests: 0,
+        },
     };
     let state = helper.hub_query_state(router_ref)?;
     assert_eq!(state, expected_response);
@@ -244,8 +340,7 @@ fn config_does_not_change_exchange_rate() -> Result<()> {
     // this only stores the gauges, but does not yet change delegations
     helper.hub_execute(
         router_ref,
-        ExecuteMsg::UpdateConfig {
-            protocol_fee_contract: None,
+        ExecuteMsg::ProposeConfigChange {
             protocol_reward_fee: None,
             delegation_strategy: Some(DelegationStrategy::Gauges {
                 amp_gauges: helper.base.amp_gauges.get_address_string(),
@@ -255,12 +350,12 @@ fn config_does_not_change_exchange_rate() -> Result<()> {
                 max_delegation_bps: 10000,
                 validator_count: 5,
             }),
-            allow_donations: None,
-            vote_operator: None,
             epoch_period: None,
             unbond_period: None,
+            config_timelock: None,
         },
     )?;
+    helper.hub_execute(router_ref, ExecuteMsg::ApplyConfigChange {})?;
 
     // after config change exchange_rate needs to be the same
     let state = helper.hub_query_state(router_ref)?;
@@ -350,11 +445,17 @@ fn config_does_not_change_exchange_rate_emps() -> Result<()> {
     let expected_response = StateResponse {
         exchange_rate: Decimal::from_ratio(new_luna, ustake_minted),
         total_ustake: ustake_minted,
-        total_uluna: new_luna,
+        total_utoken: new_luna,
         unlocked_coins: vec![],
         unbonding: Uint128::zero(),
         available: Uint128::zero(),
-        tvl_uluna: new_luna,
+        tvl_utoken: new_luna,
+        liquidity_buffer: Uint128::zero(),
+        liquidity_buffer_config: None,
+        storage_metrics: StorageMetrics {
+            previous_batches: 0,
+            unbond_requests: 0,
+        },
     };
     let state = helper.hub_query_state(router_ref)?;
     assert_eq!(state, expected_response);
@@ -377,8 +478,7 @@ fn config_does_not_change_exchange_rate_emps() -> Result<()> {
     // this only stores the gauges, but does not yet change delegations
     helper.hub_execute(
         router_ref,
-        ExecuteMsg::UpdateConfig {
-            protocol_fee_contract: None,
+        ExecuteMsg::ProposeConfigChange {
             protocol_reward_fee: None,
             delegation_strategy: Some(DelegationStrategy::Gauges {
                 amp_gauges: helper.base.amp_gauges.get_address_string(),
@@ -388,12 +488,12 @@ fn config_does_not_change_exchange_rate_emps() -> Result<()> {
                 max_delegation_bps: 10000,
                 validator_count: 5,
             }),
-            allow_donations: None,
-            vote_operator: None,
             epoch_period: None,
             unbond_period: None,
+            config_timelock: None,
         },
     )?;
+    helper.hub_execute(router_ref, ExecuteMsg::ApplyConfigChange {})?;
 
     // after config change exchange_rate needs to be the same
     let state = helper.hub_query_state(router_ref)?;
@@ -515,11 +615,17 @@ fn bond_and_harvest(
         StateResponse {
             exchange_rate: Decimal::one(),
             total_ustake: Uint128::new(300_000000),
-            total_uluna: Uint128::new(300_000000),
+            total_utoken: Uint128::new(300_000000),
             unlocked_coins: vec![],
             unbonding: Uint128::zero(),
             available: Uint128::zero(),
-            tvl_uluna: Uint128::new(300_000000),
+            tvl_utoken: Uint128::new(300_000000),
+            liquidity_buffer: Uint128::zero(),
+            liquidity_buffer_config: None,
+            storage_metrics: StorageMetrics {
+                previous_batches: 0,
+                unbond_requests: 0,
+            },
         }
     );
     let result = helper.hub_query_delegation(router_ref, "val1")?;
@@ -567,8 +673,8 @@ fn bond_and_harvest(
     result.assert_event(
         &Event::new("wasm-erishub/harvested")
             .add_attribute("_contract_addr", "contract1")
-            .add_attribute("uluna_bonded", bonded.to_string())
-            .add_attribute("uluna_protocol_fee", fee.to_string()),
+            .add_attribute("utoken_bonded", bonded.to_string())
+            .add_attribute("utoken_protocol_fee", fee.to_string()),
     );
     let ustake_minted = Uint128::new(300_000000);
     let new_luna = Uint128::new(300_000000).checked_add(bonded)?;
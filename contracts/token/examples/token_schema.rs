@@ -3,7 +3,8 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema_with_title, remove_schemas, schema_for};
 use cw20::BalanceResponse;
-use cw20_base::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use cw20_base::msg::InstantiateMsg;
+use eris_staking_token::msg::{ExecuteMsg, QueryMsg, TotalSupplyResponse};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -16,4 +17,5 @@ fn main() {
     export_schema_with_title(&schema_for!(QueryMsg), &out_dir, "QueryMsg");
 
     export_schema_with_title(&schema_for!(BalanceResponse), &out_dir, "BalanceResponse");
+    export_schema_with_title(&schema_for!(TotalSupplyResponse), &out_dir, "TotalSupplyResponse");
 }
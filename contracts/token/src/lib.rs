@@ -1,13 +1,32 @@
 use cosmwasm_std::{
-    entry_point, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
-    Storage,
+    entry_point, to_binary, Addr, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Order, Reply,
+    Response, StdError, StdResult, Storage, SubMsg, SubMsgResult, Uint128,
 };
+use cw20::BalanceResponse;
 use cw20_base::contract::{
-    execute as cw20_execute, instantiate as cw20_instantiate, query as cw20_query,
+    execute as cw20_execute, execute_send as cw20_execute_send, instantiate as cw20_instantiate,
+    query as cw20_query,
 };
-use cw20_base::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
-use cw20_base::state::{MinterData, TOKEN_INFO};
+use cw20_base::msg::{ExecuteMsg as Cw20ExecuteMsg, InstantiateMsg};
+use cw20_base::state::{MinterData, BALANCES, TOKEN_INFO};
 use cw20_base::ContractError;
+use cw_storage_plus::Bound;
+
+use crate::msg::{
+    Cw20ReceiveWithMemoMsg, ExecuteMsg, ExtensionMsg, ExtensionQueryMsg, QueryMsg,
+    TotalSupplyResponse,
+};
+use crate::snapshots::SnapshotStorage;
+use crate::state::{
+    BALANCE_CHANGELOG, SEND_FALLBACK, SNAPSHOT_START_HEIGHT, TOTAL_SUPPLY_CHANGELOG,
+};
+
+pub mod msg;
+pub mod snapshots;
+pub mod state;
+
+/// Reply id of the `receive_with_memo` hook, which falls back to the plain `receive` hook
+const RECEIVE_WITH_MEMO_REPLY_ID: u64 = 1;
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -20,23 +39,60 @@ pub fn instantiate(
         return Err(StdError::generic_err("'mint' needs to be enabled").into());
     }
 
+    SNAPSHOT_START_HEIGHT.save(deps.storage, &env.block.height)?;
+
     cw20_instantiate(deps, env, info, msg)
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, _msg: Empty) -> StdResult<Response> {
+    // tokens instantiated before snapshots were supported record them from the migration on
+    if SNAPSHOT_START_HEIGHT.may_load(deps.storage)?.is_none() {
+        SNAPSHOT_START_HEIGHT.save(deps.storage, &env.block.height)?;
+    }
+
+    Ok(Response::new())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    // balances and total supply are recorded as they are written, whichever message changes them
+    let mut storage = SnapshotStorage::new(deps.storage, env.block.height);
+    let deps = DepsMut {
+        storage: &mut storage,
+        api: deps.api,
+        querier: deps.querier,
+    };
+
+    match msg {
+        ExecuteMsg::Base(msg) => execute_base(deps, env, info, msg),
+        ExecuteMsg::Extension(ExtensionMsg::SendWithMemo {
+            contract,
+            amount,
+            msg,
+            memo,
+        }) => execute_send_with_memo(deps, env, info, contract, amount, msg, memo),
+    }
+}
+
+fn execute_base(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: Cw20ExecuteMsg,
 ) -> Result<Response, ContractError> {
     // For `burn`, we assert that the caller is the minter
     // For `burn_from`, we simply disable it
     match msg {
-        ExecuteMsg::Burn {
+        Cw20ExecuteMsg::Burn {
             ..
         } => assert_minter(deps.storage, &info.sender)?,
-        ExecuteMsg::BurnFrom {
+        Cw20ExecuteMsg::BurnFrom {
             ..
         } => return Err(StdError::generic_err("`burn_from` command is disabled").into()),
         _ => (),
@@ -45,6 +101,51 @@ pub fn execute(
     cw20_execute(deps, env, info, msg)
 }
 
+/// The tokens are sent like with `Send`, only the `receive` hook is replaced by the
+/// `receive_with_memo` hook. Its failure is caught, so that the plain hook can be sent instead.
+fn execute_send_with_memo(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: String,
+    amount: Uint128,
+    msg: Binary,
+    memo: String,
+) -> Result<Response, ContractError> {
+    let sender = info.sender.to_string();
+    let mut res =
+        cw20_execute_send(deps.branch(), env, info, contract.clone(), amount, msg.clone())?;
+
+    SEND_FALLBACK.save(deps.storage, &res.messages)?;
+
+    let receive_msg = Cw20ReceiveWithMemoMsg {
+        sender,
+        amount,
+        msg,
+        memo: memo.clone(),
+    }
+    .into_cosmos_msg(contract)?;
+    res.messages = vec![SubMsg::reply_on_error(receive_msg, RECEIVE_WITH_MEMO_REPLY_ID)];
+
+    Ok(res.add_attribute("memo", memo))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+    match (reply.id, reply.result) {
+        (RECEIVE_WITH_MEMO_REPLY_ID, SubMsgResult::Err(error)) => {
+            let messages = SEND_FALLBACK.load(deps.storage)?;
+            SEND_FALLBACK.remove(deps.storage);
+
+            Ok(Response::new()
+                .add_submessages(messages)
+                .add_attribute("action", "send_without_memo")
+                .add_attribute("error", error))
+        },
+        (id, _) => Err(StdError::generic_err(format!("unexpected reply {}", id)).into()),
+    }
+}
+
 fn assert_minter(storage: &dyn Storage, sender: &Addr) -> Result<(), ContractError> {
     let token_info = TOKEN_INFO.load(storage)?;
 
@@ -63,7 +164,66 @@ fn assert_minter(storage: &dyn Storage, sender: &Addr) -> Result<(), ContractErr
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    cw20_query(deps, env, msg)
+    match msg {
+        QueryMsg::Base(msg) => cw20_query(deps, env, msg),
+        QueryMsg::Extension(ExtensionQueryMsg::BalanceAtHeight {
+            address,
+            height,
+        }) => to_binary(&query_balance_at_height(deps, address, height)?),
+        QueryMsg::Extension(ExtensionQueryMsg::TotalSupplyAtHeight {
+            height,
+        }) => to_binary(&query_total_supply_at_height(deps, height)?),
+    }
+}
+
+fn assert_snapshotted(storage: &dyn Storage, height: u64) -> StdResult<()> {
+    let start = SNAPSHOT_START_HEIGHT.load(storage)?;
+    if height <= start {
+        return Err(StdError::generic_err(format!(
+            "snapshots are only available after height {}",
+            start
+        )));
+    }
+
+    Ok(())
+}
+
+fn query_balance_at_height(deps: Deps, address: String, height: u64) -> StdResult<BalanceResponse> {
+    assert_snapshotted(deps.storage, height)?;
+    let address = deps.api.addr_validate(&address)?;
+
+    // the first change from the height on recorded the balance at the height, without any change
+    // the balance is still the current one
+    let balance = match BALANCE_CHANGELOG
+        .prefix(&address)
+        .range(deps.storage, Some(Bound::inclusive(height)), None, Order::Ascending)
+        .next()
+        .transpose()?
+    {
+        Some((_, balance)) => balance,
+        None => BALANCES.may_load(deps.storage, &address)?.unwrap_or_default(),
+    };
+
+    Ok(BalanceResponse {
+        balance,
+    })
+}
+
+fn query_total_supply_at_height(deps: Deps, height: u64) -> StdResult<TotalSupplyResponse> {
+    assert_snapshotted(deps.storage, height)?;
+
+    let total_supply = match TOTAL_SUPPLY_CHANGELOG
+        .range(deps.storage, Some(Bound::inclusive(height)), None, Order::Ascending)
+        .next()
+        .transpose()?
+    {
+        Some((_, total_supply)) => total_supply,
+        None => TOKEN_INFO.load(deps.storage)?.total_supply,
+    };
+
+    Ok(TotalSupplyResponse {
+        total_supply,
+    })
 }
 
 #[cfg(test)]
@@ -71,8 +231,10 @@ mod tests {
     use cosmwasm_std::testing::{
         mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
     };
-    use cosmwasm_std::{OwnedDeps, Uint128};
-    use cw20_base::state::{TokenInfo, BALANCES};
+    use cosmwasm_std::{attr, from_binary, CosmosMsg, OwnedDeps, WasmMsg};
+    use cw20::{Cw20Coin, Logo, LogoInfo, MarketingInfoResponse, MinterResponse};
+    use cw20_base::msg::{InstantiateMarketingInfo, QueryMsg as Cw20QueryMsg};
+    use cw20_base::state::TokenInfo;
 
     use super::*;
 
@@ -103,9 +265,41 @@ mod tests {
             .save(deps.as_mut().storage, &Addr::unchecked("alice"), &Uint128::new(100))
             .unwrap();
 
+        // The token was instantiated before snapshots were supported
+        migrate(deps.as_mut(), mock_env_at_height(10000), Empty {}).unwrap();
+
         deps
     }
 
+    fn mock_env_at_height(height: u64) -> Env {
+        let mut env = mock_env();
+        env.block.height = height;
+        env
+    }
+
+    fn query_balance_at(deps: Deps, address: &str, height: u64) -> StdResult<Uint128> {
+        let res: BalanceResponse = from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::Extension(ExtensionQueryMsg::BalanceAtHeight {
+                address: address.to_string(),
+                height,
+            }),
+        )?)?;
+        Ok(res.balance)
+    }
+
+    fn query_total_supply_at(deps: Deps, height: u64) -> StdResult<Uint128> {
+        let res: TotalSupplyResponse = from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::Extension(ExtensionQueryMsg::TotalSupplyAtHeight {
+                height,
+            }),
+        )?)?;
+        Ok(res.total_supply)
+    }
+
     #[test]
     fn asserting_minter() {
         let mut deps = setup_test();
@@ -115,9 +309,9 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("alice", &[]),
-            ExecuteMsg::Burn {
+            ExecuteMsg::Base(Cw20ExecuteMsg::Burn {
                 amount: Uint128::new(100),
-            },
+            }),
         );
         assert_eq!(res, Err(StdError::generic_err("only minter can execute token burn").into()));
 
@@ -126,9 +320,9 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("stake_hub", &[]),
-            ExecuteMsg::Burn {
+            ExecuteMsg::Base(Cw20ExecuteMsg::Burn {
                 amount: Uint128::new(100),
-            },
+            }),
         );
         assert!(res.is_ok());
 
@@ -150,11 +344,341 @@ mod tests {
             deps.as_mut(),
             mock_env(),
             mock_info("stake_hub", &[]),
-            ExecuteMsg::BurnFrom {
+            ExecuteMsg::Base(Cw20ExecuteMsg::BurnFrom {
                 owner: "alice".to_string(),
                 amount: Uint128::new(100),
-            },
+            }),
         );
         assert_eq!(res, Err(StdError::generic_err("`burn_from` command is disabled").into()));
     }
+
+    #[test]
+    fn instantiating_with_marketing() {
+        let mut deps = mock_dependencies();
+
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("stake_hub", &[]),
+            InstantiateMsg {
+                name: "Stake Token".to_string(),
+                symbol: "STAKE".to_string(),
+                decimals: 6,
+                initial_balances: vec![],
+                mint: Some(MinterResponse {
+                    minter: "stake_hub".to_string(),
+                    cap: None,
+                }),
+                marketing: Some(InstantiateMarketingInfo {
+                    project: Some("Eris Protocol".to_string()),
+                    description: Some("Liquid staked token".to_string()),
+                    marketing: Some("owner".to_string()),
+                    logo: Some(Logo::Url("https://example.com/logo.svg".to_string())),
+                }),
+            },
+        )
+        .unwrap();
+
+        let res: MarketingInfoResponse = from_binary(
+            &query(deps.as_ref(), mock_env(), QueryMsg::Base(Cw20QueryMsg::MarketingInfo {}))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            res,
+            MarketingInfoResponse {
+                project: Some("Eris Protocol".to_string()),
+                description: Some("Liquid staked token".to_string()),
+                logo: Some(LogoInfo::Url("https://example.com/logo.svg".to_string())),
+                marketing: Some(Addr::unchecked("owner")),
+            }
+        );
+
+        // The marketing admin can update the marketing info
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Base(Cw20ExecuteMsg::UpdateMarketing {
+                project: None,
+                description: Some("Eris liquid staked token".to_string()),
+                marketing: None,
+            }),
+        )
+        .unwrap();
+
+        let res: MarketingInfoResponse = from_binary(
+            &query(deps.as_ref(), mock_env(), QueryMsg::Base(Cw20QueryMsg::MarketingInfo {}))
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(res.description, Some("Eris liquid staked token".to_string()));
+    }
+
+    #[test]
+    fn sending_with_memo() {
+        let mut deps = setup_test();
+
+        let msg: ExecuteMsg = from_binary(&Binary::from(
+            br#"{"send_with_memo":{"contract":"exchange","amount":"40","msg":"e30=","memo":"deposit-1"}}"#.to_vec(),
+        ))
+        .unwrap();
+
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::reply_on_error(
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: "exchange".to_string(),
+                    msg: Binary::from(
+                        br#"{"receive_with_memo":{"sender":"alice","amount":"40","msg":"e30=","memo":"deposit-1"}}"#.to_vec()
+                    ),
+                    funds: vec![],
+                }),
+                RECEIVE_WITH_MEMO_REPLY_ID
+            )]
+        );
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "send"),
+                attr("from", "alice"),
+                attr("to", "exchange"),
+                attr("amount", "40"),
+                attr("memo", "deposit-1"),
+            ]
+        );
+
+        let balance = BALANCES.load(deps.as_ref().storage, &Addr::unchecked("alice")).unwrap();
+        assert_eq!(balance, Uint128::new(60));
+        let balance = BALANCES.load(deps.as_ref().storage, &Addr::unchecked("exchange")).unwrap();
+        assert_eq!(balance, Uint128::new(40));
+
+        // Receivers without support for memos get the plain `receive` hook
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: RECEIVE_WITH_MEMO_REPLY_ID,
+                result: SubMsgResult::Err("unknown variant `receive_with_memo`".to_string()),
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![SubMsg::new(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: "exchange".to_string(),
+                msg: Binary::from(
+                    br#"{"receive":{"sender":"alice","amount":"40","msg":"e30="}}"#.to_vec()
+                ),
+                funds: vec![],
+            }))]
+        );
+        assert_eq!(SEND_FALLBACK.may_load(deps.as_ref().storage).unwrap(), None);
+
+        // Alice can't send more than her balance
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            ExecuteMsg::Extension(ExtensionMsg::SendWithMemo {
+                contract: "exchange".to_string(),
+                amount: Uint128::new(61),
+                msg: Binary::default(),
+                memo: "deposit-2".to_string(),
+            }),
+        );
+        assert!(res.is_err());
+
+        // Vanilla CW20 messages are still accepted
+        let msg: ExecuteMsg = from_binary(&Binary::from(
+            br#"{"transfer":{"recipient":"bob","amount":"10"}}"#.to_vec(),
+        ))
+        .unwrap();
+        assert_eq!(
+            msg,
+            ExecuteMsg::Base(Cw20ExecuteMsg::Transfer {
+                recipient: "bob".to_string(),
+                amount: Uint128::new(10),
+            })
+        );
+    }
+
+    #[test]
+    fn tracking_snapshots() {
+        let mut deps = mock_dependencies();
+
+        instantiate(
+            deps.as_mut(),
+            mock_env_at_height(100),
+            mock_info("stake_hub", &[]),
+            InstantiateMsg {
+                name: "Stake Token".to_string(),
+                symbol: "STAKE".to_string(),
+                decimals: 6,
+                initial_balances: vec![Cw20Coin {
+                    address: "alice".to_string(),
+                    amount: Uint128::new(100),
+                }],
+                mint: Some(MinterResponse {
+                    minter: "stake_hub".to_string(),
+                    cap: None,
+                }),
+                marketing: None,
+            },
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env_at_height(105),
+            mock_info("alice", &[]),
+            ExecuteMsg::Base(Cw20ExecuteMsg::Transfer {
+                recipient: "bob".to_string(),
+                amount: Uint128::new(30),
+            }),
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env_at_height(110),
+            mock_info("stake_hub", &[]),
+            ExecuteMsg::Base(Cw20ExecuteMsg::Mint {
+                recipient: "bob".to_string(),
+                amount: Uint128::new(50),
+            }),
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env_at_height(112),
+            mock_info("bob", &[]),
+            ExecuteMsg::Extension(ExtensionMsg::SendWithMemo {
+                contract: "exchange".to_string(),
+                amount: Uint128::new(20),
+                msg: Binary::default(),
+                memo: "deposit-1".to_string(),
+            }),
+        )
+        .unwrap();
+
+        // Values are the ones at the start of the block
+        let deps = deps.as_ref();
+        assert_eq!(query_balance_at(deps, "alice", 101).unwrap(), Uint128::new(100));
+        assert_eq!(query_balance_at(deps, "alice", 105).unwrap(), Uint128::new(100));
+        assert_eq!(query_balance_at(deps, "alice", 106).unwrap(), Uint128::new(70));
+        assert_eq!(query_balance_at(deps, "bob", 105).unwrap(), Uint128::zero());
+        assert_eq!(query_balance_at(deps, "bob", 106).unwrap(), Uint128::new(30));
+        assert_eq!(query_balance_at(deps, "bob", 111).unwrap(), Uint128::new(80));
+        assert_eq!(query_balance_at(deps, "bob", 113).unwrap(), Uint128::new(60));
+        assert_eq!(query_balance_at(deps, "exchange", 112).unwrap(), Uint128::zero());
+        assert_eq!(query_balance_at(deps, "exchange", 200).unwrap(), Uint128::new(20));
+
+        assert_eq!(query_total_supply_at(deps, 101).unwrap(), Uint128::new(100));
+        assert_eq!(query_total_supply_at(deps, 110).unwrap(), Uint128::new(100));
+        assert_eq!(query_total_supply_at(deps, 111).unwrap(), Uint128::new(150));
+
+        // Nothing is known before the instantiation
+        let err = StdError::generic_err("snapshots are only available after height 100");
+        assert_eq!(query_balance_at(deps, "alice", 100), Err(err.clone()));
+        assert_eq!(query_total_supply_at(deps, 99), Err(err));
+    }
+
+    #[test]
+    fn tracking_snapshots_after_migration() {
+        let mut deps = setup_test();
+
+        // Migrating again does not move the start of the snapshots
+        migrate(deps.as_mut(), mock_env_at_height(10010), Empty {}).unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env_at_height(10020),
+            mock_info("stake_hub", &[]),
+            ExecuteMsg::Base(Cw20ExecuteMsg::Burn {
+                amount: Uint128::new(40),
+            }),
+        )
+        .unwrap();
+
+        // Balances that existed before the migration are covered from the start of the snapshots
+        let deps = deps.as_ref();
+        assert_eq!(query_balance_at(deps, "stake_hub", 10001).unwrap(), Uint128::new(100));
+        assert_eq!(query_balance_at(deps, "stake_hub", 10020).unwrap(), Uint128::new(100));
+        assert_eq!(query_balance_at(deps, "stake_hub", 10021).unwrap(), Uint128::new(60));
+        assert_eq!(query_balance_at(deps, "alice", 10001).unwrap(), Uint128::new(100));
+        assert_eq!(query_balance_at(deps, "alice", 10021).unwrap(), Uint128::new(100));
+
+        assert_eq!(query_total_supply_at(deps, 10001).unwrap(), Uint128::new(200));
+        assert_eq!(query_total_supply_at(deps, 10021).unwrap(), Uint128::new(160));
+
+        let res = query_balance_at(deps, "alice", 10000);
+        assert_eq!(
+            res,
+            Err(StdError::generic_err("snapshots are only available after height 10000"))
+        );
+    }
+
+    #[test]
+    fn tracking_snapshots_of_allowances() {
+        let mut deps = setup_test();
+
+        execute(
+            deps.as_mut(),
+            mock_env_at_height(10005),
+            mock_info("alice", &[]),
+            ExecuteMsg::Base(Cw20ExecuteMsg::IncreaseAllowance {
+                spender: "bob".to_string(),
+                amount: Uint128::new(100),
+                expires: None,
+            }),
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env_at_height(10010),
+            mock_info("bob", &[]),
+            ExecuteMsg::Base(Cw20ExecuteMsg::TransferFrom {
+                owner: "alice".to_string(),
+                recipient: "charlie".to_string(),
+                amount: Uint128::new(30),
+            }),
+        )
+        .unwrap();
+
+        execute(
+            deps.as_mut(),
+            mock_env_at_height(10020),
+            mock_info("bob", &[]),
+            ExecuteMsg::Base(Cw20ExecuteMsg::SendFrom {
+                owner: "alice".to_string(),
+                contract: "exchange".to_string(),
+                amount: Uint128::new(50),
+                msg: Binary::default(),
+            }),
+        )
+        .unwrap();
+
+        // The balances of the owner and the recipients are recorded, not the one of the spender
+        let deps = deps.as_ref();
+        assert_eq!(query_balance_at(deps, "alice", 10010).unwrap(), Uint128::new(100));
+        assert_eq!(query_balance_at(deps, "alice", 10011).unwrap(), Uint128::new(70));
+        assert_eq!(query_balance_at(deps, "alice", 10020).unwrap(), Uint128::new(70));
+        assert_eq!(query_balance_at(deps, "alice", 10021).unwrap(), Uint128::new(20));
+        assert_eq!(query_balance_at(deps, "charlie", 10010).unwrap(), Uint128::zero());
+        assert_eq!(query_balance_at(deps, "charlie", 10011).unwrap(), Uint128::new(30));
+        assert_eq!(query_balance_at(deps, "exchange", 10020).unwrap(), Uint128::zero());
+        assert_eq!(query_balance_at(deps, "exchange", 10021).unwrap(), Uint128::new(50));
+        assert_eq!(query_balance_at(deps, "bob", 10021).unwrap(), Uint128::zero());
+
+        let bob_changes = BALANCE_CHANGELOG
+            .prefix(&Addr::unchecked("bob"))
+            .range(deps.storage, None, None, Order::Ascending)
+            .count();
+        assert_eq!(bob_changes, 0);
+        assert_eq!(query_total_supply_at(deps, 10021).unwrap(), Uint128::new(200));
+    }
 }
@@ -0,0 +1,79 @@
+use cosmwasm_std::{to_binary, Binary, CosmosMsg, StdResult, Uint128, WasmMsg};
+use cw20_base::msg::{ExecuteMsg as Cw20ExecuteMsg, QueryMsg as Cw20QueryMsg};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum ExecuteMsg {
+    /// Messages of the vanilla CW20 contract
+    Base(Cw20ExecuteMsg),
+    /// Messages added by the stake token
+    Extension(ExtensionMsg),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtensionMsg {
+    /// Same as `Send`, the memo is forwarded to the receiving contract with the `receive_with_memo`
+    /// hook. If the contract does not accept it, the plain `receive` hook of `Send` is used instead.
+    SendWithMemo {
+        contract: String,
+        amount: Uint128,
+        msg: Binary,
+        memo: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum QueryMsg {
+    /// Queries of the vanilla CW20 contract
+    Base(Cw20QueryMsg),
+    /// Queries added by the stake token
+    Extension(ExtensionQueryMsg),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtensionQueryMsg {
+    /// Balance of the address at the start of the block. Response: `cw20::BalanceResponse`
+    BalanceAtHeight {
+        address: String,
+        height: u64,
+    },
+    /// Total supply at the start of the block. Response: `TotalSupplyResponse`
+    TotalSupplyAtHeight {
+        height: u64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalSupplyResponse {
+    pub total_supply: Uint128,
+}
+
+/// `Cw20ReceiveMsg` with the memo of the sender, sent as `receive_with_memo` hook
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw20ReceiveWithMemoMsg {
+    pub sender: String,
+    pub amount: Uint128,
+    pub msg: Binary,
+    pub memo: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum ReceiverExecuteMsg {
+    ReceiveWithMemo(Cw20ReceiveWithMemoMsg),
+}
+
+impl Cw20ReceiveWithMemoMsg {
+    pub fn into_cosmos_msg(self, contract_addr: impl Into<String>) -> StdResult<CosmosMsg> {
+        Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: contract_addr.into(),
+            msg: to_binary(&ReceiverExecuteMsg::ReceiveWithMemo(self))?,
+            funds: vec![],
+        }))
+    }
+}
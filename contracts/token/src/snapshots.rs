@@ -0,0 +1,131 @@
+use cosmwasm_std::{from_slice, Addr, Order, Record, StdResult, Storage, Uint128};
+use cw20_base::state::TokenInfo;
+
+use crate::state::{BALANCE_CHANGELOG, TOTAL_SUPPLY_CHANGELOG};
+
+/// Key prefix of the CW20 balances, i.e. the length-prefixed namespace of `BALANCES`
+const BALANCES_KEY_PREFIX: &[u8] = b"\x00\x07balance";
+/// Key of the CW20 token info, which holds the total supply
+const TOKEN_INFO_KEY: &[u8] = b"token_info";
+
+/// Storage recording the balances and the total supply before their first change in a block.
+///
+/// Every write of the CW20 balances goes through it, so the snapshots don't depend on knowing which
+/// messages change which balances.
+pub struct SnapshotStorage<'a> {
+    storage: &'a mut dyn Storage,
+    height: u64,
+}
+
+impl<'a> SnapshotStorage<'a> {
+    pub fn new(storage: &'a mut dyn Storage, height: u64) -> Self {
+        Self {
+            storage,
+            height,
+        }
+    }
+
+    fn record_change(&mut self, key: &[u8]) -> StdResult<()> {
+        if let Some(address) = key.strip_prefix(BALANCES_KEY_PREFIX) {
+            let address = Addr::unchecked(String::from_utf8(address.to_vec())?);
+            if !BALANCE_CHANGELOG.has(self.storage, (&address, self.height)) {
+                let balance = match self.storage.get(key) {
+                    Some(value) => from_slice(&value)?,
+                    None => Uint128::zero(),
+                };
+                BALANCE_CHANGELOG.save(self.storage, (&address, self.height), &balance)?;
+            }
+        } else if key == TOKEN_INFO_KEY && !TOTAL_SUPPLY_CHANGELOG.has(self.storage, self.height) {
+            if let Some(value) = self.storage.get(key) {
+                let token_info: TokenInfo = from_slice(&value)?;
+                TOTAL_SUPPLY_CHANGELOG.save(self.storage, self.height, &token_info.total_supply)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Storage for SnapshotStorage<'_> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.storage.get(key)
+    }
+
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'b> {
+        self.storage.range(start, end, order)
+    }
+
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        // `Storage` can't return errors, but the recorded values are the ones written by the CW20
+        // contract, so they always deserialize
+        self.record_change(key).expect("failed to record snapshot");
+        self.storage.set(key, value);
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.record_change(key).expect("failed to record snapshot");
+        self.storage.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::testing::MockStorage;
+    use cw20_base::state::{BALANCES, TOKEN_INFO};
+
+    use super::*;
+
+    #[test]
+    fn matching_cw20_keys() {
+        let mut storage = MockStorage::new();
+        let alice = Addr::unchecked("alice");
+
+        BALANCES.save(&mut storage, &alice, &Uint128::new(100)).unwrap();
+        assert!(storage.get(&[BALANCES_KEY_PREFIX, &b"alice"[..]].concat()).is_some());
+
+        TOKEN_INFO
+            .save(
+                &mut storage,
+                &TokenInfo {
+                    name: "Stake Token".to_string(),
+                    symbol: "STAKE".to_string(),
+                    decimals: 6,
+                    total_supply: Uint128::new(100),
+                    mint: None,
+                },
+            )
+            .unwrap();
+        assert!(storage.get(TOKEN_INFO_KEY).is_some());
+
+        // Only the first change in a block is recorded
+        let mut snapshot_storage = SnapshotStorage::new(&mut storage, 105);
+        BALANCES.save(&mut snapshot_storage, &alice, &Uint128::new(70)).unwrap();
+        BALANCES.save(&mut snapshot_storage, &alice, &Uint128::new(40)).unwrap();
+        BALANCES.save(&mut snapshot_storage, &Addr::unchecked("bob"), &Uint128::new(60)).unwrap();
+        TOKEN_INFO
+            .update(&mut snapshot_storage, |mut token_info| -> StdResult<_> {
+                token_info.total_supply += Uint128::new(30);
+                Ok(token_info)
+            })
+            .unwrap();
+
+        let changelog = BALANCE_CHANGELOG
+            .range(&storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            changelog,
+            vec![
+                ((Addr::unchecked("alice"), 105), Uint128::new(100)),
+                ((Addr::unchecked("bob"), 105), Uint128::zero()),
+            ]
+        );
+        assert_eq!(TOTAL_SUPPLY_CHANGELOG.load(&storage, 105).unwrap(), Uint128::new(100));
+        assert_eq!(BALANCES.load(&storage, &alice).unwrap(), Uint128::new(40));
+    }
+}
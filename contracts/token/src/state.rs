@@ -0,0 +1,16 @@
+use cosmwasm_std::{Addr, SubMsg, Uint128};
+use cw_storage_plus::{Item, Map};
+
+/// Messages of the plain CW20 send, dispatched if the receiver rejects the `receive_with_memo` hook
+pub const SEND_FALLBACK: Item<Vec<SubMsg>> = Item::new("send_fallback");
+
+/// Height from which on the snapshots are recorded, balances that have not changed since then are
+/// read from the CW20 balances
+pub const SNAPSHOT_START_HEIGHT: Item<u64> = Item::new("snapshot_start_height");
+
+/// Balances of the addresses before their first change in a block, indexed by the address and the
+/// height of the block
+pub const BALANCE_CHANGELOG: Map<(&Addr, u64), Uint128> = Map::new("balance_changelog");
+
+/// Total supply before its first change in a block, indexed by the height of the block
+pub const TOTAL_SUPPLY_CHANGELOG: Map<u64, Uint128> = Map::new("total_supply_changelog");
@@ -1,10 +1,12 @@
 use cosmwasm_schema::cw_serde;
 
-use cosmwasm_std::{attr, Addr, Decimal, StdResult, Uint128};
+use cosmwasm_std::{attr, Addr, Decimal, Empty, StdResult, Uint128};
 use cw20::{BalanceResponse, Cw20QueryMsg, MinterResponse};
 
 use cw_multi_test::{App, ContractWrapper, Executor};
 
+use crate::lsm;
+
 pub const MULTIPLIER: u64 = 1_000_000;
 
 #[cw_serde]
@@ -46,6 +48,7 @@ pub struct BaseErisTestPackage {
     pub prop_gauges: ContractInfoWrapper,
     pub amp_lp: ContractInfoWrapper,
     pub amp_token: ContractInfoWrapper,
+    pub lsm_module: ContractInfoWrapper,
 }
 
 #[cw_serde]
@@ -67,9 +70,11 @@ impl BaseErisTestPackage {
             amp_gauges: None.into(),
             amp_token: None.into(),
             prop_gauges: None.into(),
+            lsm_module: None.into(),
         };
 
         base_pack.init_token(router, msg.owner.clone());
+        base_pack.init_lsm_module(router, msg.owner.clone());
         base_pack.init_hub(router, msg.owner.clone());
         base_pack.init_voting_escrow(router, msg.owner.clone());
         base_pack.init_emp_gauges(router, msg.owner.clone());
@@ -115,10 +120,25 @@ impl BaseErisTestPackage {
         .into()
     }
 
+    fn init_lsm_module(&mut self, router: &mut App, owner: Addr) {
+        let code_id = router.store_code(lsm::lsm_module_contract());
+
+        let instance = router
+            .instantiate_contract(code_id, owner, &Empty {}, &[], "LSM module", None)
+            .unwrap();
+
+        self.lsm_module = Some(ContractInfo {
+            address: instance,
+            code_id,
+        })
+        .into()
+    }
+
     fn init_hub(&mut self, router: &mut App, owner: Addr) {
         let hub_contract = Box::new(
             ContractWrapper::new_with_empty(
-                eris_staking_hub::contract::execute,
+                // redemptions of tokenized shares are handled by the lsm stand-in
+                lsm::hub_execute,
                 eris_staking_hub::contract::instantiate,
                 eris_staking_hub::contract::query,
             )
@@ -145,6 +165,11 @@ impl BaseErisTestPackage {
             protocol_reward_fee: Decimal::from_ratio(1u128, 100u128),
             delegation_strategy: None,
             vote_operator: None,
+            token_factory_subdenom: None,
+            denom: None,
+            // config changes are applied right away in the tests
+            config_timelock: Some(0),
+            stake_token_marketing: None,
         };
 
         let instance =
@@ -283,35 +308,38 @@ impl BaseErisTestPackage {
         owner: Addr,
         use_default_hub: bool,
     ) {
-        let delegation_strategy = if use_default_hub {
-            None
-        } else {
-            Some(eris::hub::DelegationStrategy::Gauges {
-                amp_gauges: self.amp_gauges.get_address_string(),
-                emp_gauges: Some(self.emp_gauges.get_address_string()),
-                amp_factor_bps: 5000,
-                min_delegation_bps: 100,
-                max_delegation_bps: 2500,
-                validator_count: 5,
-            })
-        };
-
-        router
-            .execute_contract(
-                owner.clone(),
-                self.hub.get_address(),
-                &eris::hub::ExecuteMsg::UpdateConfig {
-                    protocol_fee_contract: None,
-                    protocol_reward_fee: None,
-                    delegation_strategy,
-                    allow_donations: None,
-                    vote_operator: None,
-                    epoch_period: None,
-                    unbond_period: None,
-                },
-                &[],
-            )
-            .unwrap();
+        if !use_default_hub {
+            router
+                .execute_contract(
+                    owner.clone(),
+                    self.hub.get_address(),
+                    &eris::hub::ExecuteMsg::ProposeConfigChange {
+                        protocol_reward_fee: None,
+                        delegation_strategy: Some(eris::hub::DelegationStrategy::Gauges {
+                            amp_gauges: self.amp_gauges.get_address_string(),
+                            emp_gauges: Some(self.emp_gauges.get_address_string()),
+                            amp_factor_bps: 5000,
+                            min_delegation_bps: 100,
+                            max_delegation_bps: 2500,
+                            validator_count: 5,
+                        }),
+                        epoch_period: None,
+                        unbond_period: None,
+                        config_timelock: None,
+                    },
+                    &[],
+                )
+                .unwrap();
+
+            router
+                .execute_contract(
+                    owner.clone(),
+                    self.hub.get_address(),
+                    &eris::hub::ExecuteMsg::ApplyConfigChange {},
+                    &[],
+                )
+                .unwrap();
+        }
 
         router
             .execute_contract(
@@ -1,9 +1,9 @@
 use anyhow::Result;
 use cosmwasm_std::{
-    attr, coin, to_binary, Addr, Delegation, FullDelegation, StdResult, Uint128, VoteOption,
+    attr, coin, to_binary, Addr, Coin, Delegation, FullDelegation, StdResult, Uint128, VoteOption,
 };
 use cw20::Cw20ExecuteMsg;
-use cw_multi_test::{App, AppResponse, Executor};
+use cw_multi_test::{App, AppResponse, BankSudo, Executor, SudoMsg};
 use eris::{emp_gauges::AddEmpInfo, governance_helper::WEEK};
 
 use crate::base::{BaseErisTestInitMessage, BaseErisTestPackage};
@@ -395,6 +395,46 @@ impl EscrowHelper {
         )
     }
 
+    /// Tokenizes the amount into shares of the validator, the tokens are escrowed in the lsm stand-in
+    pub fn lsm_tokenize(
+        &self,
+        router_ref: &mut App,
+        sender: impl Into<String>,
+        validator: impl Into<String>,
+        amount: u128,
+    ) -> Result<Coin> {
+        let sender: String = sender.into();
+        let shares = coin(amount, format!("{0}/1", validator.into()));
+
+        router_ref.send_tokens(
+            Addr::unchecked(sender.clone()),
+            self.base.lsm_module.get_address(),
+            &[coin(amount, "uluna")],
+        )?;
+        router_ref.sudo(SudoMsg::Bank(BankSudo::Mint {
+            to_address: sender,
+            amount: vec![shares.clone()],
+        }))?;
+
+        Ok(shares)
+    }
+
+    pub fn hub_bond_lsm_shares(
+        &self,
+        router_ref: &mut App,
+        sender: impl Into<String>,
+        shares: Coin,
+    ) -> Result<AppResponse> {
+        router_ref.execute_contract(
+            Addr::unchecked(sender),
+            self.base.hub.get_address(),
+            &eris::hub::ExecuteMsg::BondLsmShares {
+                receiver: None,
+            },
+            &[shares],
+        )
+    }
+
     pub fn hub_execute_sender(
         &self,
         router_ref: &mut App,
@@ -1,5 +1,6 @@
 pub mod base;
 mod custom_gov;
+pub mod lsm;
 use std::str::FromStr;
 
 use anyhow::{Error, Ok, Result};
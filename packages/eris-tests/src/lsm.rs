@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    coin, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Empty, Env,
+    MessageInfo, Response, StakingMsg, StdError, StdResult, SubMsg, WasmMsg,
+};
+use cw_multi_test::{Contract, ContractWrapper};
+use eris::hub::ExecuteMsg;
+use eris_staking_hub::error::ContractError;
+
+// cw-multi-test can't execute stargate messages, so the liquid staking module is replaced by a
+// stand-in contract. Tokenizing escrows the backing tokens in the stand-in, redeeming releases
+// them to the redeemer which delegates them right away.
+
+pub const REDEEM_TYPE_URL: &str = "/cosmos.staking.v1beta1.MsgRedeemTokensForShares";
+pub const BONDED_DENOM: &str = "uluna";
+
+thread_local! {
+    static LSM_MODULE: RefCell<Option<Addr>> = RefCell::new(None);
+}
+
+pub fn lsm_module_address() -> Addr {
+    LSM_MODULE.with(|module| module.borrow().clone()).expect("lsm module not instantiated")
+}
+
+#[cw_serde]
+pub enum LsmExecuteMsg {
+    /// Burns the attached shares and sends the backing tokens to the sender
+    Redeem {},
+}
+
+fn instantiate(_deps: DepsMut, env: Env, _info: MessageInfo, _msg: Empty) -> StdResult<Response> {
+    LSM_MODULE.with(|module| *module.borrow_mut() = Some(env.contract.address));
+    Ok(Response::new())
+}
+
+fn execute(
+    _deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: LsmExecuteMsg,
+) -> StdResult<Response> {
+    match msg {
+        LsmExecuteMsg::Redeem {} => {
+            let backing: u128 = info.funds.iter().map(|c| c.amount.u128()).sum();
+            if backing == 0 {
+                return Err(StdError::generic_err("no shares to redeem"));
+            }
+
+            Ok(Response::new()
+                .add_message(BankMsg::Burn {
+                    amount: info.funds,
+                })
+                .add_message(BankMsg::Send {
+                    to_address: info.sender.to_string(),
+                    amount: vec![coin(backing, BONDED_DENOM)],
+                }))
+        },
+    }
+}
+
+fn query(_deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+    Ok(Binary::default())
+}
+
+pub fn lsm_module_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new_with_empty(execute, instantiate, query))
+}
+
+/// Executes the hub and replaces the redemption of shares with the stand-in
+pub fn hub_execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    let funds = info.funds.clone();
+    let mut response = eris_staking_hub::contract::execute(deps, env, info, msg)?;
+
+    response.messages = response
+        .messages
+        .into_iter()
+        .flat_map(|sub_msg| match &sub_msg.msg {
+            CosmosMsg::Stargate {
+                type_url,
+                ..
+            } if type_url == REDEEM_TYPE_URL => redeem_msgs(&funds[0]),
+            _ => vec![sub_msg],
+        })
+        .collect();
+
+    Ok(response)
+}
+
+fn redeem_msgs(shares: &Coin) -> Vec<SubMsg> {
+    let validator = shares.denom.split('/').next().unwrap().to_string();
+
+    vec![
+        SubMsg::new(WasmMsg::Execute {
+            contract_addr: lsm_module_address().to_string(),
+            msg: to_binary(&LsmExecuteMsg::Redeem {}).unwrap(),
+            funds: vec![shares.clone()],
+        }),
+        SubMsg::new(StakingMsg::Delegate {
+            validator,
+            amount: coin(shares.amount.u128(), BONDED_DENOM),
+        }),
+    ]
+}
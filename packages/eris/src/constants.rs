@@ -1,2 +1,3 @@
 pub const DAY: u64 = 24 * 60 * 60;
 pub const WEEK: u64 = DAY * 7;
+pub const YEAR: u64 = DAY * 365;
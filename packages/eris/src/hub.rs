@@ -1,11 +1,16 @@
+use std::fmt;
+
+use astroport::asset::{Asset, AssetInfo};
 use cosmwasm_std::{
-    to_binary, Addr, Api, Coin, CosmosMsg, Decimal, Empty, QuerierWrapper, StdResult, Timestamp,
-    Uint128, VoteOption, WasmMsg,
+    to_binary, Addr, Api, Binary, Coin, CosmosMsg, Decimal, Empty, QuerierWrapper, StdError,
+    StdResult, Timestamp, Uint128, VoteOption, WasmMsg,
 };
 use cw20::Cw20ReceiveMsg;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::adapters::pair::Pair;
+use crate::adapters::router::{Router, RouterType};
 use crate::helper::addr_opt_validate;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -28,6 +33,67 @@ pub enum DelegationStrategy<T = String> {
         /// count of validators that should receive delegations
         validator_count: u8,
     },
+    /// validators receive the share of delegations defined by the owner
+    Defined {
+        /// share of each validator in basic points, summing up to 10000
+        shares_bps: Vec<(String, u16)>,
+    },
+    /// core validators receive a fixed share, the remainder is distributed by the gauges
+    Hybrid {
+        /// fixed share of each core validator in basic points, summing up to at most 10000
+        core_shares_bps: Vec<(String, u16)>,
+        /// gauges based on vAmp voting
+        amp_gauges: T,
+        /// gauges based on eris merit points
+        emp_gauges: Option<T>,
+        /// weight between amp and emp gauges between 0 and 1
+        amp_factor_bps: u16,
+        /// min amount of delegation needed, relative to the remainder
+        min_delegation_bps: u16,
+        /// max amount of delegation needed, relative to the remainder
+        max_delegation_bps: u16,
+        /// count of validators that should receive delegations from the remainder
+        validator_count: u8,
+    },
+}
+
+impl<T> DelegationStrategy<T> {
+    /// Returns the validators of the explicit shares, if the strategy defines any
+    pub fn defined_validators(&self) -> Vec<&String> {
+        match self {
+            DelegationStrategy::Defined {
+                shares_bps,
+            } => shares_bps.iter().map(|(validator, _)| validator).collect(),
+            DelegationStrategy::Hybrid {
+                core_shares_bps,
+                ..
+            } => core_shares_bps.iter().map(|(validator, _)| validator).collect(),
+            _ => vec![],
+        }
+    }
+}
+
+fn validate_shares_bps(shares_bps: &[(String, u16)], exact: bool) -> StdResult<()> {
+    let total: u32 = shares_bps.iter().map(|(_, bps)| *bps as u32).sum();
+    if exact && total != 10000 {
+        return Err(StdError::generic_err(format!(
+            "shares need to sum up to 10000 bps, got {0}",
+            total
+        )));
+    }
+    if total > 10000 {
+        return Err(StdError::generic_err(format!(
+            "shares can't sum up to more than 10000 bps, got {0}",
+            total
+        )));
+    }
+
+    for (i, (validator, _)) in shares_bps.iter().enumerate() {
+        if shares_bps[..i].iter().any(|(v, _)| v == validator) {
+            return Err(StdError::generic_err(format!("duplicated share of {0}", validator)));
+        }
+    }
+    Ok(())
 }
 
 impl DelegationStrategy<String> {
@@ -49,6 +115,34 @@ impl DelegationStrategy<String> {
                 validator_count,
                 max_delegation_bps,
             },
+            DelegationStrategy::Defined {
+                shares_bps,
+            } => {
+                validate_shares_bps(&shares_bps, true)?;
+                DelegationStrategy::Defined {
+                    shares_bps,
+                }
+            },
+            DelegationStrategy::Hybrid {
+                core_shares_bps,
+                amp_gauges,
+                emp_gauges,
+                amp_factor_bps,
+                min_delegation_bps,
+                max_delegation_bps,
+                validator_count,
+            } => {
+                validate_shares_bps(&core_shares_bps, false)?;
+                DelegationStrategy::Hybrid {
+                    core_shares_bps,
+                    amp_gauges: api.addr_validate(&amp_gauges)?,
+                    emp_gauges: addr_opt_validate(api, &emp_gauges)?,
+                    amp_factor_bps,
+                    min_delegation_bps,
+                    max_delegation_bps,
+                    validator_count,
+                }
+            },
         };
         Ok(result)
     }
@@ -81,6 +175,28 @@ pub struct InstantiateMsg {
     pub delegation_strategy: Option<DelegationStrategy>,
     /// Contract address that is allowed to vote
     pub vote_operator: Option<String>,
+    /// Creates the stake token as token factory denom `factory/{hub}/{subdenom}` instead of
+    /// instantiating a CW20 token. `cw20_code_id` is ignored in this case.
+    pub token_factory_subdenom: Option<String>,
+    /// Denom of the native token that is staked, defaults to `uluna`
+    pub denom: Option<String>,
+    /// Time a proposed config change has to wait until it can be applied, in seconds. Defaults to
+    /// the epoch period plus the unbond period, so that stakers can exit before.
+    pub config_timelock: Option<u64>,
+    /// Marketing info of the CW20 stake token, not supported for token factory stake tokens
+    pub stake_token_marketing: Option<StakeTokenMarketing>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct StakeTokenMarketing {
+    /// Project the stake token belongs to
+    pub project: Option<String>,
+    /// Description of the stake token
+    pub description: Option<String>,
+    /// Account allowed to update the marketing info and upload a logo, defaults to the owner
+    pub marketing: Option<String>,
+    /// Url of the stake token logo
+    pub logo_url: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -94,10 +210,40 @@ pub enum ExecuteMsg {
     },
     /// Donates specified amount of Luna to pool
     Donate {},
+    /// Bond tokenized delegation shares of a whitelisted validator, the shares are redeemed into a
+    /// delegation of the hub and stake tokens are minted at the current exchange rate
+    BondLsmShares {
+        receiver: Option<String>,
+    },
+    /// Submit an unbonding request with the attached stake token, only available if the stake
+    /// token is a token factory denom
+    QueueUnbond {
+        receiver: Option<String>,
+    },
+    /// Unbond the attached stake token immediately from the liquidity buffer, only available if
+    /// the stake token is a token factory denom
+    InstantUnbond {
+        receiver: Option<String>,
+    },
     /// Withdraw Luna that have finished unbonding in previous batches
     WithdrawUnbonded {
         receiver: Option<String>,
     },
+    /// Withdraw Luna that have finished unbonding to the given users, callable by anyone. Like
+    /// WithdrawUnbonded, it can't be paused, so that users can always exit with their funds.
+    WithdrawUnbondedFor {
+        users: Vec<String>,
+    },
+    /// Cancel the sender's unbonding request in the pending batch and return the stake tokens
+    CancelUnbond {
+        batch_id: u64,
+    },
+    /// Transfer all or part of the sender's unbonding request in a batch to another address
+    TransferUnbondRequest {
+        batch_id: u64,
+        recipient: String,
+        shares: Uint128,
+    },
     /// Add a validator to the whitelist; callable by the owner
     AddValidator {
         validator: String,
@@ -127,6 +273,13 @@ pub enum ExecuteMsg {
     Reconcile {},
     /// Submit the current pending batch of unbonding requests to be unbonded
     SubmitBatch {},
+    /// Sweep the dust of abandoned unbonding requests in finished batches to the protocol fee
+    /// contract and remove the batches that are fully withdrawn, callable by anyone. Requires a
+    /// dust cleanup config with a nonzero threshold and grace period
+    PruneBatches {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
     /// Vote on a proposal (only allowed by the vote_operator)
     Vote {
         proposal_id: u64,
@@ -137,6 +290,23 @@ pub enum ExecuteMsg {
         proposal_id: u64,
         votes: Vec<(Decimal, VoteOption)>,
     },
+    /// Vote on a proposal with the result of the prop gauges, once its voting period there has
+    /// ended and the quorum was reached. Callable by anyone.
+    VoteFromGauge {
+        proposal_id: u64,
+    },
+    /// Opt in or out of keeping a history of the sender's bond, unbond and withdraw events
+    TrackHistory {
+        enabled: bool,
+    },
+    /// Pause operations, e.g. during an incident (only allowed by the guardian or the owner)
+    Pause {
+        operations: Vec<PausableOperation>,
+    },
+    /// Unpause operations (only allowed by the guardian or the owner)
+    Unpause {
+        operations: Vec<PausableOperation>,
+    },
 
     /// Callbacks; can only be invoked by the contract itself
     Callback(CallbackMsg),
@@ -145,20 +315,67 @@ pub enum ExecuteMsg {
     UpdateConfig {
         /// Contract address where fees are sent
         protocol_fee_contract: Option<String>,
-        /// Fees that are being applied during reinvest of staking rewards
-        protocol_reward_fee: Option<Decimal>, // "1 is 100%, 0.05 is 5%"
         /// Specifies wether donations are allowed.
         allow_donations: Option<bool>,
-        /// Strategy how delegations should be handled
-        delegation_strategy: Option<DelegationStrategy>,
         /// Update the vote_operator
         vote_operator: Option<String>,
-
+        /// Update the liquidity buffer used for instant unbonding
+        liquidity_buffer: Option<LiquidityBufferConfig>,
+        /// Specifies wether validators are removed automatically when a slashing is detected. The
+        /// removal happens during reconciliation.
+        remove_slashed_validators: Option<bool>,
+        /// Adds or replaces the swap routes used to convert reward coins to Luna
+        insert_swap_routes: Option<Vec<RewardSwapRoute>>,
+        /// Removes the swap routes of the given reward denoms
+        delete_swap_routes: Option<Vec<String>>,
+        /// Update the rules validators need to fulfill to receive delegations, they are applied with
+        /// the next TuneDelegations
+        validator_checks: Option<ValidatorChecks>,
+        /// Update the guardian, who can pause and unpause operations. An empty string removes the
+        /// guardian.
+        guardian: Option<String>,
+        /// Splits the protocol fee across these receivers, an empty list sends the whole fee to
+        /// the protocol_fee_contract
+        fee_receivers: Option<Vec<FeeReceiver>>,
+        /// Maintenance that is carried out when users bond, unbond or withdraw
+        auto_maintenance: Option<AutoMaintenanceConfig>,
+        /// Reward paid to whoever calls an overdue maintenance action
+        keeper_reward: Option<KeeperRewardConfig>,
+        /// Prop gauges contract whose results can be used to vote on proposals
+        prop_gauges: Option<String>,
+        /// Update when dust of abandoned unbonding requests is swept
+        dust_cleanup: Option<DustCleanupConfig>,
+        /// Update whether deposits are split across the validators furthest below their target
+        split_bond: Option<SplitBondConfig>,
+        /// Disables instant unbonding, the liquidity buffer is delegated again
+        remove_liquidity_buffer: Option<bool>,
+        /// Only changeable through `ProposeConfigChange`, setting it is rejected
+        protocol_reward_fee: Option<Decimal>,
+        /// Only changeable through `ProposeConfigChange`, setting it is rejected
+        delegation_strategy: Option<DelegationStrategy>,
+        /// Only changeable through `ProposeConfigChange`, setting it is rejected
+        epoch_period: Option<u64>,
+        /// Only changeable through `ProposeConfigChange`, setting it is rejected
+        unbond_period: Option<u64>,
+    },
+    /// Proposes a change of parameters that affect stakers. It can be applied once the config
+    /// timelock has passed. A pending change has to be cancelled before proposing a new one.
+    ProposeConfigChange {
+        /// Fees that are being applied during reinvest of staking rewards
+        protocol_reward_fee: Option<Decimal>,
+        /// Strategy how delegations should be handled
+        delegation_strategy: Option<DelegationStrategy>,
         /// How often the unbonding queue is to be executed, in seconds
         epoch_period: Option<u64>,
         /// The staking module's unbonding time, in seconds
         unbond_period: Option<u64>,
+        /// Time a proposed config change has to wait until it can be applied, in seconds
+        config_timelock: Option<u64>,
     },
+    /// Applies the pending config change after its timelock has passed
+    ApplyConfigChange {},
+    /// Discards the pending config change
+    CancelConfigChange {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -169,19 +386,38 @@ pub enum ReceiveMsg {
     QueueUnbond {
         receiver: Option<String>,
     },
+    /// Unbond immediately by receiving Luna from the liquidity buffer, a fee is deducted from the
+    /// received amount and stays in the buffer.
+    InstantUnbond {
+        receiver: Option<String>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum CallbackMsg {
-    /// Swap Terra stablecoins held by the contract to Luna
-    // Swap {},
+    /// Swap reward coins held by the contract to Luna
+    Swap {},
+    /// Swap a single reward coin through its route. A failing swap leaves the coin unlocked.
+    SwapCoin {
+        coin: Coin,
+    },
     /// Following the swaps, stake the Luna acquired to the whitelisted validators
     Reinvest {},
+    /// Submit the batch, reconcile and harvest if overdue, the keeper receives the keeper tip
+    AutoMaintenance {
+        keeper: Addr,
+    },
 
     CheckReceivedCoin {
         snapshot: Coin,
     },
+    /// Following the redemption of tokenized shares, mint stake tokens for the redeemed amount
+    MintLsmShares {
+        receiver: Addr,
+        validator: String,
+        delegation_before: Uint128,
+    },
 }
 
 impl CallbackMsg {
@@ -237,6 +473,27 @@ pub enum QueryMsg {
         start_after: Option<u64>,
         limit: Option<u32>,
     },
+    /// Enumerate the daily exchange rate checkpoints, starting with the latest. Response: `ExchangeRatesResponse`
+    ExchangeRates {
+        // start after the provided timestamp in days
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Reward coins other than Luna that are held by the contract and their swap route. Response: `Vec<PendingRewardsResponseItem>`
+    PendingRewards {},
+    /// Enumerate all slashings that have been detected by the hub. Response: `Vec<SlashingEvent>`
+    SlashingEvents {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Open unbonding entries of each validator the contract undelegated from. Response: `UnbondingSlotsResponse`
+    UnbondingSlots {},
+    /// Enumerate the history of a user who opted in to it, starting with the latest. Response: `Vec<UserHistoryItem>`
+    UserHistory {
+        user: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -259,24 +516,89 @@ pub struct ConfigResponse {
 
     /// Defines how delegations are spread out
     pub delegation_strategy: DelegationStrategy<String>,
+
+    /// Rules validators need to fulfill to receive delegations, not set if unchecked
+    pub validator_checks: Option<ValidatorChecks>,
+
+    /// Account who can pause and unpause operations
+    pub guardian: Option<String>,
+    /// Operations that are currently paused
+    pub pause_flags: PauseFlags,
+
+    /// Receivers of the protocol fee, if empty the protocol_fee_contract receives all fees
+    pub fee_receivers: Vec<FeeReceiver>,
+
+    /// Maintenance that is carried out when users bond, unbond or withdraw, not set if never configured
+    pub auto_maintenance: Option<AutoMaintenanceConfig>,
+    /// Reward paid to whoever calls an overdue maintenance action, not set if never configured
+    pub keeper_reward: Option<KeeperRewardConfig>,
+    /// Prop gauges contract whose results can be used to vote on proposals, not set if disabled
+    pub prop_gauges: Option<String>,
+    /// When dust of abandoned unbonding requests is swept, not set if batches are never pruned
+    pub dust_cleanup: Option<DustCleanupConfig>,
+    /// How deposits are split across validators, not set if each deposit goes to a single validator
+    pub split_bond: Option<SplitBondConfig>,
+    /// Denom of the native token that is staked, hubs without this field stake `uluna`
+    #[serde(default = "default_denom")]
+    pub denom: String,
+    /// Time a proposed config change has to wait until it can be applied, in seconds
+    #[serde(default)]
+    pub config_timelock: u64,
+    /// Config change that has been proposed and is waiting for its timelock
+    pub pending_config_change: Option<PendingConfigChange>,
+}
+
+fn default_denom() -> String {
+    "uluna".to_string()
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct StateResponse {
     /// Total supply to the Stake token
     pub total_ustake: Uint128,
-    /// Total amount of uluna staked (bonded)
-    pub total_uluna: Uint128,
-    /// The exchange rate between ustake and uluna, in terms of uluna per ustake
+    /// Total amount of utoken staked (bonded)
+    #[serde(alias = "total_uluna")]
+    pub total_utoken: Uint128,
+    /// The exchange rate between ustake and utoken, in terms of utoken per ustake
     pub exchange_rate: Decimal,
     /// Staking rewards currently held by the contract that are ready to be reinvested
     pub unlocked_coins: Vec<Coin>,
-    // Amount of uluna currently unbonding
+    // Amount of utoken currently unbonding
     pub unbonding: Uint128,
-    // Amount of uluna currently available as balance of the contract
+    // Amount of utoken currently available as balance of the contract
     pub available: Uint128,
-    // Total amount of uluna within the contract (bonded + unbonding + available)
-    pub tvl_uluna: Uint128,
+    // Total amount of utoken within the contract (bonded + unbonding + available)
+    #[serde(alias = "tvl_uluna")]
+    pub tvl_utoken: Uint128,
+    // Amount of utoken kept liquid for instant unbonding (part of available)
+    pub liquidity_buffer: Uint128,
+    // Configuration of the liquidity buffer, not set if instant unbonding is disabled
+    pub liquidity_buffer_config: Option<LiquidityBufferConfig>,
+    // Number of unbonding entries kept in storage
+    #[serde(default)]
+    pub storage_metrics: StorageMetrics,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct StorageMetrics {
+    /// Batches that have been submitted for unbonding and are not fully withdrawn
+    pub previous_batches: u64,
+    /// Unbonding requests that are not withdrawn, including the pending batch
+    pub unbond_requests: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ExchangeRatesResponse {
+    pub exchange_rates: Vec<(u64, ExchangeHistory)>,
+    // APR between the oldest and the latest returned exchange rate, annualized. A falling exchange
+    // rate, e.g. after a slashing, results in an APR of 0, as `Decimal` can't be negative.
+    pub apr: Option<Decimal>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ExchangeHistory {
+    pub exchange_rate: Decimal,
+    pub time_s: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -340,6 +662,384 @@ pub struct FeeConfig {
     pub protocol_reward_fee: Decimal, // "1 is 100%, 0.05 is 5%"
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LiquidityBufferConfig {
+    /// Amount of utoken that should be kept liquid for instant unbonding
+    pub target: Uint128,
+    /// Fee that is deducted from instant unbondings, it stays in the buffer
+    pub instant_unbond_fee: Decimal, // "1 is 100%, 0.05 is 5%"
+    /// Max share of each bond deposit that is used to refill the buffer
+    pub refill_bond_share: Decimal,
+    /// Max share of the reinvested rewards that is used to refill the buffer
+    pub refill_reinvest_share: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AutoMaintenanceConfig {
+    /// Whether bonding, unbonding and withdrawing carry out the overdue maintenance
+    pub enabled: bool,
+    /// Gas available to the maintenance, if it runs out the maintenance is skipped
+    pub gas_limit: u64,
+    /// Minimum time between two automatic harvests, in seconds
+    pub harvest_interval: u64,
+    /// Amount of utoken paid from the harvested rewards to the user triggering the harvest
+    pub keeper_tip: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeeperReward {
+    /// Share of the rewards harvested after the action
+    Share(Decimal),
+    /// Fixed amount of utoken. All keepers of a harvest together receive at most 10% of the
+    /// harvested rewards
+    Fixed(Uint128),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct KeeperRewardConfig {
+    /// Reward for each rewarded action, paid from the next harvested rewards. Only overdue
+    /// harvests, batch submissions and reconciliations are rewarded
+    pub reward: KeeperReward,
+    /// Minimum time between two rewarded calls of the same action, in seconds
+    pub cooldown: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PendingConfigChange {
+    /// Fees that are being applied during reinvest of staking rewards
+    pub protocol_reward_fee: Option<Decimal>,
+    /// Strategy how delegations should be handled
+    pub delegation_strategy: Option<DelegationStrategy>,
+    /// How often the unbonding queue is to be executed, in seconds
+    pub epoch_period: Option<u64>,
+    /// The staking module's unbonding time, in seconds
+    pub unbond_period: Option<u64>,
+    /// Time a proposed config change has to wait until it can be applied, in seconds
+    pub config_timelock: Option<u64>,
+    /// Block time from which the change can be applied, in seconds
+    pub apply_after: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct DustCleanupConfig {
+    /// Unbonding requests worth at most this amount of utoken are swept
+    pub threshold: Uint128,
+    /// Time after a batch finished unbonding until its requests can be swept, in seconds
+    pub grace_period: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SplitBondConfig {
+    /// Whether deposits are split across validators in proportion to their deficit against the
+    /// delegation goal, instead of going to the validator with the smallest delegation
+    pub enabled: bool,
+    /// Maximum number of validators a single deposit is split across
+    pub max_delegations: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PausableOperation {
+    /// Bonding, including donations and tokenized shares
+    Bond,
+    /// Queueing, cancelling and transferring unbonding requests, as well as instant unbonding
+    QueueUnbond,
+    Harvest,
+    Rebalance,
+    SubmitBatch,
+    PruneBatches,
+}
+
+impl fmt::Display for PausableOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PausableOperation::Bond => write!(f, "bond"),
+            PausableOperation::QueueUnbond => write!(f, "queue_unbond"),
+            PausableOperation::Harvest => write!(f, "harvest"),
+            PausableOperation::Rebalance => write!(f, "rebalance"),
+            PausableOperation::SubmitBatch => write!(f, "submit_batch"),
+            PausableOperation::PruneBatches => write!(f, "prune_batches"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub struct PauseFlags {
+    pub bond: bool,
+    pub queue_unbond: bool,
+    pub harvest: bool,
+    pub rebalance: bool,
+    #[serde(default)]
+    pub submit_batch: bool,
+    #[serde(default)]
+    pub prune_batches: bool,
+}
+
+impl PauseFlags {
+    pub fn is_paused(&self, operation: &PausableOperation) -> bool {
+        match operation {
+            PausableOperation::Bond => self.bond,
+            PausableOperation::QueueUnbond => self.queue_unbond,
+            PausableOperation::Harvest => self.harvest,
+            PausableOperation::Rebalance => self.rebalance,
+            PausableOperation::SubmitBatch => self.submit_batch,
+            PausableOperation::PruneBatches => self.prune_batches,
+        }
+    }
+
+    pub fn set(&mut self, operation: &PausableOperation, paused: bool) {
+        match operation {
+            PausableOperation::Bond => self.bond = paused,
+            PausableOperation::QueueUnbond => self.queue_unbond = paused,
+            PausableOperation::Harvest => self.harvest = paused,
+            PausableOperation::Rebalance => self.rebalance = paused,
+            PausableOperation::SubmitBatch => self.submit_batch = paused,
+            PausableOperation::PruneBatches => self.prune_batches = paused,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ValidatorChecks {
+    /// Maximum commission a validator is allowed to charge
+    pub max_commission: Decimal, // "1 is 100%, 0.05 is 5%"
+    /// Minimum amount of utoken the validator operator needs to self-delegate
+    pub min_self_delegation: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SlashingEvent {
+    /// ID of this slashing event
+    pub id: u64,
+    /// Validator whose delegation was less than expected
+    pub validator: String,
+    /// Block time when the slashing was detected, in seconds
+    pub time: u64,
+    /// Amount of utoken the contract expected to be delegated to the validator
+    #[serde(alias = "uluna_expected")]
+    pub utoken_expected: Uint128,
+    /// Amount of utoken missing from the delegation
+    #[serde(alias = "uluna_slashed")]
+    pub utoken_slashed: Uint128,
+    /// Whether the validator has been removed from the validator set because of the slashing
+    pub removed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserHistoryAction {
+    Bond,
+    Unbond,
+    /// The unbonding request was cancelled and the stake tokens were returned
+    CancelUnbond,
+    Withdraw,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct UserHistoryItem {
+    /// ID of this entry in the history of the user
+    pub id: u64,
+    pub action: UserHistoryAction,
+    /// Amount of utoken bonded, expected from the unbonding, no longer expected after cancelling
+    /// it, or withdrawn
+    #[serde(alias = "uluna")]
+    pub utoken: Uint128,
+    /// Amount of ustake minted, queued for unbonding, returned by the cancellation, or redeemed by
+    /// the withdrawal
+    pub ustake: Uint128,
+    /// The exchange rate at the time, in terms of utoken per ustake. For withdrawals, it is the
+    /// rate realized by the withdrawn batches.
+    pub exchange_rate: Decimal,
+    /// Block time of the event, in seconds
+    pub time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct UnbondingSlotsResponse {
+    /// Maximum number of unbonding entries the staking module allows per validator
+    pub max_entries: u32,
+    /// Slot usage of the whitelisted validators and of the validators with open entries
+    pub validators: Vec<UnbondingSlotsResponseItem>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct UnbondingSlotsResponseItem {
+    pub validator: String,
+    /// Number of open unbonding entries
+    pub used: u32,
+    /// Completion times of the open unbonding entries, in seconds
+    pub completion_times: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapRouteType<T = String> {
+    /// Swap through a router, the route has to start with the reward and end with Luna
+    Path {
+        router: T,
+        router_type: RouterType,
+        route: Vec<AssetInfo>,
+    },
+    /// Swap through a single pair
+    Pair {
+        pair_contract: T,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct RewardSwapRoute<T = String> {
+    /// Denom of the reward coin that is swapped
+    pub denom: String,
+    /// Contracts used for the swap
+    pub route_type: SwapRouteType<T>,
+    /// Max spread accepted when swapping
+    pub max_spread: Decimal,
+}
+
+impl RewardSwapRoute<String> {
+    pub fn validate(self, api: &dyn Api, ask_denom: &str) -> StdResult<RewardSwapRoute<Addr>> {
+        if self.denom == ask_denom {
+            return Err(StdError::generic_err(format!("can't swap {} to itself", ask_denom)));
+        }
+
+        let route_type = match self.route_type {
+            SwapRouteType::Path {
+                router,
+                router_type,
+                route,
+            } => {
+                let offer = AssetInfo::NativeToken {
+                    denom: self.denom.clone(),
+                };
+                let ask = AssetInfo::NativeToken {
+                    denom: ask_denom.to_string(),
+                };
+                if route.len() < 2 || route[0] != offer || route[route.len() - 1] != ask {
+                    return Err(StdError::generic_err(format!(
+                        "route for {} has to start with {} and end with {}",
+                        self.denom, self.denom, ask_denom
+                    )));
+                }
+                SwapRouteType::Path {
+                    router: api.addr_validate(&router)?,
+                    router_type,
+                    route,
+                }
+            },
+            SwapRouteType::Pair {
+                pair_contract,
+            } => SwapRouteType::Pair {
+                pair_contract: api.addr_validate(&pair_contract)?,
+            },
+        };
+
+        Ok(RewardSwapRoute {
+            denom: self.denom,
+            route_type,
+            max_spread: self.max_spread,
+        })
+    }
+}
+
+impl RewardSwapRoute<Addr> {
+    pub fn swap_msg(&self, amount: Uint128) -> StdResult<CosmosMsg> {
+        let offer_asset = Asset {
+            info: AssetInfo::NativeToken {
+                denom: self.denom.clone(),
+            },
+            amount,
+        };
+
+        match &self.route_type {
+            SwapRouteType::Path {
+                router,
+                router_type,
+                route,
+            } => Router(router.clone()).execute_swap_operations_msg(
+                offer_asset,
+                router_type.create_swap_operations(route)?,
+                None,
+                None,
+                Some(self.max_spread),
+            ),
+            SwapRouteType::Pair {
+                pair_contract,
+            } => Pair(pair_contract.clone()).swap_msg(
+                &offer_asset,
+                None,
+                Some(self.max_spread),
+                None,
+            ),
+        }
+    }
+
+    pub fn into_unchecked(self) -> RewardSwapRoute<String> {
+        RewardSwapRoute {
+            denom: self.denom,
+            route_type: match self.route_type {
+                SwapRouteType::Path {
+                    router,
+                    router_type,
+                    route,
+                } => SwapRouteType::Path {
+                    router: router.to_string(),
+                    router_type,
+                    route,
+                },
+                SwapRouteType::Pair {
+                    pair_contract,
+                } => SwapRouteType::Pair {
+                    pair_contract: pair_contract.to_string(),
+                },
+            },
+            max_spread: self.max_spread,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct FeeReceiver<T = String> {
+    /// Address receiving its share of the protocol fee
+    pub addr: T,
+    /// Share of the protocol fee, relative to the sum of all weights
+    pub weight: u64,
+    /// Optional message executed on the receiver with the fee attached
+    pub msg: Option<Binary>,
+}
+
+impl FeeReceiver<String> {
+    pub fn validate(self, api: &dyn Api) -> StdResult<FeeReceiver<Addr>> {
+        if self.weight == 0 {
+            return Err(StdError::generic_err(format!("weight of {} can't be zero", self.addr)));
+        }
+
+        Ok(FeeReceiver {
+            addr: api.addr_validate(&self.addr)?,
+            weight: self.weight,
+            msg: self.msg,
+        })
+    }
+}
+
+impl FeeReceiver<Addr> {
+    pub fn into_unchecked(self) -> FeeReceiver<String> {
+        FeeReceiver {
+            addr: self.addr.to_string(),
+            weight: self.weight,
+            msg: self.msg,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PendingRewardsResponseItem {
+    /// Reward coin held by the contract that has not been swapped yet
+    pub reward: Coin,
+    /// Route used to swap the reward to Luna during the next harvest, if configured
+    pub swap_route: Option<RewardSwapRoute>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Batch {
     /// ID of this batch
@@ -348,8 +1048,9 @@ pub struct Batch {
     pub reconciled: bool,
     /// Total amount of shares remaining this batch. Each `ustake` burned = 1 share
     pub total_shares: Uint128,
-    /// Amount of `uluna` in this batch that have not been claimed
-    pub uluna_unclaimed: Uint128,
+    /// Amount of `utoken` in this batch that have not been claimed
+    #[serde(alias = "uluna_unclaimed")]
+    pub utoken_unclaimed: Uint128,
     /// Estimated time when this batch will finish unbonding
     pub est_unbond_end_time: u64,
 }
@@ -424,3 +1125,32 @@ pub fn get_hub_validators(
     let config: ConfigResponse = querier.query_wasm_smart(hub_addr.into(), &QueryMsg::Config {})?;
     Ok(config.validators)
 }
+
+#[cfg(test)]
+mod tests {
+    use cosmwasm_std::{from_slice, to_vec};
+
+    use super::*;
+
+    #[test]
+    fn serializing_generic_field_names() {
+        let batch = Batch {
+            id: 1,
+            reconciled: true,
+            total_shares: Uint128::new(100),
+            utoken_unclaimed: Uint128::new(101),
+            est_unbond_end_time: 10000,
+        };
+
+        let json = to_vec(&batch).unwrap();
+        assert_eq!(
+            json,
+            br#"{"id":1,"reconciled":true,"total_shares":"100","utoken_unclaimed":"101","est_unbond_end_time":10000}"#
+        );
+        assert_eq!(from_slice::<Batch>(&json).unwrap(), batch);
+
+        // batches stored before the rename are still readable
+        let legacy = br#"{"id":1,"reconciled":true,"total_shares":"100","uluna_unclaimed":"101","est_unbond_end_time":10000}"#;
+        assert_eq!(from_slice::<Batch>(legacy).unwrap(), batch);
+    }
+}